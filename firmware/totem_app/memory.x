@@ -1,5 +1,6 @@
 MEMORY
 {
-    FLASH : ORIGIN = 0x08000000, LENGTH = 1M
+    /* The last 4K are reserved for the settings. */
+    FLASH : ORIGIN = 0x08000000, LENGTH = 1020K
     RAM : ORIGIN = 0x20000000, LENGTH = 96K
 }
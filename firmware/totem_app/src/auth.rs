@@ -0,0 +1,225 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Authentication of ERCP Basic sessions.
+
+use heapless::Vec;
+use rand::{RngCore as _, SeedableRng as _};
+use rand_chacha::ChaCha20Rng;
+use totem_protocol::auth::{self, NONCE_LEN, SECRET_MAX_LEN};
+
+/// The shared secret provisioned at build time, if any.
+///
+/// It is read from the `TOTEM_AUTH_SECRET` environment variable when building
+/// the firmware. If the variable is unset, the firmware is built without a
+/// secret and authentication stays disabled until a secret is set over ERCP.
+const BUILD_SECRET: Option<&str> = option_env!("TOTEM_AUTH_SECRET");
+
+/// The authentication state of the ERCP Basic session.
+pub struct Auth {
+    /// The shared secret, if authentication is enabled.
+    secret: Option<Vec<u8, SECRET_MAX_LEN>>,
+    /// The nonce of the pending challenge.
+    nonce: Option<[u8; NONCE_LEN]>,
+    /// Whether the host has authenticated.
+    authenticated: bool,
+    /// The random number generator for nonces.
+    rng: ChaCha20Rng,
+}
+
+/// An error that can occur when setting the secret.
+#[derive(Debug)]
+pub struct SecretTooLong;
+
+impl Auth {
+    /// Creates a new authentication state.
+    ///
+    /// The secret is the one provisioned at build time through the
+    /// `TOTEM_AUTH_SECRET` environment variable, if any. `seed` is used to
+    /// seed the generator for nonces, and must be unpredictable.
+    pub fn new(seed: [u8; 32]) -> Self {
+        let secret = BUILD_SECRET
+            .and_then(|secret| Vec::from_slice(secret.as_bytes()).ok())
+            .filter(|secret| !secret.is_empty());
+
+        if BUILD_SECRET.is_some_and(|secret| secret.len() > SECRET_MAX_LEN) {
+            crate::warn!("TOTEM_AUTH_SECRET is too long, ignoring it.");
+        }
+
+        Self {
+            secret,
+            nonce: None,
+            authenticated: false,
+            rng: ChaCha20Rng::from_seed(seed),
+        }
+    }
+
+    /// Returns whether commands changing the state are allowed.
+    ///
+    /// This is the case either if authentication is disabled or if the host
+    /// has authenticated.
+    pub fn is_authorised(&self) -> bool {
+        self.secret.is_none() || self.authenticated
+    }
+
+    /// Starts a new challenge, returning its nonce.
+    pub fn challenge(&mut self) -> [u8; NONCE_LEN] {
+        let mut nonce = [0; NONCE_LEN];
+        self.rng.fill_bytes(&mut nonce);
+        self.nonce = Some(nonce);
+        nonce
+    }
+
+    /// Checks the response to the pending challenge.
+    ///
+    /// The pending challenge is consumed in any case, and the session is closed
+    /// if the response is invalid.
+    pub fn respond(&mut self, response: &[u8]) -> bool {
+        self.authenticated = match (&self.secret, self.nonce.take()) {
+            (Some(secret), Some(nonce)) => {
+                auth::verify(secret, &nonce, response)
+            }
+            _ => false,
+        };
+
+        self.authenticated
+    }
+
+    /// Closes the session.
+    pub fn logout(&mut self) {
+        self.authenticated = false;
+        self.nonce = None;
+    }
+
+    /// Sets the shared secret.
+    ///
+    /// An empty secret disables authentication. The current session is closed
+    /// in any case.
+    pub fn set_secret(&mut self, secret: &[u8]) -> Result<(), SecretTooLong> {
+        let secret = Vec::from_slice(secret).map_err(|_| SecretTooLong)?;
+        self.secret = Some(secret).filter(|secret| !secret.is_empty());
+        self.logout();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use totem_protocol::auth::response;
+
+    use super::*;
+
+    /// The secret used in the tests.
+    const SECRET: &[u8] = b"totem";
+
+    /// Returns an authentication state with the test secret.
+    fn auth() -> Auth {
+        let mut auth = Auth::new([42; 32]);
+        auth.set_secret(SECRET).unwrap();
+        auth
+    }
+
+    #[test]
+    fn challenges_are_fresh_and_seeded() {
+        let mut auth = auth();
+        let first = auth.challenge();
+        let second = auth.challenge();
+        assert_ne!(first, second);
+
+        let mut other = Auth::new([42; 32]);
+        assert_eq!(other.challenge(), first);
+
+        let mut other = Auth::new([43; 32]);
+        assert_ne!(other.challenge(), first);
+    }
+
+    #[test]
+    fn a_valid_response_opens_the_session() {
+        let mut auth = auth();
+        assert!(!auth.is_authorised());
+
+        let nonce = auth.challenge();
+        assert!(auth.respond(&response(SECRET, &nonce)));
+        assert!(auth.is_authorised());
+
+        auth.logout();
+        assert!(!auth.is_authorised());
+    }
+
+    #[test]
+    fn a_wrong_response_is_rejected() {
+        let mut auth = auth();
+
+        let nonce = auth.challenge();
+        assert!(!auth.respond(&response(b"wrong", &nonce)));
+        assert!(!auth.is_authorised());
+
+        let nonce = auth.challenge();
+        let mut mac = response(SECRET, &nonce);
+        mac[0] ^= 0x01;
+        assert!(!auth.respond(&mac));
+        assert!(!auth.respond(&mac[..16]));
+        assert!(!auth.is_authorised());
+    }
+
+    #[test]
+    fn a_response_is_rejected_without_a_challenge() {
+        let mut auth = auth();
+        assert!(!auth.respond(&response(SECRET, &[0; NONCE_LEN])));
+        assert!(!auth.is_authorised());
+    }
+
+    #[test]
+    fn an_old_challenge_cannot_be_replayed() {
+        let mut auth = auth();
+
+        let nonce = auth.challenge();
+        let mac = response(SECRET, &nonce);
+        assert!(auth.respond(&mac));
+        auth.logout();
+
+        // The nonce has been consumed by the first response.
+        assert!(!auth.respond(&mac));
+
+        // A response to a superseded challenge is not valid either.
+        let old = auth.challenge();
+        auth.challenge();
+        assert!(!auth.respond(&response(SECRET, &old)));
+        assert!(!auth.is_authorised());
+    }
+
+    #[test]
+    fn an_invalid_response_closes_the_session() {
+        let mut auth = auth();
+
+        let nonce = auth.challenge();
+        assert!(auth.respond(&response(SECRET, &nonce)));
+
+        auth.challenge();
+        assert!(!auth.respond(&[0; 32]));
+        assert!(!auth.is_authorised());
+    }
+
+    #[test]
+    fn an_empty_secret_disables_authentication() {
+        let mut auth = auth();
+        assert!(!auth.is_authorised());
+
+        auth.set_secret(&[]).unwrap();
+        assert!(auth.is_authorised());
+
+        assert!(auth.set_secret(&[0; SECRET_MAX_LEN + 1]).is_err());
+    }
+}
@@ -25,9 +25,18 @@ use led_effects::{
 };
 use rand::distributions::Uniform;
 use smart_leds::RGB8;
-use totem_board::constants::{LEDS_PER_HALF_STRIP, LEDS_PER_STRIP, NUM_LEDS};
+use totem_board::constants::LED_LAYOUT;
 use totem_ui::state::Temperature;
 
+/// The total number of LEDs.
+pub const NUM_LEDS: usize = LED_LAYOUT.num_leds();
+
+/// The number of LEDs per strip.
+const LEDS_PER_STRIP: usize = LED_LAYOUT.leds_per_strip;
+
+/// The number of LEDs per half strip, including the middle one of odd strips.
+const LEDS_PER_HALF_STRIP: usize = LEDS_PER_STRIP.div_ceil(2);
+
 type SymmetricRainbow = Duplicate<
     Symmetry<Rainbow<LEDS_PER_HALF_STRIP>, LEDS_PER_STRIP, LEDS_PER_HALF_STRIP>,
     NUM_LEDS,
@@ -35,6 +44,8 @@ type SymmetricRainbow = Duplicate<
 >;
 
 /// A Totem chaser.
+///
+/// Chasers render frames for the layout of the board profile.
 pub enum Chaser {
     /// No chaser.
     None,
@@ -110,7 +121,7 @@ impl led_effects::sequence::Sequence<NUM_LEDS> for Sequence {
             Config::Rainbow(config) => {
                 Self::SymmetricRainbow(Duplicate::new(DuplicateConfig {
                     config,
-                    duplicates: 8,
+                    duplicates: LED_LAYOUT.strips,
                 }))
             }
         }
@@ -0,0 +1,144 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Cue layer of the LED strip.
+//!
+//! The cue layer sits between the chaser and the LED strip: while a cue is
+//! running, each frame of the chaser is blended with the overlay of the cue.
+
+use smart_leds::{
+    colors::{BLACK, WHITE},
+    RGB8,
+};
+use totem_protocol::cue::{Color, Cue, CueEffect};
+
+/// The cue fired by a tap on B2.
+pub const TAP_CUE: Cue = Cue {
+    effect: CueEffect::Flash,
+    duration_ms: 300,
+};
+
+/// The cue fired by a double tap on B2.
+pub const DOUBLE_TAP_CUE: Cue = Cue {
+    effect: CueEffect::Burst(Color {
+        r: 255,
+        g: 0,
+        b: 64,
+    }),
+    duration_ms: 800,
+};
+
+/// The cue fired by holding B2.
+pub const HOLD_CUE: Cue = Cue {
+    effect: CueEffect::Blackout,
+    duration_ms: 1000,
+};
+
+/// The cue confirming that a preset has been saved with B2.
+pub const PRESET_SAVED_CUE: Cue = Cue {
+    effect: CueEffect::Burst(Color { r: 0, g: 255, b: 0 }),
+    duration_ms: 500,
+};
+
+/// The cue layer.
+#[derive(Default)]
+pub struct CueLayer {
+    /// The running cue, with the time when it has been fired.
+    cue: Option<(Cue, u32)>,
+}
+
+/// The overlay of a cue for a given frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Overlay {
+    /// The colour of the overlay.
+    color: RGB8,
+    /// The opacity of the overlay, from 0 (transparent) to 255 (opaque).
+    alpha: u8,
+}
+
+impl CueLayer {
+    /// Creates an empty cue layer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fires a cue at `now_ms`, replacing the running one if any.
+    pub fn fire(&mut self, cue: Cue, now_ms: u32) {
+        self.cue = Some((cue, now_ms));
+    }
+
+    /// Returns whether a cue is running.
+    pub fn is_active(&self) -> bool {
+        self.cue.is_some()
+    }
+
+    /// Returns the overlay at `now_ms`, releasing the cue once it has ended.
+    pub fn overlay(&mut self, now_ms: u32) -> Overlay {
+        let (cue, fired_ms) = match self.cue {
+            Some(cue) => cue,
+            None => return Overlay::TRANSPARENT,
+        };
+
+        let elapsed = now_ms.wrapping_sub(fired_ms);
+        let duration = u32::from(cue.duration_ms);
+
+        if elapsed >= duration {
+            self.cue = None;
+            return Overlay::TRANSPARENT;
+        }
+
+        // Flashes and bursts fade out linearly over their duration.
+        let fading = (255 * (duration - elapsed) / duration) as u8;
+
+        match cue.effect {
+            CueEffect::Flash => Overlay {
+                color: WHITE,
+                alpha: fading,
+            },
+            CueEffect::Burst(Color { r, g, b }) => Overlay {
+                color: RGB8::new(r, g, b),
+                alpha: fading,
+            },
+            CueEffect::Blackout => Overlay {
+                color: BLACK,
+                alpha: u8::MAX,
+            },
+        }
+    }
+}
+
+impl Overlay {
+    /// A fully transparent overlay.
+    pub const TRANSPARENT: Self = Self {
+        color: BLACK,
+        alpha: 0,
+    };
+
+    /// Blends the overlay over a pixel.
+    pub fn blend(&self, pixel: RGB8) -> RGB8 {
+        RGB8::new(
+            self.blend_channel(pixel.r, self.color.r),
+            self.blend_channel(pixel.g, self.color.g),
+            self.blend_channel(pixel.b, self.color.b),
+        )
+    }
+
+    fn blend_channel(&self, below: u8, above: u8) -> u8 {
+        let alpha = u16::from(self.alpha);
+        let value =
+            (u16::from(below) * (255 - alpha) + u16::from(above) * alpha) / 255;
+        value as u8
+    }
+}
@@ -14,39 +14,552 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
 //! ERCP Basic integration for the Totem application firmware.
+//!
+//! The router and processing path are generic over the transport: any
+//! [`Adapter`] can carry an [`ErcpLink`], be it a UART, a radio module or a
+//! loopback. Each link owns its router, hence its own authentication session.
+//!
+//! Some data is costly to gather, like the readings of the inputs which take
+//! hundreds of ADC samples. Instead of gathering it for every frame, the router
+//! leaves a [`Query`] in the [`ErcpContext`] for the requests needing it: the
+//! caller of [`process`] then gathers the data and sends the reply with
+//! [`answer`]. Changes to the show are deferred the same way, as the show
+//! player is shared with the show task.
 
-use ercp_basic::Router;
+use ercp_basic::{
+    ack,
+    adapter::Adapter,
+    command::{nack_reason, PING, PROTOCOL, VERSION},
+    nack, Command, ErcpBasic, Router, Timer,
+};
+use heapless::Vec;
+use totem_protocol::{
+    auth::SECRET_MAX_LEN,
+    code::{
+        AUTH_CHALLENGE, AUTH_LOGOUT, AUTH_RESPONSE, IDENTITY, LOG_DRAIN_REPLY,
+        PROTOCOL_VERSION,
+    },
+    cue::Cue,
+    identity::{DeviceId, Identity, Name},
+    nack_reason::{
+        BUSY, NOT_FOUND, OUT_OF_RANGE, TRAILING_BYTES, TRUNCATED,
+        UNAUTHENTICATED, UNKNOWN_VARIANT,
+    },
+    preset::PRESET_COUNT,
+    DecodeError, Request, Response,
+};
+use totem_ui::state::{UIState, UIStatePatch};
 
 #[cfg(feature = "ui_graphical")]
-use totem_ui::state::UIState;
+use crate::show::{ShowControl, ShowError};
+use crate::{auth::Auth, reset::ResetInfo};
+
+#[cfg(feature = "ui_physical")]
+use totem_protocol::nack_reason::UNSUPPORTED;
+#[cfg(feature = "ui_graphical")]
+use totem_protocol::{
+    batch,
+    host_link::HostWatchdogConfig,
+    nack_reason::{INVALID_SHOW, NO_SPACE},
+};
+
+/// The size of the buffer for reply values.
+const REPLY_BUFFER_SIZE: usize = 128;
+
+/// An ERCP Basic link to the Totem router, over any transport.
+pub type ErcpLink<A, T> = ErcpBasic<A, T, TotemRouter>;
+
+/// The outcome of receiving data on an [`ErcpLink`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reception {
+    /// The frame is not complete yet.
+    Incomplete,
+    /// A complete frame has been received and is ready to be processed.
+    Complete,
+    /// An error has occurred while receiving data.
+    Error,
+}
+
+/// A query for data gathered on demand, left by the router in the context.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Query {
+    /// The telemetry, for a Telemetry request.
+    Telemetry,
+    /// The status of the link with the host, for a Host_Link_Status request.
+    HostLinkStatus,
+    /// The readings of the inputs, for an Inputs request.
+    Inputs,
+    /// The saved presets, for a Presets request.
+    Presets,
+    /// The preset in a slot, for a Preset_Recall request.
+    PresetRecall(usize),
+    /// The report of the power-on self-test, for a Self_Test request.
+    SelfTest,
+    /// The error counters, for an Error_Counters request.
+    ErrorCounters,
+    /// The timing of the LED frames, for a Frame_Timing request.
+    FrameTiming,
+    /// The playback status of the show, for a Show_Status request.
+    ShowStatus,
+}
+
+/// The answer to a [`Query`].
+#[derive(Debug)]
+pub enum Answer<'a> {
+    /// The response to send back.
+    Response(Response<'a>),
+    /// The preset to recall, if the slot is not empty.
+    Preset(Option<UIState>),
+    /// The outcome of a change to the show, with the UI state to apply.
+    #[cfg(feature = "ui_graphical")]
+    Show(Result<UIStatePatch, ShowError>),
+}
 
 /// The context for the ERCP Basic router.
 #[derive(Default)]
 pub struct ErcpContext {
+    /// The optional UI state update.
+    pub ui_state_patch: Option<UIStatePatch>,
+    /// Whether the UI is lagging behind the updates already received.
+    pub ui_busy: bool,
+    #[cfg(feature = "ui_graphical")]
+    /// The optional new configuration of the host link watchdog.
+    pub host_watchdog_config: Option<HostWatchdogConfig>,
+    /// The optional cue to fire.
+    pub cue: Option<Cue>,
+    /// Whether a valid command has been received from the host.
+    pub host_seen: bool,
     #[cfg(feature = "ui_graphical")]
-    /// The optional graphical UI state update.
-    pub ui_state_update: Option<UIState>,
+    /// The optional change to the show, to apply before sending the reply
+    /// with [`answer`].
+    pub show_control: Option<ShowControl>,
+    /// The optional query for data to answer the request.
+    pub query: Option<Query>,
+    /// The optional preset slot where to save the current UI state.
+    pub preset_save: Option<usize>,
+    /// The optional new name to save.
+    pub name_change: Option<Name>,
+    /// The optional new shared secret to save.
+    pub secret_change: Option<Vec<u8, SECRET_MAX_LEN>>,
+    /// The time elapsed since boot, in milliseconds.
+    pub now_ms: u32,
 }
 
 /// The ERCP Basic router for Totem.
-pub struct TotemRouter;
+pub struct TotemRouter {
+    /// The authentication state.
+    auth: Auth,
+    /// The unique ID of the Totem.
+    device_id: DeviceId,
+    /// The name of the Totem.
+    name: Name,
+    /// The information about the last reset.
+    reset: ResetInfo,
+    /// The buffer for reply values.
+    reply_buffer: [u8; REPLY_BUFFER_SIZE],
+}
+
+impl TotemRouter {
+    /// Creates a new router.
+    ///
+    /// `seed` is used to generate authentication challenges, and must be
+    /// unpredictable. `secret` is the shared secret saved at runtime, which
+    /// overrides the one provisioned at build time. `device_id` and `name`
+    /// identify the Totem to the host, and `reset` is reported by the
+    /// Reset_Report command.
+    pub fn new(
+        seed: [u8; 32],
+        secret: Option<&[u8]>,
+        device_id: DeviceId,
+        name: Name,
+        reset: ResetInfo,
+    ) -> Self {
+        let mut auth = Auth::new(seed);
+        if let Some(secret) = secret {
+            auth.set_secret(secret).ok();
+        }
+
+        Self {
+            auth,
+            device_id,
+            name,
+            reset,
+            reply_buffer: [0; REPLY_BUFFER_SIZE],
+        }
+    }
+
+    /// Handles a decoded request.
+    fn handle(
+        &mut self,
+        request: Request,
+        ctx: &mut ErcpContext,
+    ) -> Option<Command> {
+        match request {
+            Request::Telemetry => query(Query::Telemetry, ctx),
+            Request::HostLinkStatus => query(Query::HostLinkStatus, ctx),
+            Request::LogDrain => self.log_drain(),
+            Request::Inputs => query(Query::Inputs, ctx),
+            Request::ErrorCounters => query(Query::ErrorCounters, ctx),
+            Request::FrameTiming => query(Query::FrameTiming, ctx),
+            Request::ProtocolVersion => {
+                self.reply(Response::ProtocolVersion(totem_protocol::VERSION))
+            }
+
+            Request::Cue(cue) => {
+                if cue.is_valid() {
+                    ctx.cue = Some(cue);
+                    Some(ack!())
+                } else {
+                    Some(nack!(OUT_OF_RANGE))
+                }
+            }
+
+            Request::PresetSave(slot) => {
+                if (slot as usize) < PRESET_COUNT {
+                    ctx.preset_save = Some(slot as usize);
+                    Some(ack!())
+                } else {
+                    Some(nack!(OUT_OF_RANGE))
+                }
+            }
+
+            // Recalling a preset is a UI update, made once the preset is known.
+            Request::PresetRecall(slot) => {
+                if (slot as usize) < PRESET_COUNT {
+                    query(Query::PresetRecall(slot as usize), ctx)
+                } else {
+                    Some(nack!(OUT_OF_RANGE))
+                }
+            }
+
+            Request::Presets => query(Query::Presets, ctx),
+
+            Request::SelfTest => query(Query::SelfTest, ctx),
+
+            Request::ResetReport => {
+                let reset = self.reset.clone();
+                self.reply(Response::ResetReport(reset.report()))
+            }
+
+            Request::Identity => {
+                let name = self.name;
+                self.reply(Response::Identity(Identity {
+                    id: self.device_id,
+                    name: name.as_str(),
+                }))
+            }
+
+            Request::SetName(name) => match Name::new(name) {
+                Some(name) => {
+                    self.name = name;
+                    ctx.name_change = Some(name);
+                    Some(ack!())
+                }
+
+                None => Some(nack!(OUT_OF_RANGE)),
+            },
+
+            Request::AuthChallenge => {
+                let nonce = self.auth.challenge();
+                self.reply(Response::AuthChallenge(nonce))
+            }
+
+            Request::AuthResponse(response) => {
+                if self.auth.respond(response) {
+                    Some(ack!())
+                } else {
+                    Some(nack!(UNAUTHENTICATED))
+                }
+            }
+
+            Request::AuthLogout => {
+                self.auth.logout();
+                Some(ack!())
+            }
+
+            Request::AuthSetSecret(secret) => {
+                match self.auth.set_secret(secret) {
+                    Ok(()) => {
+                        ctx.secret_change = Vec::from_slice(secret).ok();
+                        Some(ack!())
+                    }
+                    Err(_) => Some(nack!(OUT_OF_RANGE)),
+                }
+            }
+
+            #[cfg(feature = "ui_graphical")]
+            Request::UIUpdate(state) => update_ui(state.into(), ctx),
+
+            #[cfg(feature = "ui_graphical")]
+            Request::UIPatch(patch) => update_ui(patch, ctx),
+
+            #[cfg(feature = "ui_graphical")]
+            Request::SetMode(mode) => update_ui(
+                UIStatePatch {
+                    mode: Some(mode),
+                    ..Default::default()
+                },
+                ctx,
+            ),
+
+            #[cfg(feature = "ui_graphical")]
+            Request::SetBrightness(brightness) => update_ui(
+                UIStatePatch {
+                    brightness: Some(brightness),
+                    ..Default::default()
+                },
+                ctx,
+            ),
+
+            #[cfg(feature = "ui_graphical")]
+            Request::SetSpeed(speed) => update_ui(
+                UIStatePatch {
+                    speed: Some(speed),
+                    ..Default::default()
+                },
+                ctx,
+            ),
+
+            #[cfg(feature = "ui_graphical")]
+            Request::SetTemperature(temperature) => update_ui(
+                UIStatePatch {
+                    temperature: Some(temperature),
+                    ..Default::default()
+                },
+                ctx,
+            ),
+
+            #[cfg(feature = "ui_graphical")]
+            Request::SetScreenState(screen_state) => update_ui(
+                UIStatePatch {
+                    screen_state: Some(screen_state),
+                    ..Default::default()
+                },
+                ctx,
+            ),
+
+            #[cfg(feature = "ui_graphical")]
+            Request::Batch(encoded) => self.batch(encoded, ctx),
+
+            #[cfg(feature = "ui_graphical")]
+            Request::SetHostWatchdog(config) => {
+                if config.is_valid() {
+                    ctx.host_watchdog_config = Some(config);
+                    Some(ack!())
+                } else {
+                    Some(nack!(OUT_OF_RANGE))
+                }
+            }
+
+            #[cfg(feature = "ui_graphical")]
+            Request::ShowClear => control_show(ShowControl::Clear, ctx),
+
+            #[cfg(feature = "ui_graphical")]
+            Request::ShowUpload(chunk) => match Vec::from_slice(chunk) {
+                Ok(chunk) => control_show(ShowControl::Append(chunk), ctx),
+                Err(()) => Some(nack!(NO_SPACE)),
+            },
+
+            #[cfg(feature = "ui_graphical")]
+            Request::ShowStart => control_show(ShowControl::Seek(0), ctx),
+
+            #[cfg(feature = "ui_graphical")]
+            Request::ShowSeek(position_ms) => {
+                control_show(ShowControl::Seek(position_ms), ctx)
+            }
+
+            #[cfg(feature = "ui_graphical")]
+            Request::ShowStop => control_show(ShowControl::Stop, ctx),
+
+            #[cfg(feature = "ui_graphical")]
+            Request::ShowStatus => query(Query::ShowStatus, ctx),
+
+            // The physical UI cannot be driven over ERCP.
+            #[cfg(feature = "ui_physical")]
+            Request::UIUpdate(_)
+            | Request::UIPatch(_)
+            | Request::SetMode(_)
+            | Request::SetBrightness(_)
+            | Request::SetSpeed(_)
+            | Request::SetTemperature(_)
+            | Request::SetScreenState(_)
+            | Request::Batch(_)
+            | Request::SetHostWatchdog(_)
+            | Request::ShowClear
+            | Request::ShowUpload(_)
+            | Request::ShowStart
+            | Request::ShowStop
+            | Request::ShowSeek(_)
+            | Request::ShowStatus => Some(nack!(UNSUPPORTED)),
+        }
+    }
+
+    /// Handles Log_Drain requests.
+    ///
+    /// The oldest buffered log record is sent in the reply, or `None` if the
+    /// buffer is empty.
+    fn log_drain(&mut self) -> Option<Command> {
+        let reply_buffer = &mut self.reply_buffer;
+        let len = crate::log::drain_one(|record| {
+            Response::LogRecord(record)
+                .encode(reply_buffer)
+                .ok()
+                .map(|value| value.len())
+        })?;
+
+        Command::new(LOG_DRAIN_REPLY, &self.reply_buffer[..len]).ok()
+    }
+
+    /// Handles Batch requests.
+    ///
+    /// The items are merged into the pending update only if all of them are
+    /// valid, and the status of each item is sent in the reply.
+    #[cfg(feature = "ui_graphical")]
+    fn batch(
+        &mut self,
+        encoded: &[u8],
+        ctx: &mut ErcpContext,
+    ) -> Option<Command> {
+        if ctx.ui_busy {
+            return Some(nack!(BUSY));
+        }
+
+        let mut statuses = [batch::OK; batch::MAX_ITEMS];
+        let mut count = 0;
+        let mut patch = UIStatePatch::default();
+
+        for item in batch::items(encoded) {
+            if count == batch::MAX_ITEMS {
+                return Some(nack!(OUT_OF_RANGE));
+            }
+
+            statuses[count] = match item {
+                Ok(item) if item.is_valid() => {
+                    patch.merge(&item.into());
+                    batch::OK
+                }
+                Ok(_) => OUT_OF_RANGE,
+                Err(error) => decode_reason(error),
+            };
+
+            count += 1;
+        }
+
+        let statuses = &statuses[..count];
+        if batch::is_applied(statuses) {
+            ctx.ui_state_patch
+                .get_or_insert_with(Default::default)
+                .merge(&patch);
+        }
+
+        self.reply(Response::Batch(statuses))
+    }
+
+    /// Sends a response.
+    fn reply(&mut self, response: Response) -> Option<Command> {
+        let value = response.encode(&mut self.reply_buffer).ok()?;
+        Command::new(response.code(), value).ok()
+    }
+}
+
+/// Leaves a query in the context, the reply being sent with [`answer`].
+fn query<'a>(query: Query, ctx: &mut ErcpContext) -> Option<Command<'a>> {
+    ctx.query = Some(query);
+    None
+}
+
+/// Merges a partial update of the UI into the pending one, replying with an
+/// ACK if the patch is valid or a NACK otherwise.
+fn update_ui<'a>(
+    patch: UIStatePatch,
+    ctx: &mut ErcpContext,
+) -> Option<Command<'a>> {
+    if ctx.ui_busy {
+        Some(nack!(BUSY))
+    } else if patch.is_valid() {
+        ctx.ui_state_patch
+            .get_or_insert_with(Default::default)
+            .merge(&patch);
+        Some(ack!())
+    } else {
+        Some(nack!(OUT_OF_RANGE))
+    }
+}
+
+/// Leaves a change to the show in the context, the reply being sent with
+/// [`answer`].
+#[cfg(feature = "ui_graphical")]
+fn control_show<'a>(
+    control: ShowControl,
+    ctx: &mut ErcpContext,
+) -> Option<Command<'a>> {
+    ctx.show_control = Some(control);
+    None
+}
+
+/// Returns the NACK for a show error.
+#[cfg(feature = "ui_graphical")]
+fn show_nack<'a>(error: ShowError) -> Command<'a> {
+    match error {
+        ShowError::Playing => nack!(BUSY),
+        ShowError::Full => nack!(NO_SPACE),
+        ShowError::Invalid => nack!(INVALID_SHOW),
+    }
+}
+
+/// Returns the NACK reason for a decode error.
+fn decode_reason(error: DecodeError) -> u8 {
+    match error {
+        DecodeError::Truncated => TRUNCATED,
+        DecodeError::UnknownVariant => UNKNOWN_VARIANT,
+        DecodeError::TrailingBytes => TRAILING_BYTES,
+        DecodeError::UnknownCommand(_) => nack_reason::UNKNOWN_COMMAND,
+        DecodeError::InvalidValue => nack_reason::INVALID_ARGUMENTS,
+    }
+}
+
+/// Returns whether a command can be run without authentication.
+///
+/// This is only the case for the commands needed to identify the Totem and to
+/// authenticate: the ERCP Basic Ping, Protocol and Version, the Totem
+/// Protocol_Version and Identity, and the authentication commands. Any other
+/// command, including the ERCP Basic Reset and the reads of the logs and
+/// state, needs authentication.
+fn is_open(code: u8) -> bool {
+    matches!(
+        code,
+        PING | PROTOCOL
+            | VERSION
+            | PROTOCOL_VERSION
+            | IDENTITY
+            | AUTH_CHALLENGE
+            | AUTH_RESPONSE
+            | AUTH_LOGOUT
+    )
+}
 
 impl Router for TotemRouter {
     type Context = ErcpContext;
 
     fn route(
         &mut self,
-        command: ercp_basic::Command,
-        #[allow(unused)] ctx: &mut Self::Context,
-    ) -> Option<ercp_basic::Command> {
-        match command.code() {
-            #[cfg(feature = "ui_graphical")]
-            totem_ui::graphical::UI_UPDATE => totem_ui::graphical::ui_update(
-                command,
-                &mut ctx.ui_state_update,
-            ),
+        command: Command,
+        ctx: &mut Self::Context,
+    ) -> Option<Command> {
+        if !is_open(command.code()) && !self.auth.is_authorised() {
+            return Some(nack!(UNAUTHENTICATED));
+        }
 
-            _ => self.default_routes(command),
+        match Request::decode(command.code(), command.value()) {
+            Ok(request) => {
+                ctx.host_seen = true;
+                self.handle(request, ctx)
+            }
+            Err(DecodeError::UnknownCommand(_)) => self.default_routes(command),
+            Err(error) => {
+                let reason = decode_reason(error);
+                Some(nack!(reason))
+            }
         }
     }
 
@@ -55,6 +568,365 @@ impl Router for TotemRouter {
     }
 
     fn description(&self) -> &str {
-        env!("CARGO_PKG_DESCRIPTION")
+        if self.name.is_empty() {
+            env!("CARGO_PKG_DESCRIPTION")
+        } else {
+            self.name.as_str()
+        }
+    }
+}
+
+/// Handles incoming data on a link.
+///
+/// This is meant to be called when the transport has received data, typically
+/// from its interrupt handler. When a complete frame is received, it must then
+/// be processed with [`process`].
+pub fn receive<A: Adapter, T: Timer>(link: &mut ErcpLink<A, T>) -> Reception {
+    if link.handle_data().is_err() {
+        Reception::Error
+    } else if link.complete_frame_received() {
+        Reception::Complete
+    } else {
+        Reception::Incomplete
+    }
+}
+
+/// Processes the complete frame received on a link, sending the reply back.
+///
+/// If the request needs data gathered on demand, [`ErcpContext::query`] is set
+/// instead and the reply must be sent with [`answer`].
+///
+/// Returns whether the frame has been processed successfully.
+pub fn process<A: Adapter, T: Timer>(
+    link: &mut ErcpLink<A, T>,
+    context: &mut ErcpContext,
+) -> bool {
+    link.process(context).is_ok()
+}
+
+/// Sends the reply to the query or show change left in the context by
+/// [`process`].
+///
+/// The UI update resulting from the answer, if any, is stored in `context`.
+///
+/// Returns whether the reply has been sent successfully.
+pub fn answer<A: Adapter, T: Timer>(
+    link: &mut ErcpLink<A, T>,
+    answer: Answer,
+    context: &mut ErcpContext,
+) -> bool {
+    let mut reply_buffer = [0; REPLY_BUFFER_SIZE];
+
+    let reply = match answer {
+        Answer::Response(response) => response
+            .encode(&mut reply_buffer)
+            .ok()
+            .and_then(|value| Command::new(response.code(), value).ok()),
+        Answer::Preset(Some(state)) => update_ui(state.into(), context),
+        Answer::Preset(None) => Some(nack!(NOT_FOUND)),
+
+        // The UI state at the seek position is applied even when the UI is
+        // busy, so that the show does not start from a stale state.
+        #[cfg(feature = "ui_graphical")]
+        Answer::Show(Ok(patch)) => {
+            if !patch.is_empty() {
+                context
+                    .ui_state_patch
+                    .get_or_insert_with(Default::default)
+                    .merge(&patch);
+            }
+
+            Some(ack!())
+        }
+
+        #[cfg(feature = "ui_graphical")]
+        Answer::Show(Err(error)) => Some(show_nack(error)),
+    };
+
+    reply.is_some_and(|reply| link.notify(reply).is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use ercp_basic::{
+        adapter::SerialAdapter,
+        command::{ACK, NACK, RESET},
+    };
+    use totem_protocol::{
+        auth::{response, NONCE_LEN},
+        code::{AUTH_CHALLENGE_REPLY, PROTOCOL_VERSION_REPLY},
+        reset::ResetCause,
+        state::Mode,
+    };
+    use totem_utils::{
+        loopback::{DeviceEnd, Loopback, TestEnd},
+        monotonic_timer::MonotonicTimer,
+    };
+
+    use super::*;
+
+    /// The maximum size of a frame in the tests.
+    const FRAME_SIZE: usize = 64;
+
+    /// The shared secret used in the tests.
+    const SECRET: &[u8] = b"totem";
+
+    /// An ERCP Basic link over the device end of a loopback.
+    type TestLink<'a> =
+        ErcpLink<SerialAdapter<DeviceEnd<'a, FRAME_SIZE>>, MonotonicTimer<u32>>;
+
+    /// Returns a frozen time: no frame times out.
+    fn now() -> u32 {
+        0
+    }
+
+    /// Returns a link to a router, with `secret` as its shared secret if any.
+    fn link<'a>(
+        device: DeviceEnd<'a, FRAME_SIZE>,
+        secret: Option<&[u8]>,
+    ) -> TestLink<'a> {
+        let router = TotemRouter::new(
+            [0; 32],
+            secret,
+            DeviceId(0),
+            Name::default(),
+            ResetInfo {
+                cause: ResetCause::PowerOn,
+                resets: 0,
+                panic: None,
+            },
+        );
+
+        let adapter = SerialAdapter::new(device);
+        let timer = MonotonicTimer::new(now);
+        ErcpLink::new(adapter, timer, router)
+    }
+
+    /// Sends a frame to the device and returns its reply.
+    fn exchange(
+        link: &mut TestLink<'_>,
+        test: &mut TestEnd<'_, FRAME_SIZE>,
+        frame: &[u8],
+        ctx: &mut ErcpContext,
+    ) -> Vec<u8, FRAME_SIZE> {
+        test.feed(frame).unwrap();
+
+        let mut reception = Reception::Incomplete;
+        while reception == Reception::Incomplete && test.has_data() {
+            reception = receive(link);
+        }
+
+        assert_eq!(reception, Reception::Complete);
+        assert!(process(link, ctx));
+
+        core::iter::from_fn(|| test.take()).collect()
+    }
+
+    /// Encodes an ERCP Basic frame.
+    fn frame(code: u8, value: &[u8]) -> Vec<u8, FRAME_SIZE> {
+        let mut frame = Vec::new();
+        frame.extend_from_slice(b"ERCPB").unwrap();
+        frame.push(code).unwrap();
+        frame.push(value.len() as u8).unwrap();
+        frame.extend_from_slice(value).unwrap();
+        frame.push(crc8(&frame[5..])).unwrap();
+        frame.push(0x04).unwrap();
+        frame
+    }
+
+    /// Encodes the ERCP Basic frame of a request.
+    fn request(request: Request<'_>) -> Vec<u8, FRAME_SIZE> {
+        let mut buffer = [0; FRAME_SIZE];
+        let value = request.encode(&mut buffer).unwrap();
+        frame(request.code(), value)
+    }
+
+    /// Computes the CRC-8 of the type, length and value of a frame.
+    fn crc8(data: &[u8]) -> u8 {
+        data.iter().fold(0, |crc, &byte| {
+            (0..8).fold(crc ^ byte, |crc, _| {
+                if crc & 0x80 == 0x80 {
+                    (crc << 1) ^ 0x07
+                } else {
+                    crc << 1
+                }
+            })
+        })
+    }
+
+    /// Authenticates the host with the test secret.
+    fn authenticate(
+        link: &mut TestLink<'_>,
+        test: &mut TestEnd<'_, FRAME_SIZE>,
+        ctx: &mut ErcpContext,
+    ) {
+        let challenge = request(Request::AuthChallenge);
+        let reply = exchange(link, test, &challenge, ctx);
+        assert_eq!(reply[5], AUTH_CHALLENGE_REPLY);
+
+        let nonce = &reply[7..7 + NONCE_LEN];
+        let response = response(SECRET, nonce);
+        let reply = exchange(
+            link,
+            test,
+            &request(Request::AuthResponse(&response)),
+            ctx,
+        );
+        assert_eq!(reply, frame(ACK, &[]));
+    }
+
+    #[test]
+    fn a_request_is_replied_through_a_loopback() {
+        let mut loopback = Loopback::<FRAME_SIZE>::new();
+        let (device, mut test) = loopback.split();
+        let mut link = link(device, None);
+
+        let reply = exchange(
+            &mut link,
+            &mut test,
+            &frame(PROTOCOL_VERSION, &[]),
+            &mut ErcpContext::default(),
+        );
+
+        let expected =
+            frame(PROTOCOL_VERSION_REPLY, &[totem_protocol::VERSION]);
+        assert_eq!(reply, expected);
+    }
+
+    #[test]
+    fn a_mutating_command_needs_authentication() {
+        let mut loopback = Loopback::<FRAME_SIZE>::new();
+        let (device, mut test) = loopback.split();
+        let mut link = link(device, Some(SECRET));
+        let mut ctx = ErcpContext::default();
+
+        let save = request(Request::PresetSave(0));
+        let reply = exchange(&mut link, &mut test, &save, &mut ctx);
+        assert_eq!(reply, frame(NACK, &[UNAUTHENTICATED]));
+        assert_eq!(ctx.preset_save, None);
+
+        authenticate(&mut link, &mut test, &mut ctx);
+
+        let reply = exchange(&mut link, &mut test, &save, &mut ctx);
+        assert_eq!(reply, frame(ACK, &[]));
+        assert_eq!(ctx.preset_save, Some(0));
+
+        let logout = request(Request::AuthLogout);
+        let reply = exchange(&mut link, &mut test, &logout, &mut ctx);
+        assert_eq!(reply, frame(ACK, &[]));
+
+        let reply = exchange(&mut link, &mut test, &save, &mut ctx);
+        assert_eq!(reply, frame(NACK, &[UNAUTHENTICATED]));
+    }
+
+    #[test]
+    fn only_read_only_commands_are_open_before_authentication() {
+        let mut loopback = Loopback::<FRAME_SIZE>::new();
+        let (device, mut test) = loopback.split();
+        let mut link = link(device, Some(SECRET));
+        let mut ctx = ErcpContext::default();
+
+        let version = request(Request::ProtocolVersion);
+        let reply = exchange(&mut link, &mut test, &version, &mut ctx);
+        assert_eq!(
+            reply,
+            frame(PROTOCOL_VERSION_REPLY, &[totem_protocol::VERSION])
+        );
+
+        for closed in [frame(RESET, &[]), request(Request::LogDrain)] {
+            let reply = exchange(&mut link, &mut test, &closed, &mut ctx);
+            assert_eq!(reply, frame(NACK, &[UNAUTHENTICATED]));
+        }
+    }
+
+    #[test]
+    fn a_replayed_response_is_rejected() {
+        let mut loopback = Loopback::<FRAME_SIZE>::new();
+        let (device, mut test) = loopback.split();
+        let mut link = link(device, Some(SECRET));
+        let mut ctx = ErcpContext::default();
+
+        let challenge = request(Request::AuthChallenge);
+        let reply = exchange(&mut link, &mut test, &challenge, &mut ctx);
+        let response = response(SECRET, &reply[7..7 + NONCE_LEN]);
+        let response = request(Request::AuthResponse(&response));
+
+        let reply = exchange(&mut link, &mut test, &response, &mut ctx);
+        assert_eq!(reply, frame(ACK, &[]));
+
+        let logout = request(Request::AuthLogout);
+        exchange(&mut link, &mut test, &logout, &mut ctx);
+
+        let reply = exchange(&mut link, &mut test, &response, &mut ctx);
+        assert_eq!(reply, frame(NACK, &[UNAUTHENTICATED]));
+
+        let save = request(Request::PresetSave(0));
+        let reply = exchange(&mut link, &mut test, &save, &mut ctx);
+        assert_eq!(reply, frame(NACK, &[UNAUTHENTICATED]));
+    }
+
+    #[test]
+    fn a_mutating_command_updates_the_context() {
+        let mut loopback = Loopback::<FRAME_SIZE>::new();
+        let (device, mut test) = loopback.split();
+        let mut link = link(device, None);
+        let mut ctx = ErcpContext::default();
+
+        let save = request(Request::PresetSave(PRESET_COUNT as u8 - 1));
+        let reply = exchange(&mut link, &mut test, &save, &mut ctx);
+
+        assert_eq!(reply, frame(ACK, &[]));
+        assert_eq!(ctx.preset_save, Some(PRESET_COUNT - 1));
+        assert!(ctx.host_seen);
+    }
+
+    #[test]
+    fn an_out_of_range_value_is_nacked_with_its_reason() {
+        let mut loopback = Loopback::<FRAME_SIZE>::new();
+        let (device, mut test) = loopback.split();
+        let mut link = link(device, None);
+        let mut ctx = ErcpContext::default();
+
+        let save = request(Request::PresetSave(PRESET_COUNT as u8));
+        let reply = exchange(&mut link, &mut test, &save, &mut ctx);
+
+        assert_eq!(reply, frame(NACK, &[OUT_OF_RANGE]));
+        assert_eq!(ctx.preset_save, None);
+    }
+
+    #[cfg(feature = "ui_physical")]
+    #[test]
+    fn a_ui_command_is_unsupported_by_the_physical_ui() {
+        let mut loopback = Loopback::<FRAME_SIZE>::new();
+        let (device, mut test) = loopback.split();
+        let mut link = link(device, None);
+        let mut ctx = ErcpContext::default();
+
+        let set_mode = request(Request::SetMode(Mode::Off));
+        let reply = exchange(&mut link, &mut test, &set_mode, &mut ctx);
+
+        assert_eq!(reply, frame(NACK, &[UNSUPPORTED]));
+        assert_eq!(ctx.ui_state_patch, None);
+    }
+
+    #[cfg(feature = "ui_graphical")]
+    #[test]
+    fn a_ui_command_patches_the_graphical_ui() {
+        let mut loopback = Loopback::<FRAME_SIZE>::new();
+        let (device, mut test) = loopback.split();
+        let mut link = link(device, None);
+        let mut ctx = ErcpContext::default();
+
+        let set_mode = request(Request::SetMode(Mode::Off));
+        let reply = exchange(&mut link, &mut test, &set_mode, &mut ctx);
+
+        assert_eq!(reply, frame(ACK, &[]));
+        assert_eq!(
+            ctx.ui_state_patch,
+            Some(UIStatePatch {
+                mode: Some(Mode::Off),
+                ..Default::default()
+            })
+        );
     }
 }
@@ -0,0 +1,83 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Frame clock with absolute deadlines.
+//!
+//! Scheduling each frame a period after the previous one has been rendered
+//! makes the frame rate lower than the target and drift with the rendering
+//! time. Instead, the [`FrameClock`] keeps absolute deadlines, each one period
+//! after the previous one. When a frame starts so late that the deadlines of
+//! the next ones have passed, these frames are skipped, so that effects keep
+//! their speed.
+
+/// The timing of a frame, given by the frame clock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameTick {
+    /// The delay between the deadline of the frame and its start, in
+    /// microseconds.
+    pub jitter_us: u32,
+    /// The number of frames to skip, as their deadline has passed too.
+    pub skipped: u32,
+    /// The deadline of the next frame, in microseconds since boot.
+    pub next_deadline_us: u64,
+}
+
+/// A periodic frame clock.
+#[derive(Debug)]
+pub struct FrameClock {
+    /// The period between two frames, in microseconds.
+    period_us: u32,
+    /// The deadline of the next frame, if the clock is running.
+    deadline_us: Option<u64>,
+}
+
+impl FrameClock {
+    /// Creates a stopped frame clock with a period of `period_us`.
+    pub const fn new(period_us: u32) -> Self {
+        Self {
+            period_us,
+            deadline_us: None,
+        }
+    }
+
+    /// Returns the period between two frames, in microseconds.
+    pub fn period_us(&self) -> u32 {
+        self.period_us
+    }
+
+    /// Ticks the clock at the start of a frame, at `now_us`.
+    ///
+    /// A stopped clock starts with a deadline of `now_us`.
+    pub fn tick(&mut self, now_us: u64) -> FrameTick {
+        let period_us = u64::from(self.period_us);
+        let deadline_us = self.deadline_us.unwrap_or(now_us);
+        let late_us = now_us.saturating_sub(deadline_us);
+        let skipped = late_us / period_us;
+        let next_deadline_us = deadline_us + (skipped + 1) * period_us;
+
+        self.deadline_us = Some(next_deadline_us);
+
+        FrameTick {
+            jitter_us: late_us.try_into().unwrap_or(u32::MAX),
+            skipped: skipped.try_into().unwrap_or(u32::MAX),
+            next_deadline_us,
+        }
+    }
+
+    /// Stops the clock, when no more frames are rendered.
+    pub fn stop(&mut self) {
+        self.deadline_us = None;
+    }
+}
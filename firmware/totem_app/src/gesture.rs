@@ -0,0 +1,124 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Gesture detection on a push button.
+//!
+//! The detector is fed with the level of the button at a regular period, which
+//! is expected to be longer than its bouncing time.
+
+/// The minimum press duration for a hold, in milliseconds.
+const HOLD_MS: u32 = 600;
+
+/// The minimum press duration for a long hold, in milliseconds.
+const LONG_HOLD_MS: u32 = 3000;
+
+/// The maximum time between two taps of a multiple tap, in milliseconds.
+const MULTI_TAP_MS: u32 = 300;
+
+/// A gesture on a push button.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Gesture {
+    /// A short press.
+    Tap,
+    /// Two short presses in a row.
+    DoubleTap,
+    /// Three short presses in a row.
+    TripleTap,
+    /// A long press, detected while the button is still pressed.
+    Hold,
+    /// A very long press, detected while the button is still pressed after a
+    /// hold.
+    LongHold,
+}
+
+/// A gesture detector.
+#[derive(Default)]
+pub struct GestureDetector {
+    /// Whether the button was pressed at the last update.
+    pressed: bool,
+    /// The time of the last press or release.
+    edge_ms: u32,
+    /// Whether the current press has already been reported as a hold.
+    held: bool,
+    /// Whether the current press has already been reported as a long hold.
+    long_held: bool,
+    /// The number of taps waiting to be reported.
+    taps: u8,
+}
+
+impl GestureDetector {
+    /// Creates a new detector.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Updates the detector with the level of the button at `now_ms`.
+    ///
+    /// Returns a gesture when one has been completed.
+    pub fn update(&mut self, pressed: bool, now_ms: u32) -> Option<Gesture> {
+        let since_edge = now_ms.wrapping_sub(self.edge_ms);
+
+        match (self.pressed, pressed) {
+            // Press.
+            (false, true) => {
+                self.pressed = true;
+                self.edge_ms = now_ms;
+                self.held = false;
+                self.long_held = false;
+                None
+            }
+
+            // Release.
+            (true, false) => {
+                self.pressed = false;
+                self.edge_ms = now_ms;
+
+                if self.held {
+                    None
+                } else if self.taps == 2 {
+                    self.taps = 0;
+                    Some(Gesture::TripleTap)
+                } else {
+                    self.taps += 1;
+                    None
+                }
+            }
+
+            (true, true) if !self.held && since_edge >= HOLD_MS => {
+                self.held = true;
+                self.taps = 0;
+                Some(Gesture::Hold)
+            }
+
+            (true, true) if !self.long_held && since_edge >= LONG_HOLD_MS => {
+                self.long_held = true;
+                Some(Gesture::LongHold)
+            }
+
+            // No more taps are coming.
+            (false, false) if self.taps > 0 && since_edge > MULTI_TAP_MS => {
+                let taps = self.taps;
+                self.taps = 0;
+
+                match taps {
+                    1 => Some(Gesture::Tap),
+                    _ => Some(Gesture::DoubleTap),
+                }
+            }
+
+            _ => None,
+        }
+    }
+}
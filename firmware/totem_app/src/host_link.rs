@@ -0,0 +1,84 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Watchdog on the link with the host.
+//!
+//! Each valid command received from the host feeds the watchdog. When the
+//! host has been silent for longer than the configured timeout, the watchdog
+//! expires once and yields the failsafe UI state, until the host comes back.
+
+use totem_protocol::{
+    host_link::{HostLinkStatus, HostWatchdogConfig},
+    state::UIState,
+};
+
+/// A watchdog on the link with the host.
+#[derive(Default)]
+pub struct HostWatchdog {
+    /// The current configuration.
+    config: HostWatchdogConfig,
+    /// The time when the last valid command has been received.
+    last_seen_ms: u32,
+    /// Whether the watchdog has expired since the last valid command.
+    expired: bool,
+    /// The number of times the watchdog has expired.
+    losses: u32,
+}
+
+impl HostWatchdog {
+    /// Creates a disabled watchdog.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Configures the watchdog at `now_ms`.
+    pub fn configure(&mut self, config: HostWatchdogConfig, now_ms: u32) {
+        self.config = config;
+        self.feed(now_ms);
+    }
+
+    /// Records a valid command received from the host at `now_ms`.
+    pub fn feed(&mut self, now_ms: u32) {
+        self.last_seen_ms = now_ms;
+        self.expired = false;
+    }
+
+    /// Checks the watchdog at `now_ms`.
+    ///
+    /// Returns the failsafe UI state when the watchdog expires, and `None`
+    /// otherwise, including while it stays expired.
+    pub fn check(&mut self, now_ms: u32) -> Option<UIState> {
+        let silence = now_ms.wrapping_sub(self.last_seen_ms);
+
+        if self.config.is_enabled()
+            && !self.expired
+            && silence > self.config.timeout_ms
+        {
+            self.expired = true;
+            self.losses = self.losses.saturating_add(1);
+            Some(self.config.failsafe)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the status of the link with the host.
+    pub fn status(&self) -> HostLinkStatus {
+        HostLinkStatus {
+            losses: self.losses,
+            failsafe_active: self.expired,
+        }
+    }
+}
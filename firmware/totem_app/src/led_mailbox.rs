@@ -0,0 +1,61 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Coalescing of the updates sent to the LED task.
+//!
+//! Other tasks do not queue their updates as messages to the LED task, where a
+//! burst of them could exhaust its capacity. Instead, they post them in a
+//! [`LedMailbox`] and wake the LED task up, which applies them before rendering
+//! its next frame. Each post replaces the previous one of the same kind: only
+//! the latest UI state matters, and a cue replaces the running one anyway.
+
+use totem_protocol::cue::Cue;
+use totem_ui::state::UIState;
+
+/// The latest updates waiting for the LED task.
+#[derive(Default)]
+pub struct LedMailbox {
+    /// The latest UI state, if it has changed.
+    state: Option<UIState>,
+    /// The latest cue to fire, if any.
+    cue: Option<Cue>,
+}
+
+impl LedMailbox {
+    /// Creates an empty mailbox.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Posts a new UI state, replacing the one not applied yet if any.
+    pub fn post_state(&mut self, state: UIState) {
+        self.state = Some(state);
+    }
+
+    /// Posts a cue to fire, replacing the one not fired yet if any.
+    pub fn post_cue(&mut self, cue: Cue) {
+        self.cue = Some(cue);
+    }
+
+    /// Takes the latest UI state, if it has changed.
+    pub fn take_state(&mut self) -> Option<UIState> {
+        self.state.take()
+    }
+
+    /// Takes the latest cue to fire, if any.
+    pub fn take_cue(&mut self) -> Option<Cue> {
+        self.cue.take()
+    }
+}
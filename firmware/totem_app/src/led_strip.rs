@@ -15,18 +15,64 @@
 
 //! LED strip utilities.
 
-use smart_leds::{colors::BLACK, SmartLedsWrite as _};
+use smart_leds::{colors::BLACK, SmartLedsWrite as _, RGB8};
 
-use totem_board::{constants::NUM_LEDS, peripheral::LedStrip};
+use totem_board::{
+    layout::{LedLayout, MAX_NUM_LEDS},
+    peripheral::LedStrip,
+};
+
+/// The number of attempts to write a frame to the LED strip.
+pub const WRITE_ATTEMPTS: u32 = 3;
+
+/// The outcome of writing a frame to the LED strip.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameWrite {
+    /// The number of attempts which have failed.
+    pub failed_attempts: u32,
+    /// Whether the frame has eventually been written.
+    pub written: bool,
+}
 
 /// LED strip extension trait.
 pub trait LedStripExt {
-    /// Switches off the LED strip.
-    fn off(&mut self);
+    /// Writes a frame to the LED strip, retrying up to [`WRITE_ATTEMPTS`]
+    /// times on error.
+    fn write_frame(&mut self, frame: impl Iterator<Item = RGB8>) -> FrameWrite;
+
+    /// Switches off the LEDs of a layout.
+    fn off(&mut self, layout: LedLayout) -> FrameWrite;
 }
 
 impl LedStripExt for LedStrip {
-    fn off(&mut self) {
-        self.write([BLACK; NUM_LEDS].into_iter()).unwrap();
+    fn write_frame(&mut self, frame: impl Iterator<Item = RGB8>) -> FrameWrite {
+        // Buffer the frame, so that it can be written again.
+        let mut buffer = [RGB8::default(); MAX_NUM_LEDS];
+        let len = buffer
+            .iter_mut()
+            .zip(frame)
+            .map(|(pixel, color)| *pixel = color)
+            .count();
+
+        let mut failed_attempts = 0;
+        while failed_attempts < WRITE_ATTEMPTS {
+            if self.write(buffer[..len].iter().copied()).is_ok() {
+                return FrameWrite {
+                    failed_attempts,
+                    written: true,
+                };
+            }
+
+            failed_attempts += 1;
+        }
+
+        FrameWrite {
+            failed_attempts,
+            written: false,
+        }
+    }
+
+    fn off(&mut self, layout: LedLayout) -> FrameWrite {
+        self.write_frame((0..layout.num_leds()).map(|_| BLACK))
     }
 }
@@ -22,6 +22,22 @@
 #![deny(unused_must_use)]
 #![forbid(unsafe_code)]
 
+pub mod auth;
 pub mod chaser;
+pub mod cue;
 pub mod ercp;
+pub mod frame_clock;
+pub mod gesture;
+pub mod host_link;
+pub mod led_mailbox;
 pub mod led_strip;
+pub mod log;
+pub mod reset;
+pub mod screen;
+pub mod self_test;
+pub mod settings;
+pub mod show;
+pub mod supervisor;
+pub mod sync;
+pub mod telemetry;
+pub mod ui_update;
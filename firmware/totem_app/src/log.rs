@@ -0,0 +1,217 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Log buffering, to stream logs over ERCP Basic.
+//!
+//! Logs sent through `defmt-rtt` are only visible with a debug probe. To make
+//! them available through a simple serial cable, the `ercp_log` feature makes
+//! the [`error!`](crate::error), [`warn!`](crate::warn) and
+//! [`info!`](crate::info) macros format the record once into a ring buffer,
+//! which can then be drained over ERCP Basic, and forward the formatted
+//! message to `defmt`. Without the feature, which is off by default, the macros
+//! only log through `defmt`, which defers the formatting to the host.
+//!
+//! Records are formatted and timestamped with the interrupts enabled: only
+//! pushing them to the buffer and popping them from it is done in a critical
+//! section.
+
+use core::{
+    cell::{Cell, RefCell},
+    fmt::{self, Write as _},
+};
+
+use cortex_m::interrupt::{self, Mutex};
+use heapless::{Deque, String};
+use totem_protocol::log::LogRecord;
+
+pub use totem_protocol::log::LogLevel;
+
+/// The maximum number of buffered records.
+pub const LOG_BUFFER_SIZE: usize = 32;
+
+/// The maximum length of a log message.
+pub const MESSAGE_MAX_LEN: usize = 96;
+
+/// The log buffer.
+static BUFFER: Mutex<RefCell<LogBuffer>> =
+    Mutex::new(RefCell::new(LogBuffer::new()));
+
+/// The clock used to timestamp records.
+static CLOCK: Mutex<Cell<Option<fn() -> u32>>> = Mutex::new(Cell::new(None));
+
+/// Logs an error.
+#[cfg(feature = "ercp_log")]
+#[macro_export]
+macro_rules! error {
+    ($($arg:tt)+) => {
+        $crate::log::record(
+            $crate::log::LogLevel::Error,
+            format_args!($($arg)+),
+        )
+    };
+}
+
+/// Logs an error.
+#[cfg(not(feature = "ercp_log"))]
+#[macro_export]
+macro_rules! error {
+    ($($arg:tt)+) => {
+        defmt::error!($($arg)+)
+    };
+}
+
+/// Logs a warning.
+#[cfg(feature = "ercp_log")]
+#[macro_export]
+macro_rules! warn {
+    ($($arg:tt)+) => {
+        $crate::log::record(
+            $crate::log::LogLevel::Warn,
+            format_args!($($arg)+),
+        )
+    };
+}
+
+/// Logs a warning.
+#[cfg(not(feature = "ercp_log"))]
+#[macro_export]
+macro_rules! warn {
+    ($($arg:tt)+) => {
+        defmt::warn!($($arg)+)
+    };
+}
+
+/// Logs an information.
+#[cfg(feature = "ercp_log")]
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)+) => {
+        $crate::log::record(
+            $crate::log::LogLevel::Info,
+            format_args!($($arg)+),
+        )
+    };
+}
+
+/// Logs an information.
+#[cfg(not(feature = "ercp_log"))]
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)+) => {
+        defmt::info!($($arg)+)
+    };
+}
+
+/// A buffered log record.
+struct Record {
+    /// The timestamp of the record, in milliseconds.
+    timestamp_ms: u32,
+    /// The level of the record.
+    level: LogLevel,
+    /// The number of records dropped before this one.
+    dropped: u32,
+    /// The log message, truncated to [`MESSAGE_MAX_LEN`].
+    message: String<MESSAGE_MAX_LEN>,
+}
+
+/// A ring buffer of log records.
+struct LogBuffer {
+    /// The buffered records.
+    records: Deque<Record, LOG_BUFFER_SIZE>,
+}
+
+impl LogBuffer {
+    const fn new() -> Self {
+        Self {
+            records: Deque::new(),
+        }
+    }
+
+    fn push(&mut self, record: Record) {
+        // When the buffer is full, drop the oldest record and account for it
+        // in the next one, so that the host knows some records are missing.
+        if self.records.is_full() {
+            if let Some(oldest) = self.records.pop_front() {
+                if let Some(next) = self.records.front_mut() {
+                    next.dropped += oldest.dropped + 1;
+                }
+            }
+        }
+
+        self.records.push_back(record).ok();
+    }
+}
+
+/// Sets the clock used to timestamp records.
+///
+/// The clock must return the time elapsed since boot, in milliseconds.
+pub fn init(clock: fn() -> u32) {
+    interrupt::free(|cs| CLOCK.borrow(cs).set(Some(clock)));
+}
+
+/// Records a log message in the buffer and forwards it to `defmt`.
+///
+/// Messages longer than [`MESSAGE_MAX_LEN`] are truncated.
+pub fn record(level: LogLevel, args: fmt::Arguments) {
+    let mut message = TruncatingString(String::new());
+    message.write_fmt(args).ok();
+
+    let text = message.0.as_str();
+    match level {
+        LogLevel::Error => defmt::error!("{=str}", text),
+        LogLevel::Warn => defmt::warn!("{=str}", text),
+        LogLevel::Info => defmt::info!("{=str}", text),
+    }
+
+    let clock = interrupt::free(|cs| CLOCK.borrow(cs).get());
+    let record = Record {
+        timestamp_ms: clock.map_or(0, |clock| clock()),
+        level,
+        dropped: 0,
+        message: message.0,
+    };
+
+    // Only move the record in the buffer with the interrupts disabled.
+    interrupt::free(|cs| BUFFER.borrow(cs).borrow_mut().push(record));
+}
+
+/// Pops the oldest record from the buffer and passes it to `f`.
+pub fn drain_one<R>(f: impl FnOnce(Option<LogRecord>) -> R) -> R {
+    let record = interrupt::free(|cs| {
+        BUFFER.borrow(cs).borrow_mut().records.pop_front()
+    });
+
+    f(record.as_ref().map(|record| LogRecord {
+        timestamp_ms: record.timestamp_ms,
+        level: record.level,
+        dropped: record.dropped,
+        message: record.message.as_str(),
+    }))
+}
+
+/// A string silently truncating what does not fit in it.
+struct TruncatingString(String<MESSAGE_MAX_LEN>);
+
+impl fmt::Write for TruncatingString {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            if self.0.push(c).is_err() {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -24,20 +24,26 @@ compile_error!("You must select a UI.");
 #[cfg(all(feature = "ui_physical", feature = "ui_graphical"))]
 compile_error!("You must select only one UI.");
 
+#[cfg(all(feature = "sync_leader", feature = "sync_follower"))]
+compile_error!("A Totem cannot be both a sync leader and a sync follower.");
+
+// Without panic-probe, the panic handler from `totem_app::reset` records the
+// panic and resets.
 #[cfg(feature = "panic-probe")]
 use panic_probe as _;
-#[cfg(not(feature = "panic-probe"))]
-use panic_reset as _;
 
 use defmt_rtt as _;
 
 #[rtic::app(device = totem_board::pac, dispatchers = [TIM2, TIM3])]
 mod app {
-    use systick_monotonic::Systick;
+    use core::fmt::Write as _;
+
+    use dwt_systick_monotonic::{fugit::TimerInstantU64, DwtSystick};
 
     use defmt::Format;
     use embedded_time::{duration::Seconds, rate::Hertz};
-    use ercp_basic::{adapter::SerialAdapter, ErcpBasic};
+    use ercp_basic::adapter::SerialAdapter;
+    use heapless::String;
     use led_effects::{
         chaser::{Chaser as _, RainbowChaser, RandomUnicolor},
         sequence::{
@@ -47,26 +53,57 @@ mod app {
         time::TimeConfig,
     };
     use rand::distributions::Uniform;
-    use smart_leds::{
-        brightness as set_brightness, colors::RED, SmartLedsWrite as _,
-    };
+    use smart_leds::{brightness as set_brightness, colors::RED, RGB8};
 
+    use cortex_m::peripheral::DWT;
     use totem_app::{
         chaser::Chaser,
-        ercp::{ErcpContext, TotemRouter},
+        cue::{CueLayer, DOUBLE_TAP_CUE, HOLD_CUE, PRESET_SAVED_CUE, TAP_CUE},
+        ercp::{
+            self, Answer, ErcpContext, ErcpLink, Query, Reception, TotemRouter,
+        },
+        error,
+        frame_clock::FrameClock,
+        gesture::{Gesture, GestureDetector},
+        host_link::HostWatchdog,
+        info,
+        led_mailbox::LedMailbox,
         led_strip::LedStripExt as _,
+        reset::ResetInfo,
+        screen::ManagedScreen,
+        settings::Settings,
+        show::ShowPlayer,
+        supervisor::{SupervisedTask, Supervisor, SupervisorConfig},
+        sync::Synchroniser,
+        telemetry::Monitor,
+        ui_update::PendingUpdate,
+        warn,
     };
     use totem_board::{
         board::Board,
-        constants::LED_BUFFER_SIZE,
-        peripheral::{ErcpSerial, LedStrip, Screen},
+        constants::{LED_BUFFER_SIZE, LED_LAYOUT},
+        identity::unique_id,
+        peripheral::{ErcpSerial, LedStrip, SyncRx, SyncTx, Watchdog},
         prelude::*,
+        reset::ResetFlags,
+        storage::SettingsFlash,
+    };
+    use totem_protocol::{
+        cue::Cue,
+        identity::{DeviceId, Name},
+        inputs::InputReadings,
+        preset::PRESET_COUNT,
+        self_test::SelfTestReport,
+        show::ShowAction,
+        telemetry::AdcReadings,
+        Response,
     };
+    use totem_sync::{Correction, FrameReader, MAX_FRAME_SIZE};
     use totem_ui::{
-        state::{Brightness, Mode, ScreenState, UIState},
+        state::{Brightness, Mode, ScreenState, UIState, UIStatePatch},
         UI as _,
     };
-    use totem_utils::{delay::AsmDelay, fake_timer::FakeTimer};
+    use totem_utils::{delay::AsmDelay, monotonic_timer::MonotonicTimer};
 
     #[cfg(feature = "ui_graphical")]
     use totem_ui::GraphicalUI;
@@ -74,7 +111,7 @@ mod app {
     #[cfg(feature = "ui_physical")]
     use totem_board::peripheral::{B1, R1, R2, R3, S1};
     #[cfg(feature = "ui_physical")]
-    use totem_ui::PhysicalUI;
+    use totem_ui::{physical::SpareInputs, PhysicalUI};
 
     const MESSAGES: [(&str, &str); 10] = [
         (" Chateau Perche ", "  Avrilly 2022  "),
@@ -94,27 +131,50 @@ mod app {
     ////////////////////////////////////////////////////////////////////////////
 
     #[monotonic(binds = SysTick, default = true)]
-    type Monotonic = Systick<100>;
+    type Monotonic = DwtSystick<MONOTONIC_RATE>;
 
     #[shared]
     struct SharedResources {
         ui: UI,
-        screen: Option<Screen>,
-        ercp: ErcpBasic<SerialAdapter<ErcpSerial>, FakeTimer, TotemRouter>,
+        screen: ManagedScreen,
+        ercp: ErcpLink<SerialAdapter<ErcpSerial>, ErcpTimer>,
+        monitor: Monitor,
+        sync: Synchroniser,
+        pending_update: PendingUpdate,
+        host_watchdog: HostWatchdog,
+        settings: Settings<SettingsFlash>,
+        supervisor: Supervisor,
+        led_mailbox: LedMailbox,
+        show_player: ShowPlayer,
     }
 
     #[local]
     struct LocalResources {
         // UI task
         ui_state: UIState,
+        sync_tx: SyncTx,
+        b2_gestures: GestureDetector,
 
         // LED task
         led_strip: LedStrip,
         brightness: Brightness,
         time_config: TimeConfig,
         chaser: Chaser,
+        cue_layer: CueLayer,
+
+        // Screen task
+        device_id: DeviceId,
+
+        // Synchronisation reception task
+        sync_rx: SyncRx,
+        frame_reader: FrameReader,
+
+        // Supervisor task
+        watchdog: Watchdog,
     }
 
+    type ErcpTimer = MonotonicTimer<TimerInstantU64<MONOTONIC_RATE>>;
+
     #[cfg(feature = "ui_physical")]
     type UI = PhysicalUI<R1, R2, R3, S1, B1>;
     #[cfg(feature = "ui_graphical")]
@@ -124,17 +184,13 @@ mod app {
     //                             Message types                              //
     ////////////////////////////////////////////////////////////////////////////
 
-    #[derive(Debug, Format)]
-    pub enum LedTaskMessage {
-        UpdateMode(UIState),
-        Next,
-    }
-
     #[derive(Debug, Format)]
     pub enum ScreenTaskMessage {
         Start,
         Stop,
         Next,
+        Identity(Name),
+        Reset(ResetInfo),
     }
 
     ////////////////////////////////////////////////////////////////////////////
@@ -144,6 +200,40 @@ mod app {
     /// The refresh rate for the update task.
     const REFRESH_RATE: Hertz = Hertz(50);
 
+    /// The period between two frames of the LED effects, in milliseconds.
+    const FRAME_PERIOD_MS: u32 = 1000 / REFRESH_RATE.0;
+
+    /// The period between two frames of the LED effects, in microseconds.
+    const FRAME_PERIOD_US: u32 = 1_000_000 / REFRESH_RATE.0;
+
+    /// The maximum number of frames skipped at once when a frame starts late.
+    const MAX_FRAME_SKIP: u32 = 10;
+
+    /// The tick rate of the monotonic timer, in Hz.
+    ///
+    /// The monotonic timer counts the core cycles with the DWT and only
+    /// interrupts on the next scheduled task, so the frames start within a few
+    /// cycles of their deadline without a periodic tick.
+    const MONOTONIC_RATE: u32 = SYSCLK;
+
+    /// The number of ticks of the monotonic timer per microsecond.
+    const TICKS_PER_US: u64 = {
+        assert!(MONOTONIC_RATE % 1_000_000 == 0);
+        (MONOTONIC_RATE / 1_000_000) as u64
+    };
+
+    /// The system clock frequency, in Hz.
+    const SYSCLK: u32 = 80_000_000;
+
+    /// The liveness supervision of the tasks by the independent watchdog.
+    const SUPERVISOR_CONFIG: SupervisorConfig = SupervisorConfig {
+        watchdog_timeout_ms: 2000,
+        check_period_ms: 100,
+        ui_deadline_ms: Some(500),
+        led_deadline_ms: Some(500),
+        ercp_deadline_ms: Some(1000),
+    };
+
     ////////////////////////////////////////////////////////////////////////////
     //                                  Init                                  //
     ////////////////////////////////////////////////////////////////////////////
@@ -152,21 +242,40 @@ mod app {
     fn init(
         cx: init::Context,
     ) -> (SharedResources, LocalResources, init::Monotonics) {
-        defmt::info!("Firmware starting...");
+        totem_app::log::init(now_ms);
+        info!("Firmware starting...");
 
-        let cp = cx.core;
+        let mut cp = cx.core;
         let dp = cx.device;
 
         ////////////////////////////////////////////////////////////////////////
         //                            System init                             //
         ////////////////////////////////////////////////////////////////////////
 
-        let monotonic = Systick::new(cp.SYST, 80_000_000);
+        // The monotonic timer enables the cycle counter, which is also used to
+        // measure the execution time of tasks.
+        let monotonic = DwtSystick::new(&mut cp.DCB, cp.DWT, cp.SYST, SYSCLK);
 
         ////////////////////////////////////////////////////////////////////////
         //                            Board init                              //
         ////////////////////////////////////////////////////////////////////////
 
+        let reset = ResetInfo::gather(ResetFlags::take(&dp.RCC));
+        info!(
+            "Reset cause: {}, {} resets since power-on.",
+            reset.cause, reset.resets
+        );
+        if let Some(ref panic) = reset.panic {
+            let panic = panic.report();
+            error!(
+                "Panicked at {}:{}:{}: {}",
+                panic.file, panic.line, panic.column, panic.message
+            );
+        }
+
+        let mut board = Board::init(dp, cx.local.led_buffer);
+        let self_test_report = self_test(&mut board);
+
         #[allow(unused)]
         let Board {
             r1,
@@ -180,15 +289,22 @@ mod app {
             microphone,
             p_adc,
             mut led_strip,
-            mut screen,
+            screen,
             ercp_serial,
-        } = Board::init(dp, cx.local.led_buffer);
+            sync_serial,
+            settings_flash,
+            mut watchdog,
+            rng,
+        } = board;
+
+        if screen.is_none() {
+            warn!("No screen detected, running without it.");
+        }
 
         // Ensure both the LED strip and screen start off.
-        led_strip.off();
-        if let Some(ref mut screen) = screen {
-            screen.set_rgb(0, 0, 0).unwrap();
-        }
+        led_strip.off(LED_LAYOUT);
+        let mut screen = ManagedScreen::new(screen);
+        screen.with(|screen| screen.set_rgb(0, 0, 0));
 
         ////////////////////////////////////////////////////////////////////////
         //                          Resources init                            //
@@ -196,40 +312,127 @@ mod app {
 
         // Shared
 
+        // Seed the generator for authentication challenges from the hardware
+        // random number generator.
+        let mut seed = [0; 32];
+        for chunk in seed.chunks_exact_mut(4) {
+            chunk.copy_from_slice(&rng.get_random_data().to_le_bytes());
+        }
+
         #[cfg(feature = "ui_physical")]
-        let ui = PhysicalUI::new(p_adc, r1, r2, r3, s1, b1);
+        let mut ui = PhysicalUI::new(
+            p_adc,
+            r1,
+            r2,
+            r3,
+            s1,
+            b1,
+            SpareInputs {
+                r4,
+                s2,
+                b2,
+                microphone,
+            },
+        );
         #[cfg(feature = "ui_graphical")]
-        let ui = GraphicalUI::new();
+        let mut ui = GraphicalUI::new();
+
+        let settings = Settings::new(settings_flash);
+        restore_state(&mut ui, &settings);
+
+        let device_id = DeviceId::from_uid(&unique_id());
+        let name = settings.name();
+        info!("Device ID: {}, name: {}.", device_id, name);
 
         let adapter = SerialAdapter::new(ercp_serial);
-        let ercp = ErcpBasic::new(adapter, FakeTimer, TotemRouter);
+        let timer = MonotonicTimer::new(monotonics::now);
+        let secret = settings.secret();
+        let router = TotemRouter::new(
+            seed,
+            secret.as_deref(),
+            device_id,
+            name,
+            reset.clone(),
+        );
+        let ercp = ErcpLink::new(adapter, timer, router);
+        let mut monitor = Monitor::new();
+        if let Some(report) = self_test_report {
+            monitor.record_self_test(report);
+        }
+
+        let sync = Synchroniser::new(FRAME_PERIOD_MS);
+        let pending_update = PendingUpdate::new();
+        let mut host_watchdog = HostWatchdog::new();
+        restore_host_watchdog(&mut host_watchdog, &settings);
+        let supervisor = Supervisor::new(SUPERVISOR_CONFIG);
+        let led_mailbox = LedMailbox::new();
+        let show_player = ShowPlayer::new();
 
         // UI task
 
         let ui_state = UIState::default();
+        let (sync_tx, sync_rx) = sync_serial.split();
+        let b2_gestures = GestureDetector::new();
 
         // LED task
 
         let brightness = Brightness::default();
         let time_config = TimeConfig::new(REFRESH_RATE, Seconds(1));
         let chaser = Chaser::None;
+        let cue_layer = CueLayer::new();
+
+        // Synchronisation reception task
+
+        let frame_reader = FrameReader::new();
+
+        // Supervisor task
 
-        defmt::info!("Firmware initialised!");
+        // The watchdog is started last, as the self-test blocks for a while.
+        watchdog.start(SUPERVISOR_CONFIG.watchdog_timeout_ms.millis());
+
+        info!("Firmware initialised!");
 
         ////////////////////////////////////////////////////////////////////////
         //                           Task startup                             //
         ////////////////////////////////////////////////////////////////////////
 
         ui_task::spawn().unwrap();
+        supervisor_task::spawn().unwrap();
+
+        // After an unexpected reset, show its cause instead of the identity.
+        if reset.cause.is_expected() {
+            screen_task::spawn(ScreenTaskMessage::Identity(name)).unwrap();
+        } else {
+            screen_task::spawn(ScreenTaskMessage::Reset(reset)).unwrap();
+        }
 
         (
-            SharedResources { ui, screen, ercp },
+            SharedResources {
+                ui,
+                screen,
+                ercp,
+                monitor,
+                sync,
+                pending_update,
+                host_watchdog,
+                settings,
+                supervisor,
+                led_mailbox,
+                show_player,
+            },
             LocalResources {
                 ui_state,
+                sync_tx,
+                b2_gestures,
                 led_strip,
                 brightness,
                 time_config,
                 chaser,
+                cue_layer,
+                device_id,
+                sync_rx,
+                frame_reader,
+                watchdog,
             },
             init::Monotonics(monotonic),
         )
@@ -251,139 +454,336 @@ mod app {
     //                                 Tasks                                  //
     ////////////////////////////////////////////////////////////////////////////
 
-    #[task(priority = 1, local = [ui_state], shared = [ui])]
-    fn ui_task(mut cx: ui_task::Context) {
-        let ui_task::LocalResources { ui_state } = cx.local;
+    #[task(
+        priority = 1,
+        local = [
+            ui_state,
+            sync_tx,
+            b2_gestures,
+            preset_slot: usize = 0,
+            screen_running: bool = false,
+        ],
+        shared = [
+            ui,
+            sync,
+            pending_update,
+            host_watchdog,
+            settings,
+            supervisor,
+            monitor,
+            led_mailbox,
+        ],
+    )]
+    fn ui_task(cx: ui_task::Context) {
+        let ui_task::LocalResources {
+            ui_state,
+            sync_tx,
+            b2_gestures,
+            preset_slot,
+            screen_running,
+        } = cx.local;
+        let ui_task::SharedResources {
+            mut ui,
+            mut sync,
+            mut pending_update,
+            mut host_watchdog,
+            mut settings,
+            mut supervisor,
+            mut monitor,
+            mut led_mailbox,
+        } = cx.shared;
+
+        // Without a next run, the supervisor lets the watchdog reset the Totem.
+        if ui_task::spawn_at(monotonics::now() + 10.millis()).is_ok() {
+            supervisor.lock(|sup| sup.check_in(SupervisedTask::Ui, now_ms()));
+        } else {
+            error!("Cannot spawn ui_task.");
+            monitor.lock(|monitor| monitor.record_spawn_failure());
+        }
 
-        ui_task::spawn_at(monotonics::now() + 10.millis()).unwrap();
+        check_host_link(&mut host_watchdog, &mut pending_update, &mut ui);
 
-        let state = cx.shared.ui.lock(|ui| ui.read_state());
+        // Apply the updates received over ERCP since the last run.
+        if let Some(patch) = pending_update.lock(|pending| pending.take()) {
+            ui.lock(|ui| apply_patch(ui, &patch));
+        }
 
-        if state != *ui_state {
-            defmt::debug!("UI State: {:?}", state);
+        let b2_pressed = ui.lock(is_b2_pressed);
+        let cue = match b2_gestures.update(b2_pressed, now_ms()) {
+            Some(Gesture::Tap) => Some(TAP_CUE),
+            Some(Gesture::DoubleTap) => Some(DOUBLE_TAP_CUE),
+            Some(Gesture::Hold) => Some(HOLD_CUE),
+
+            // Recall the next saved preset, through the same path as the
+            // updates received over ERCP.
+            Some(Gesture::TripleTap) => {
+                let presets = settings.lock(|settings| settings.presets());
+                let next = (1..=PRESET_COUNT)
+                    .map(|offset| (*preset_slot + offset) % PRESET_COUNT)
+                    .find_map(|slot| Some((slot, presets[slot]?)));
+
+                if let Some((slot, state)) = next {
+                    info!("Recalling preset {}.", slot);
+                    *preset_slot = slot;
+                    pending_update
+                        .lock(|pending| pending.merge(&state.into(), now_ms()));
+                }
 
-            led_task::spawn(LedTaskMessage::UpdateMode(state)).unwrap();
+                None
+            }
 
-            if state.mode != ui_state.mode
-                || state.screen_state != ui_state.screen_state
-            {
-                let screen_message = match (state.mode, state.screen_state) {
-                    (Mode::Off, _) => ScreenTaskMessage::Stop,
-                    (_, ScreenState::Off) => ScreenTaskMessage::Stop,
-                    (_, ScreenState::On) => ScreenTaskMessage::Start,
-                };
+            // Save the current state in the last recalled preset.
+            Some(Gesture::LongHold) => {
+                let state = ui.lock(|ui| ui.read_state());
+                let slot = *preset_slot;
+
+                match settings
+                    .lock(|settings| settings.save_preset(slot, state))
+                {
+                    Ok(()) => {
+                        info!("Preset {} saved.", slot);
+                        Some(PRESET_SAVED_CUE)
+                    }
 
-                screen_task::spawn(screen_message).unwrap();
+                    Err(_) => {
+                        warn!("Cannot save preset {}.", slot);
+                        None
+                    }
+                }
             }
 
+            None => None,
+        };
+
+        if let Some(cue) = cue {
+            fire_cue(&mut led_mailbox, cue);
+        }
+
+        // Only the local state is saved, not the one of the leader.
+        let local_state = ui.lock(|ui| ui.read_state());
+        settings.lock(|settings| save_state(settings, local_state));
+
+        // Follow the state of the leader while it is present, and fall back to
+        // the local UI otherwise.
+        let state = sync
+            .lock(|sync| sync.state(now_ms()))
+            .unwrap_or(local_state);
+
+        if state != *ui_state {
+            defmt::debug!("UI State: {:?}", state);
+            led_mailbox.lock(|mailbox| mailbox.post_state(state));
+            wake_led_task();
             *ui_state = state;
         }
+
+        // Start or stop the screen, trying again on the next run on failure.
+        let screen_on =
+            state.mode != Mode::Off && state.screen_state == ScreenState::On;
+        if screen_on != *screen_running {
+            let message = if screen_on {
+                ScreenTaskMessage::Start
+            } else {
+                ScreenTaskMessage::Stop
+            };
+
+            if screen_task::spawn(message).is_ok() {
+                *screen_running = screen_on;
+            } else {
+                warn!("Cannot spawn screen_task, trying again.");
+                monitor.lock(|monitor| monitor.record_spawn_failure());
+            }
+        }
+
+        let message = sync.lock(|sync| sync.broadcast(now_ms(), *ui_state));
+        if let Some(message) = message {
+            let mut buffer = [0; MAX_FRAME_SIZE];
+            match message.encode(&mut buffer) {
+                Ok(frame) => {
+                    if sync_tx.bwrite_all(frame).is_err() {
+                        warn!("Cannot send the sync message.");
+                    }
+                }
+                Err(_) => warn!("Cannot encode the sync message."),
+            }
+        }
     }
 
     #[task(
         priority = 2,
-        capacity = 2,
         local = [
             led_strip,
             time_config,
             brightness,
             chaser,
+            cue_layer,
             drive_screen: bool = false,
+            frame_clock: FrameClock = FrameClock::new(FRAME_PERIOD_US),
         ],
-        shared = [screen],
+        shared = [screen, monitor, sync, supervisor, led_mailbox],
     )]
-    fn led_task(mut cx: led_task::Context, message: LedTaskMessage) {
+    fn led_task(cx: led_task::Context) {
         let led_task::LocalResources {
             led_strip,
             time_config,
             brightness,
             chaser,
+            cue_layer,
             drive_screen,
+            frame_clock,
         } = cx.local;
 
-        match message {
-            LedTaskMessage::UpdateMode(ui_state) => {
-                match ui_state.mode {
-                    Mode::Off => {
-                        if !matches!(chaser, Chaser::None) {
-                            defmt::info!("Switching to Off mode.");
-                            led_strip.off();
-                            *chaser = Chaser::None;
-                        }
+        let led_task::SharedResources {
+            mut screen,
+            mut monitor,
+            mut sync,
+            mut supervisor,
+            mut led_mailbox,
+        } = cx.shared;
+
+        let start = DWT::cycle_count();
+
+        // Apply the updates posted since the last frame.
+        let (ui_state, cue) = led_mailbox
+            .lock(|mailbox| (mailbox.take_state(), mailbox.take_cue()));
+
+        if let Some(ui_state) = ui_state {
+            match ui_state.mode {
+                Mode::Off => {
+                    if !matches!(chaser, Chaser::None) {
+                        info!("Switching to Off mode.");
+                        *chaser = Chaser::None;
+                        sync.lock(|sync| sync.reset_phase());
                     }
+                }
 
-                    Mode::RandomUnicolor => {
-                        if !matches!(chaser, Chaser::RandomUnicolor(_)) {
-                            defmt::info!("Switching to RandomUnicolor mode.");
-
-                            if matches!(chaser, Chaser::None) {
-                                led_task::spawn(LedTaskMessage::Next).unwrap();
-                            }
-
-                            *chaser =
-                                Chaser::RandomUnicolor(RandomUnicolor::new(
-                                    REFRESH_RATE,
-                                    Uniform::new(0, 255),
-                                    Uniform::new(300, 5_000),
-                                ));
-                        }
+                Mode::RandomUnicolor => {
+                    if !matches!(chaser, Chaser::RandomUnicolor(_)) {
+                        info!("Switching to RandomUnicolor mode.");
+                        *chaser = Chaser::RandomUnicolor(RandomUnicolor::new(
+                            REFRESH_RATE,
+                            Uniform::new(0, 255),
+                            Uniform::new(300, 5_000),
+                        ));
+                        sync.lock(|sync| sync.reset_phase());
                     }
+                }
 
-                    Mode::RainbowFontain => {
-                        if !matches!(chaser, Chaser::RainbowFontain(_)) {
-                            defmt::info!("Switching to RainbowFontain mode.");
-
-                            if matches!(chaser, Chaser::None) {
-                                led_task::spawn(LedTaskMessage::Next).unwrap();
-                            }
-
-                            *chaser =
-                                Chaser::RainbowFontain(RainbowChaser::new(
-                                    DuplicateConfig {
-                                        config: RainbowConfig {
-                                            first_color: RED.into(),
-                                            range: 150,
-                                        },
-                                        duplicates: 8,
-                                    },
-                                    time_config,
-                                ));
-                        }
+                Mode::RainbowFontain => {
+                    if !matches!(chaser, Chaser::RainbowFontain(_)) {
+                        info!("Switching to RainbowFontain mode.");
+                        *chaser = Chaser::RainbowFontain(RainbowChaser::new(
+                            DuplicateConfig {
+                                config: RainbowConfig {
+                                    first_color: RED.into(),
+                                    range: 150,
+                                },
+                                duplicates: LED_LAYOUT.strips,
+                            },
+                            time_config,
+                        ));
+                        sync.lock(|sync| sync.reset_phase());
                     }
                 }
-
-                *brightness = ui_state.brightness;
-                time_config.transition_time = ui_state.speed.transition_time();
-                chaser.set_time_config(time_config);
-                chaser.set_temperature(ui_state.temperature);
-                *drive_screen = ui_state.screen_state == ScreenState::On;
             }
 
-            LedTaskMessage::Next => {
-                if let Some(sequence) = chaser.next() {
-                    let period = (1000 / time_config.refresh_rate.0).millis();
-                    led_task::spawn_at(
-                        monotonics::now() + period,
-                        LedTaskMessage::Next,
-                    )
-                    .unwrap();
-
-                    if *drive_screen {
-                        let color = sequence.config().main_color();
-                        cx.shared.screen.lock(|screen| {
-                            if let Some(screen) = screen {
-                                screen
-                                    .set_rgb(color.r, color.g, color.b)
-                                    .unwrap();
-                            }
-                        });
-                    }
+            *brightness = ui_state.brightness;
+            time_config.transition_time = ui_state.speed.transition_time();
+            chaser.set_time_config(time_config);
+            chaser.set_temperature(ui_state.temperature);
+            *drive_screen = ui_state.screen_state == ScreenState::On;
+        }
 
-                    led_strip
-                        .write(set_brightness(sequence, brightness.value()))
-                        .unwrap();
-                }
+        if let Some(cue) = cue {
+            defmt::debug!("Firing cue: {:?}", cue);
+            cue_layer.fire(cue, now_ms());
+        }
+
+        // Frames are not rendered in Off mode, except for the duration of a
+        // cue.
+        if !is_running(chaser, cue_layer) {
+            let write = led_strip.off(LED_LAYOUT);
+            monitor.lock(|monitor| monitor.record_frame_write(write));
+            frame_clock.stop();
+            supervisor.lock(|sup| sup.idle(SupervisedTask::Led));
+            return;
+        }
+
+        // Frames are scheduled on absolute deadlines, so that the rendering
+        // time does not make the frame rate drift.
+        let tick = frame_clock.tick(now_us());
+        let next_frame = instant_at_us(tick.next_deadline_us);
+        monitor.lock(|monitor| monitor.record_frame_tick(now_ms(), tick));
+
+        // Stay in phase with the leader when following one.
+        let correction = sync.lock(|sync| sync.correction(now_ms()));
+        let skip = match correction {
+            Correction::None => 0,
+            Correction::Skip(frames) => frames,
+
+            Correction::Hold => {
+                spawn_next(
+                    led_task::spawn_at(next_frame),
+                    &mut monitor,
+                    &mut supervisor,
+                );
+
+                return;
             }
+        };
+
+        // Also skip the frames whose deadline has passed, so that the effects
+        // keep their speed when a frame starts late.
+        let skip = skip + tick.skipped.min(MAX_FRAME_SKIP);
+
+        let overlay = cue_layer.overlay(now_ms());
+
+        if let Some(sequence) = chaser.nth(skip as usize) {
+            sync.lock(|sync| sync.advance(skip + 1));
+
+            spawn_next(
+                led_task::spawn_at(next_frame),
+                &mut monitor,
+                &mut supervisor,
+            );
+
+            if *drive_screen {
+                let color = overlay.blend(sequence.config().main_color());
+                screen.lock(|screen| {
+                    screen.with(|screen| {
+                        screen.set_rgb(color.r, color.g, color.b)
+                    })
+                });
+            }
+
+            let pixels = sequence.map(|pixel| overlay.blend(pixel));
+            let write = led_strip
+                .write_frame(set_brightness(pixels, brightness.value()));
+            monitor.lock(|monitor| monitor.record_frame_write(write));
+
+            let cycles = DWT::cycle_count().wrapping_sub(start);
+            let execution_time_us = cycles / (SYSCLK / 1_000_000);
+            monitor.lock(|monitor| {
+                monitor.record_frame(now_ms(), execution_time_us)
+            });
+        } else if cue_layer.is_active() {
+            // In Off mode, render the cue alone.
+            spawn_next(
+                led_task::spawn_at(next_frame),
+                &mut monitor,
+                &mut supervisor,
+            );
+
+            let pixels = (0..LED_LAYOUT.num_leds())
+                .map(|_| overlay.blend(RGB8::default()));
+            let write = led_strip
+                .write_frame(set_brightness(pixels, brightness.value()));
+            monitor.lock(|monitor| monitor.record_frame_write(write));
+        } else if matches!(chaser, Chaser::None) {
+            // The cue has ended in Off mode.
+            let write = led_strip.off(LED_LAYOUT);
+            monitor.lock(|monitor| monitor.record_frame_write(write));
+            frame_clock.stop();
+            supervisor.lock(|sup| sup.idle(SupervisedTask::Led));
         }
     }
 
@@ -391,94 +791,722 @@ mod app {
         priority = 1,
         capacity = 2,
         local = [
+            device_id,
             next_handle: Option<screen_task::SpawnHandle> = None,
             messages: [(&'static str, &'static str); MESSAGES.len()] = MESSAGES,
             index: usize = 0,
+            running: bool = false,
         ],
-        shared = [screen],
+        shared = [screen, monitor],
     )]
     fn screen_task(mut cx: screen_task::Context, message: ScreenTaskMessage) {
         let screen_task::LocalResources {
+            device_id,
             next_handle,
             messages,
             index,
+            running,
         } = cx.local;
 
+        let mut spawn_failed = false;
+
         cx.shared.screen.lock(|screen| {
-            if let Some(screen) = screen {
+            screen.with(|screen| {
                 match message {
                     ScreenTaskMessage::Start => {
-                        if next_handle.is_none() {
-                            screen.set_rgb(255, 255, 255).unwrap();
-                            screen_task::spawn(ScreenTaskMessage::Next)
-                                .unwrap();
+                        if !*running {
+                            *running = true;
+                            screen.set_rgb(255, 255, 255)?;
+
+                            // When the identity is shown, the messages start
+                            // after it.
+                            if next_handle.is_none()
+                                && screen_task::spawn(ScreenTaskMessage::Next)
+                                    .is_err()
+                            {
+                                spawn_failed = true;
+                            }
                         }
                     }
 
                     ScreenTaskMessage::Stop => {
                         if let Some(handle) = next_handle.take() {
-                            handle.cancel().unwrap();
+                            handle.cancel().ok();
                         }
 
-                        let mut delay = AsmDelay::new(80_000_000);
-                        screen.clear(&mut delay).unwrap();
-                        screen.set_rgb(0, 0, 0).unwrap();
+                        let mut delay = AsmDelay::new(SYSCLK);
+                        screen.clear(&mut delay)?;
+                        screen.set_rgb(0, 0, 0)?;
                         *index = 0;
+                        *running = false;
+                    }
+
+                    ScreenTaskMessage::Next if !*running => {
+                        // The identity has been shown while the screen is off.
+                        *next_handle = None;
+
+                        let mut delay = AsmDelay::new(SYSCLK);
+                        screen.clear(&mut delay)?;
+                        screen.set_rgb(0, 0, 0)?;
                     }
 
                     ScreenTaskMessage::Next => {
-                        let handle = screen_task::spawn_at(
+                        *next_handle = screen_task::spawn_at(
                             monotonics::now() + 4.secs(),
                             ScreenTaskMessage::Next,
                         )
-                        .unwrap();
+                        .ok();
 
-                        *next_handle = Some(handle);
+                        spawn_failed = next_handle.is_none();
 
-                        let mut delay = AsmDelay::new(80_000_000);
-                        screen.clear(&mut delay).unwrap();
-                        screen.set_cursor_position(0, 0).unwrap();
-                        screen.write_str(messages[*index].0).unwrap();
-                        screen.set_cursor_position(0, 1).unwrap();
-                        screen.write_str(messages[*index].1).unwrap();
+                        let mut delay = AsmDelay::new(SYSCLK);
+                        screen.clear(&mut delay)?;
+                        screen.set_cursor_position(0, 0)?;
+                        screen.write_str(messages[*index].0)?;
+                        screen.set_cursor_position(0, 1)?;
+                        screen.write_str(messages[*index].1)?;
 
                         *index += 1;
                         if *index == messages.len() {
                             *index = 0;
                         }
                     }
+
+                    ScreenTaskMessage::Identity(name) => {
+                        if let Some(handle) = next_handle.take() {
+                            handle.cancel().ok();
+                        }
+
+                        // Show the identity for a while, then resume the
+                        // messages or turn the screen off.
+                        *next_handle = screen_task::spawn_at(
+                            monotonics::now() + 4.secs(),
+                            ScreenTaskMessage::Next,
+                        )
+                        .ok();
+
+                        spawn_failed = next_handle.is_none();
+
+                        let name = if name.is_empty() {
+                            "Totem"
+                        } else {
+                            name.as_str()
+                        };
+
+                        let mut id = String::<16>::new();
+                        write!(id, "ID {}", device_id).ok();
+
+                        let mut delay = AsmDelay::new(SYSCLK);
+                        screen.set_rgb(255, 255, 255)?;
+                        screen.clear(&mut delay)?;
+                        screen.set_cursor_position(0, 0)?;
+                        screen.write_str(&centre(name))?;
+                        screen.set_cursor_position(0, 1)?;
+                        screen.write_str(&centre(&id))?;
+                    }
+
+                    ScreenTaskMessage::Reset(reset) => {
+                        if let Some(handle) = next_handle.take() {
+                            handle.cancel().ok();
+                        }
+
+                        // Show the cause of the reset for a while, then resume
+                        // the messages or turn the screen off.
+                        *next_handle = screen_task::spawn_at(
+                            monotonics::now() + 4.secs(),
+                            ScreenTaskMessage::Next,
+                        )
+                        .ok();
+
+                        spawn_failed = next_handle.is_none();
+
+                        let mut cause = String::<16>::new();
+                        write!(cause, "{} #{}", reset.cause, reset.resets).ok();
+
+                        let mut location = String::<16>::new();
+                        if let Some(panic) = &reset.panic {
+                            write!(
+                                location,
+                                "{}:{}",
+                                panic.file_name(),
+                                panic.line()
+                            )
+                            .ok();
+                        }
+
+                        let mut delay = AsmDelay::new(SYSCLK);
+                        screen.set_rgb(255, 0, 0)?;
+                        screen.clear(&mut delay)?;
+                        screen.set_cursor_position(0, 0)?;
+                        screen.write_str(&centre(&cause))?;
+                        screen.set_cursor_position(0, 1)?;
+                        screen.write_str(&centre(&location))?;
+                    }
                 }
-            }
-        })
+
+                Ok(())
+            })
+        });
+
+        if spawn_failed {
+            warn!("Cannot spawn screen_task.");
+            cx.shared
+                .monitor
+                .lock(|monitor| monitor.record_spawn_failure());
+        }
     }
 
-    #[task(priority = 3, binds = USART2, shared = [ercp])]
-    fn usart2(mut cx: usart2::Context) {
+    #[task(priority = 3, binds = USART2, shared = [ercp, monitor, supervisor])]
+    fn usart2(cx: usart2::Context) {
         defmt::trace!("Receiving data on UART");
 
-        cx.shared.ercp.lock(|ercp| {
-            ercp.handle_data().ok();
+        let usart2::SharedResources {
+            mut ercp,
+            mut monitor,
+            mut supervisor,
+        } = cx.shared;
+
+        match ercp.lock(ercp::receive) {
+            Reception::Incomplete => (),
 
-            if ercp.complete_frame_received() {
+            Reception::Complete => {
                 defmt::trace!("Complete frame received!");
-                ercp_process::spawn().ok();
+
+                // The frame is expected to be processed before the deadline.
+                if ercp_process::spawn().is_ok() {
+                    supervisor.lock(|sup| {
+                        sup.check_in(SupervisedTask::Ercp, now_ms())
+                    });
+                } else {
+                    warn!("Cannot spawn ercp_process, dropping the frame.");
+                    monitor.lock(|monitor| monitor.record_spawn_failure());
+                }
             }
-        });
+
+            Reception::Error => {
+                warn!("Error while receiving an ERCP frame.");
+                monitor.lock(|monitor| monitor.record_ercp_rx_error());
+            }
+        }
     }
 
-    #[task(priority = 1, shared = [ui, ercp])]
+    #[task(
+        priority = 3,
+        binds = USART1,
+        local = [sync_rx, frame_reader],
+        shared = [sync],
+    )]
+    fn usart1(mut cx: usart1::Context) {
+        let usart1::LocalResources {
+            sync_rx,
+            frame_reader,
+        } = cx.local;
+
+        while let Ok(byte) = sync_rx.read() {
+            if let Some(message) = frame_reader.push(byte) {
+                defmt::trace!("Sync message received: {:?}", message);
+                cx.shared.sync.lock(|sync| sync.receive(&message, now_ms()));
+            }
+        }
+    }
+
+    #[task(
+        priority = 1,
+        shared = [
+            ui,
+            screen,
+            ercp,
+            monitor,
+            pending_update,
+            host_watchdog,
+            settings,
+            supervisor,
+            led_mailbox,
+            show_player,
+        ],
+    )]
     fn ercp_process(cx: ercp_process::Context) {
         defmt::debug!("ERCP frame received. Processing it…");
 
-        #[allow(unused)]
-        let ercp_process::SharedResources { mut ui, mut ercp } = cx.shared;
+        let ercp_process::SharedResources {
+            mut ui,
+            mut screen,
+            mut ercp,
+            mut monitor,
+            mut pending_update,
+            mut host_watchdog,
+            mut settings,
+            mut supervisor,
+            mut led_mailbox,
+            mut show_player,
+        } = cx.shared;
+
+        let mut context = ErcpContext {
+            now_ms: now_ms(),
+            ui_busy: pending_update.lock(|pending| pending.is_busy(now_ms())),
+            ..Default::default()
+        };
+
+        if !ercp.lock(|ercp| ercp::process(ercp, &mut context)) {
+            warn!("Error while processing an ERCP frame.");
+            monitor.lock(|monitor| monitor.record_ercp_process_error());
+        }
 
-        let mut context = ErcpContext::default();
-        ercp.lock(|ercp| ercp.process(&mut context).ok());
+        // Gather the data needed by the request, if any, only now.
+        if let Some(query) = context.query {
+            let answer = gather(
+                query,
+                &mut ui,
+                &mut screen,
+                &mut monitor,
+                &mut host_watchdog,
+                &mut settings,
+                &mut show_player,
+            );
+
+            if !ercp.lock(|ercp| ercp::answer(ercp, answer, &mut context)) {
+                warn!("Error while answering an ERCP request.");
+                monitor.lock(|monitor| monitor.record_ercp_process_error());
+            }
+        }
 
         #[cfg(feature = "ui_graphical")]
-        if let Some(state) = context.ui_state_update {
-            ui.lock(|ui| ui.set_state(state));
+        if let Some(control) = context.show_control.take() {
+            let result = show_player
+                .lock(|player| control.apply(player, context.now_ms));
+            let changed = result.is_ok() && control.changes_playback();
+            let answer = Answer::Show(result);
+
+            if !ercp.lock(|ercp| ercp::answer(ercp, answer, &mut context)) {
+                warn!("Error while answering an ERCP request.");
+                monitor.lock(|monitor| monitor.record_ercp_process_error());
+            }
+
+            if changed && show_task::spawn().is_err() {
+                warn!("Cannot spawn show_task.");
+                monitor.lock(|monitor| monitor.record_spawn_failure());
+            }
+        }
+
+        if let Some(cue) = context.cue {
+            fire_cue(&mut led_mailbox, cue);
+        }
+
+        if let Some(slot) = context.preset_save {
+            let state = ui.lock(|ui| ui.read_state());
+            match settings.lock(|settings| settings.save_preset(slot, state)) {
+                Ok(()) => info!("Preset {} saved.", slot),
+                Err(_) => warn!("Cannot save preset {}.", slot),
+            }
+        }
+
+        if let Some(name) = context.name_change {
+            match settings.lock(|settings| settings.save_name(name)) {
+                Ok(()) => info!("Name set to {}.", name),
+                Err(_) => warn!("Cannot save the name."),
+            }
+
+            if screen_task::spawn(ScreenTaskMessage::Identity(name)).is_err() {
+                warn!("Cannot spawn screen_task.");
+                monitor.lock(|monitor| monitor.record_spawn_failure());
+            }
+        }
+
+        if let Some(secret) = context.secret_change {
+            match settings.lock(|settings| settings.save_secret(&secret)) {
+                Ok(()) => info!("Shared secret saved."),
+                Err(_) => warn!("Cannot save the shared secret."),
+            }
+        }
+
+        // The update is coalesced with the pending ones, and applied by the UI
+        // task on its next run.
+        if let Some(patch) = context.ui_state_patch {
+            pending_update.lock(|pending| pending.merge(&patch, now_ms()));
+        }
+
+        #[cfg(feature = "ui_graphical")]
+        if let Some(config) = context.host_watchdog_config {
+            let result =
+                settings.lock(|settings| settings.save_host_watchdog(config));
+            match result {
+                Ok(()) => info!("Host link watchdog configuration saved."),
+                Err(_) => warn!("Cannot save the host link watchdog config."),
+            }
+        }
+
+        host_watchdog.lock(|wdg| {
+            #[cfg(feature = "ui_graphical")]
+            if let Some(config) = context.host_watchdog_config {
+                info!("Host link watchdog configured: {:?}", config);
+                wdg.configure(config, now_ms());
+            }
+
+            if context.host_seen {
+                wdg.feed(now_ms());
+            }
+        });
+
+        supervisor.lock(|sup| sup.idle(SupervisedTask::Ercp));
+    }
+
+    #[task(
+        priority = 1,
+        local = [watchdog, stalled: bool = false],
+        shared = [supervisor],
+    )]
+    fn supervisor_task(mut cx: supervisor_task::Context) {
+        let supervisor_task::LocalResources { watchdog, stalled } = cx.local;
+
+        // Without a next check, the watchdog resets the Totem.
+        if supervisor_task::spawn_after(
+            SUPERVISOR_CONFIG.check_period_ms.millis(),
+        )
+        .is_err()
+        {
+            error!("Cannot spawn supervisor_task.");
+        }
+
+        // Stop feeding the watchdog as soon as a task has stalled, so that it
+        // resets the board.
+        match cx.shared.supervisor.lock(|sup| sup.stalled(now_ms())) {
+            None => watchdog.feed(),
+
+            Some(task) => {
+                if !*stalled {
+                    error!("The {} task has stalled, waiting for reset.", task);
+                    *stalled = true;
+                }
+            }
+        }
+    }
+
+    #[task(
+        priority = 1,
+        capacity = 2,
+        local = [next_handle: Option<show_task::SpawnHandle> = None],
+        shared = [show_player, pending_update, led_mailbox, monitor],
+    )]
+    fn show_task(cx: show_task::Context) {
+        let next_handle = cx.local.next_handle;
+        let show_task::SharedResources {
+            mut show_player,
+            mut pending_update,
+            mut led_mailbox,
+            mut monitor,
+        } = cx.shared;
+
+        // The show may have changed since the last run was scheduled.
+        if let Some(handle) = next_handle.take() {
+            handle.cancel().ok();
+        }
+
+        let now = now_ms();
+        while let Some(action) = show_player.lock(|player| player.poll(now)) {
+            match action {
+                ShowAction::Patch(patch) => {
+                    pending_update.lock(|pending| pending.merge(&patch, now));
+                }
+
+                ShowAction::Cue(cue) => fire_cue(&mut led_mailbox, cue),
+            }
+        }
+
+        if let Some(delay_ms) =
+            show_player.lock(|player| player.next_event_in(now))
+        {
+            *next_handle = show_task::spawn_after(delay_ms.millis()).ok();
+
+            if next_handle.is_none() {
+                warn!("Cannot spawn show_task.");
+                monitor.lock(|monitor| monitor.record_spawn_failure());
+            }
+        }
+    }
+
+    ////////////////////////////////////////////////////////////////////////////
+    //                                Helpers                                 //
+    ////////////////////////////////////////////////////////////////////////////
+
+    /// Gathers the data answering an ERCP query.
+    fn gather(
+        query: Query,
+        ui: &mut impl rtic::Mutex<T = UI>,
+        screen: &mut impl rtic::Mutex<T = ManagedScreen>,
+        monitor: &mut impl rtic::Mutex<T = Monitor>,
+        host_watchdog: &mut impl rtic::Mutex<T = HostWatchdog>,
+        settings: &mut impl rtic::Mutex<T = Settings<SettingsFlash>>,
+        show_player: &mut impl rtic::Mutex<T = ShowPlayer>,
+    ) -> Answer<'static> {
+        let response = match query {
+            Query::Telemetry => {
+                let adc_readings = ui.lock(adc_readings);
+                let screen_present =
+                    screen.lock(|screen| screen.is_available());
+
+                Response::Telemetry(monitor.lock(|monitor| {
+                    monitor.telemetry(
+                        now_ms(),
+                        REFRESH_RATE.0,
+                        screen_present,
+                        adc_readings,
+                    )
+                }))
+            }
+
+            Query::HostLinkStatus => {
+                Response::HostLinkStatus(host_watchdog.lock(|wdg| wdg.status()))
+            }
+
+            Query::Inputs => Response::Inputs(ui.lock(read_inputs)),
+
+            Query::Presets => {
+                Response::Presets(settings.lock(|settings| settings.presets()))
+            }
+
+            Query::PresetRecall(slot) => {
+                return Answer::Preset(
+                    settings.lock(|settings| settings.presets()[slot]),
+                );
+            }
+
+            Query::SelfTest => {
+                Response::SelfTest(monitor.lock(|monitor| monitor.self_test()))
+            }
+
+            Query::ErrorCounters => {
+                let screen_failed = screen.lock(|screen| screen.has_failed());
+                Response::ErrorCounters(
+                    monitor
+                        .lock(|monitor| monitor.error_counters(screen_failed)),
+                )
+            }
+
+            Query::FrameTiming => {
+                Response::FrameTiming(monitor.lock(|monitor| {
+                    monitor.frame_timing(now_ms(), FRAME_PERIOD_US)
+                }))
+            }
+
+            Query::ShowStatus => Response::ShowStatus(
+                show_player.lock(|player| player.status(now_ms())),
+            ),
+        };
+
+        Answer::Response(response)
+    }
+
+    /// Returns the last ADC readings of the UI potentiometers, when using the
+    /// physical UI.
+    #[cfg(feature = "ui_physical")]
+    fn adc_readings(ui: &mut UI) -> Option<AdcReadings> {
+        Some(ui.adc_readings())
+    }
+
+    /// Returns the last ADC readings of the UI potentiometers, when using the
+    /// physical UI.
+    #[cfg(feature = "ui_graphical")]
+    fn adc_readings(_: &mut UI) -> Option<AdcReadings> {
+        None
+    }
+
+    /// Samples all the inputs of the board, when using the physical UI.
+    #[cfg(feature = "ui_physical")]
+    fn read_inputs(ui: &mut UI) -> Option<InputReadings> {
+        Some(ui.read_inputs())
+    }
+
+    /// Samples all the inputs of the board, when using the physical UI.
+    #[cfg(feature = "ui_graphical")]
+    fn read_inputs(_: &mut UI) -> Option<InputReadings> {
+        None
+    }
+
+    /// Runs the power-on self-test, when enabled.
+    #[cfg(feature = "self_test")]
+    fn self_test(board: &mut Board) -> Option<SelfTestReport> {
+        info!("Running the self-test...");
+
+        let mut delay = AsmDelay::new(SYSCLK);
+        let report = totem_app::self_test::run(board, &mut delay);
+
+        if report.passed() {
+            info!("Self-test passed.");
+        } else {
+            for name in report.failures() {
+                warn!("Self-test failed on {}.", name);
+            }
         }
+
+        Some(report)
+    }
+
+    /// Runs the power-on self-test, when enabled.
+    #[cfg(not(feature = "self_test"))]
+    fn self_test(_: &mut Board) -> Option<SelfTestReport> {
+        None
+    }
+
+    /// Returns whether the B2 button is pressed.
+    #[cfg(feature = "ui_physical")]
+    fn is_b2_pressed(ui: &mut UI) -> bool {
+        ui.is_b2_pressed()
+    }
+
+    /// Returns whether the B2 button is pressed.
+    ///
+    /// The graphical UI has no buttons.
+    #[cfg(feature = "ui_graphical")]
+    fn is_b2_pressed(_: &mut UI) -> bool {
+        false
+    }
+
+    /// Returns whether LED frames are being rendered, i.e. a mode other than
+    /// Off is running or a cue is active.
+    fn is_running(chaser: &Chaser, cue_layer: &CueLayer) -> bool {
+        !matches!(chaser, Chaser::None) || cue_layer.is_active()
+    }
+
+    /// Wakes the LED task up to apply the updates posted in its mailbox.
+    ///
+    /// The LED task has room for a single pending run: when it cannot be
+    /// spawned, a frame is already scheduled and applies them instead.
+    fn wake_led_task() {
+        led_task::spawn().ok();
+    }
+
+    /// Posts a cue to the LED task and wakes it up.
+    fn fire_cue(led_mailbox: &mut impl rtic::Mutex<T = LedMailbox>, cue: Cue) {
+        led_mailbox.lock(|mailbox| mailbox.post_cue(cue));
+        wake_led_task();
+    }
+
+    /// Checks in the LED task once its next frame has been spawned, or records
+    /// the spawn failure.
+    fn spawn_next<T, E>(
+        spawned: Result<T, E>,
+        monitor: &mut impl rtic::Mutex<T = Monitor>,
+        supervisor: &mut impl rtic::Mutex<T = Supervisor>,
+    ) {
+        if spawned.is_ok() {
+            supervisor.lock(|sup| sup.check_in(SupervisedTask::Led, now_ms()));
+        } else {
+            warn!("Cannot spawn led_task, stopping the frames.");
+            monitor.lock(|monitor| monitor.record_spawn_failure());
+        }
+    }
+
+    /// Applies an update received over ERCP Basic or from a preset to the UI.
+    ///
+    /// On the physical UI, the updated fields hold until their control is
+    /// moved.
+    fn apply_patch(ui: &mut UI, patch: &UIStatePatch) {
+        ui.update_state(patch);
+    }
+
+    /// Restores the saved UI state, when using the graphical UI.
+    #[cfg(feature = "ui_graphical")]
+    fn restore_state(ui: &mut UI, settings: &Settings<SettingsFlash>) {
+        if let Some(state) = settings.saved() {
+            info!("Restoring the saved UI state.");
+            ui.set_state(state);
+        }
+    }
+
+    /// Restores the saved UI state, when using the graphical UI.
+    ///
+    /// The physical UI reads its state from its inputs.
+    #[cfg(feature = "ui_physical")]
+    fn restore_state(_: &mut UI, _: &Settings<SettingsFlash>) {}
+
+    /// Restores the saved configuration of the host link watchdog, when using
+    /// the graphical UI.
+    #[cfg(feature = "ui_graphical")]
+    fn restore_host_watchdog(
+        host_watchdog: &mut HostWatchdog,
+        settings: &Settings<SettingsFlash>,
+    ) {
+        if let Some(config) = settings.host_watchdog() {
+            // The monotonic timer starts once init returns, at 0.
+            host_watchdog.configure(config, 0);
+        }
+    }
+
+    /// Restores the saved configuration of the host link watchdog, when using
+    /// the graphical UI.
+    ///
+    /// The physical UI cannot be driven by the host.
+    #[cfg(feature = "ui_physical")]
+    fn restore_host_watchdog(
+        _: &mut HostWatchdog,
+        _: &Settings<SettingsFlash>,
+    ) {
+    }
+
+    /// Reverts to the failsafe state when the host has been silent for too
+    /// long, dropping any stale update, when using the graphical UI.
+    #[cfg(feature = "ui_graphical")]
+    fn check_host_link(
+        host_watchdog: &mut impl rtic::Mutex<T = HostWatchdog>,
+        pending_update: &mut impl rtic::Mutex<T = PendingUpdate>,
+        ui: &mut impl rtic::Mutex<T = UI>,
+    ) {
+        if let Some(failsafe) = host_watchdog.lock(|wdg| wdg.check(now_ms())) {
+            warn!("Host link lost, reverting to the failsafe UI state.");
+            pending_update.lock(|pending| pending.take());
+            ui.lock(|ui| apply_patch(ui, &failsafe.into()));
+        }
+    }
+
+    /// Reverts to the failsafe state when the host has been silent for too
+    /// long, dropping any stale update, when using the graphical UI.
+    ///
+    /// The physical UI cannot be driven by the host.
+    #[cfg(feature = "ui_physical")]
+    fn check_host_link(
+        _: &mut impl rtic::Mutex<T = HostWatchdog>,
+        _: &mut impl rtic::Mutex<T = PendingUpdate>,
+        _: &mut impl rtic::Mutex<T = UI>,
+    ) {
+    }
+
+    /// Saves the UI state once it has settled, when using the graphical UI.
+    #[cfg(feature = "ui_graphical")]
+    fn save_state(settings: &mut Settings<SettingsFlash>, state: UIState) {
+        settings.update(state, now_ms());
+
+        match settings.poll(now_ms()) {
+            Ok(true) => defmt::debug!("UI state saved."),
+            Ok(false) => (),
+            Err(_) => warn!("Cannot save the UI state."),
+        }
+    }
+
+    /// Saves the UI state once it has settled, when using the graphical UI.
+    ///
+    /// The physical UI reads its state from its inputs.
+    #[cfg(feature = "ui_physical")]
+    fn save_state(_: &mut Settings<SettingsFlash>, _: UIState) {}
+
+    /// Centres a text on a line of the screen.
+    fn centre(text: &str) -> String<16> {
+        let mut line = String::new();
+        write!(line, "{:^16}", text).ok();
+        line
+    }
+
+    /// Returns the time elapsed since boot, in milliseconds.
+    fn now_ms() -> u32 {
+        monotonics::now().duration_since_epoch().to_millis() as u32
+    }
+
+    /// Returns the time elapsed since boot, in microseconds.
+    fn now_us() -> u64 {
+        monotonics::now().duration_since_epoch().to_micros()
+    }
+
+    /// Returns the first instant at or after `us` microseconds since boot.
+    fn instant_at_us(us: u64) -> TimerInstantU64<MONOTONIC_RATE> {
+        TimerInstantU64::from_ticks(us * TICKS_PER_US)
     }
 }
@@ -0,0 +1,306 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Reset and panic reporting.
+//!
+//! The cause of each reset is read at boot from the reset flags of the
+//! microcontroller. On panic, the message and location are recorded in the
+//! retained RAM region before resetting, so that the panic can be reported on
+//! the next boot. The retained region also counts the resets since power-on.
+
+use core::{
+    fmt::{self, Write as _},
+    panic::PanicInfo,
+};
+
+use defmt::Format;
+use heapless::String;
+use totem_board::{
+    reset::ResetFlags,
+    retained::{self, RETAINED_SIZE},
+};
+use totem_protocol::reset::{PanicReport, ResetCause, ResetReport};
+
+/// The maximum length of a recorded panic message.
+pub const MESSAGE_MAX_LEN: usize = 64;
+
+/// The maximum length of a recorded source file path.
+pub const FILE_MAX_LEN: usize = 40;
+
+/// The magic number marking valid retained data.
+const MAGIC: u32 = 0x544F_5445;
+
+/// The magic number marking a recorded panic.
+const PANIC_MAGIC: u32 = 0x5041_4E43;
+
+// Layout of the retained region.
+const MAGIC_OFFSET: usize = 0;
+const RESETS_OFFSET: usize = 4;
+const PANIC_MAGIC_OFFSET: usize = 8;
+const LINE_OFFSET: usize = 12;
+const COLUMN_OFFSET: usize = 16;
+const FILE_OFFSET: usize = 20;
+const MESSAGE_OFFSET: usize = FILE_OFFSET + 1 + FILE_MAX_LEN;
+
+const _: () = assert!(MESSAGE_OFFSET + 1 + MESSAGE_MAX_LEN <= RETAINED_SIZE);
+
+/// The information about the last reset, gathered at boot.
+#[derive(Debug, Format, Clone)]
+pub struct ResetInfo {
+    /// The cause of the last reset.
+    pub cause: ResetCause,
+    /// The number of resets since power-on.
+    pub resets: u32,
+    /// The panic which has caused the last reset, if any.
+    pub panic: Option<PanicRecord>,
+}
+
+/// A panic recorded before a reset.
+#[derive(Debug, Clone)]
+pub struct PanicRecord {
+    /// The panic message, truncated to [`MESSAGE_MAX_LEN`].
+    message: String<MESSAGE_MAX_LEN>,
+    /// The end of the source file path, truncated to [`FILE_MAX_LEN`].
+    file: String<FILE_MAX_LEN>,
+    /// The line where the panic has occurred.
+    line: u32,
+    /// The column where the panic has occurred.
+    column: u32,
+}
+
+impl ResetInfo {
+    /// Gathers the information about the last reset from the reset flags and
+    /// the retained region.
+    ///
+    /// This must be called once at boot: it counts the reset and clears the
+    /// recorded panic.
+    pub fn gather(flags: ResetFlags) -> Self {
+        let mut bytes = retained::read();
+
+        let power_on = get_u32(&bytes, MAGIC_OFFSET) != MAGIC;
+        let resets = if power_on {
+            0
+        } else {
+            get_u32(&bytes, RESETS_OFFSET).saturating_add(1)
+        };
+
+        let panic = (!power_on
+            && get_u32(&bytes, PANIC_MAGIC_OFFSET) == PANIC_MAGIC)
+            .then(|| PanicRecord::load(&bytes));
+
+        put_u32(&mut bytes, MAGIC_OFFSET, MAGIC);
+        put_u32(&mut bytes, RESETS_OFFSET, resets);
+        put_u32(&mut bytes, PANIC_MAGIC_OFFSET, 0);
+        retained::write(&bytes);
+
+        Self {
+            cause: cause(flags, power_on, panic.is_some()),
+            resets,
+            panic,
+        }
+    }
+
+    /// Returns the report of the last reset.
+    pub fn report(&self) -> ResetReport<'_> {
+        ResetReport {
+            cause: self.cause,
+            resets: self.resets,
+            panic: self.panic.as_ref().map(PanicRecord::report),
+        }
+    }
+}
+
+impl PanicRecord {
+    /// Returns the report of the panic.
+    pub fn report(&self) -> PanicReport<'_> {
+        PanicReport {
+            message: &self.message,
+            file: &self.file,
+            line: self.line,
+            column: self.column,
+        }
+    }
+
+    /// Returns the name of the source file where the panic has occurred.
+    pub fn file_name(&self) -> &str {
+        self.file.rsplit('/').next().unwrap_or_default()
+    }
+
+    /// Returns the line where the panic has occurred.
+    pub fn line(&self) -> u32 {
+        self.line
+    }
+
+    /// Loads a panic record from the retained region.
+    fn load(bytes: &[u8; RETAINED_SIZE]) -> Self {
+        Self {
+            message: get_str(bytes, MESSAGE_OFFSET),
+            file: get_str(bytes, FILE_OFFSET),
+            line: get_u32(bytes, LINE_OFFSET),
+            column: get_u32(bytes, COLUMN_OFFSET),
+        }
+    }
+}
+
+impl Format for PanicRecord {
+    fn format(&self, fmt: defmt::Formatter) {
+        defmt::write!(
+            fmt,
+            "{=str} at {=str}:{=u32}:{=u32}",
+            self.message.as_str(),
+            self.file.as_str(),
+            self.line,
+            self.column,
+        );
+    }
+}
+
+/// Records a panic in the retained region, to be reported on the next boot.
+pub fn record_panic(info: &PanicInfo) {
+    let mut bytes = retained::read();
+
+    if let Some(location) = info.location() {
+        // Keep the end of the path, which tells the most about the file.
+        let file = location.file();
+        let mut start = file.len().saturating_sub(FILE_MAX_LEN);
+        while !file.is_char_boundary(start) {
+            start += 1;
+        }
+
+        put_str(&mut bytes, FILE_OFFSET, FILE_MAX_LEN, |text| {
+            text.write_str(&file[start..])
+        });
+        put_u32(&mut bytes, LINE_OFFSET, location.line());
+        put_u32(&mut bytes, COLUMN_OFFSET, location.column());
+    } else {
+        put_str(&mut bytes, FILE_OFFSET, FILE_MAX_LEN, |_| Ok(()));
+        put_u32(&mut bytes, LINE_OFFSET, 0);
+        put_u32(&mut bytes, COLUMN_OFFSET, 0);
+    }
+
+    put_str(&mut bytes, MESSAGE_OFFSET, MESSAGE_MAX_LEN, |text| {
+        write!(text, "{}", info.message())
+    });
+
+    put_u32(&mut bytes, PANIC_MAGIC_OFFSET, PANIC_MAGIC);
+    retained::write(&bytes);
+}
+
+/// Records the panic in the retained region, then resets.
+///
+/// It is only defined on the target, so that the host tests keep the panic
+/// handler from `std`.
+#[cfg(all(target_os = "none", not(feature = "panic-probe")))]
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    cortex_m::interrupt::disable();
+    record_panic(info);
+    cortex_m::peripheral::SCB::sys_reset()
+}
+
+/// Returns the cause of the last reset.
+fn cause(flags: ResetFlags, power_on: bool, panicked: bool) -> ResetCause {
+    if flags.low_power {
+        ResetCause::LowPower
+    } else if flags.window_watchdog {
+        ResetCause::WindowWatchdog
+    } else if flags.independent_watchdog {
+        ResetCause::IndependentWatchdog
+    } else if flags.software && panicked {
+        ResetCause::Panic
+    } else if flags.software {
+        ResetCause::Software
+    } else if flags.brown_out && power_on {
+        // The brown-out flag is also set on power-on, which is told apart by
+        // the loss of the retained region.
+        ResetCause::PowerOn
+    } else if flags.brown_out {
+        ResetCause::BrownOut
+    } else if flags.pin {
+        ResetCause::Pin
+    } else {
+        ResetCause::Unknown
+    }
+}
+
+fn get_u32(bytes: &[u8; RETAINED_SIZE], offset: usize) -> u32 {
+    let mut word = [0; 4];
+    word.copy_from_slice(&bytes[offset..offset + 4]);
+    u32::from_le_bytes(word)
+}
+
+fn put_u32(bytes: &mut [u8; RETAINED_SIZE], offset: usize, value: u32) {
+    bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+/// Reads a text stored as its length followed by its bytes.
+///
+/// The text is read up to its first invalid character, if any.
+fn get_str<const N: usize>(
+    bytes: &[u8; RETAINED_SIZE],
+    offset: usize,
+) -> String<N> {
+    let len = (bytes[offset] as usize).min(N);
+    let raw = &bytes[offset + 1..offset + 1 + len];
+    let text = match core::str::from_utf8(raw) {
+        Ok(text) => text,
+        Err(error) => {
+            core::str::from_utf8(&raw[..error.valid_up_to()]).unwrap_or("")
+        }
+    };
+
+    let mut string = String::new();
+    string.push_str(text).ok();
+    string
+}
+
+/// Stores a text written by `f` as its length followed by its bytes, silently
+/// truncating it to `max_len`.
+fn put_str(
+    bytes: &mut [u8; RETAINED_SIZE],
+    offset: usize,
+    max_len: usize,
+    f: impl FnOnce(&mut TruncatingWriter) -> fmt::Result,
+) {
+    let mut writer = TruncatingWriter {
+        buffer: &mut bytes[offset + 1..offset + 1 + max_len],
+        len: 0,
+    };
+
+    f(&mut writer).ok();
+    bytes[offset] = writer.len as u8;
+}
+
+/// A writer silently truncating what does not fit in its buffer.
+struct TruncatingWriter<'a> {
+    buffer: &'a mut [u8],
+    len: usize,
+}
+
+impl fmt::Write for TruncatingWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            let end = self.len + c.len_utf8();
+            if end > self.buffer.len() {
+                break;
+            }
+
+            c.encode_utf8(&mut self.buffer[self.len..end]);
+            self.len = end;
+        }
+
+        Ok(())
+    }
+}
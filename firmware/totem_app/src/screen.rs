@@ -0,0 +1,68 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! LCD screen with graceful degradation.
+//!
+//! The screen is an optional accessory connected over I²C, so a loose connector
+//! must not bring the Totem down. On the first error, the screen is marked as
+//! failed and is not used anymore until the next boot.
+
+use totem_board::{hal::i2c, peripheral::Screen};
+
+use crate::warn;
+
+/// An optional LCD screen, disabled on its first error.
+pub struct ManagedScreen {
+    /// The screen, if present and working.
+    screen: Option<Screen>,
+    /// Whether the screen has been disabled after an error.
+    failed: bool,
+}
+
+impl ManagedScreen {
+    /// Manages an optional screen.
+    pub fn new(screen: Option<Screen>) -> Self {
+        Self {
+            screen,
+            failed: false,
+        }
+    }
+
+    /// Returns whether the screen is present and working.
+    pub fn is_available(&self) -> bool {
+        self.screen.is_some()
+    }
+
+    /// Returns whether the screen has been disabled after an error.
+    pub fn has_failed(&self) -> bool {
+        self.failed
+    }
+
+    /// Drives the screen with `f`, if it is available.
+    ///
+    /// If `f` returns an error, the screen is disabled.
+    pub fn with(
+        &mut self,
+        f: impl FnOnce(&mut Screen) -> Result<(), i2c::Error>,
+    ) {
+        if let Some(screen) = &mut self.screen {
+            if f(screen).is_err() {
+                warn!("Screen error, disabling the screen.");
+                self.screen = None;
+                self.failed = true;
+            }
+        }
+    }
+}
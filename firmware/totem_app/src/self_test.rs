@@ -0,0 +1,210 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Power-on self-test.
+//!
+//! When the `self_test` feature is enabled, the Totem checks its hardware at
+//! boot, before starting its tasks. Each LED strip is lit in turn while the
+//! screen shows its number, so that a dead strip stands out. Every
+//! potentiometer is then sampled and must read steadily inside its calibrated
+//! range. The result is shown on the screen, and the LEDs flash green if the
+//! self-test has passed, or red otherwise.
+
+use core::fmt::Write as _;
+
+use heapless::String;
+use smart_leds::{
+    brightness,
+    colors::{BLACK, GREEN, RED, WHITE},
+    RGB8,
+};
+use totem_board::{
+    adc::ADC,
+    board::Board,
+    constants::LED_LAYOUT,
+    peripheral::{CalibratedPotentiometer, LedStrip, Screen},
+    prelude::*,
+};
+use totem_protocol::{
+    inputs::AnalogReading,
+    self_test::{PotentiometerCheck, PotentiometerStatus, SelfTestReport},
+};
+use totem_utils::delay::AsmDelay;
+
+use crate::led_strip::{FrameWrite, LedStripExt as _};
+
+/// The number of samples taken from each potentiometer.
+const SAMPLES: u32 = 200;
+
+/// The tolerance around the calibrated range of the potentiometers.
+const RANGE_TOLERANCE: u16 = 16;
+
+/// The maximum spread between the samples of a steady potentiometer.
+const MAX_SPREAD: u16 = 200;
+
+/// The time each LED strip is lit, in milliseconds.
+const STRIP_TIME_MS: u16 = 400;
+
+/// The time the result is shown on the LEDs, in milliseconds.
+const RESULT_TIME_MS: u16 = 1500;
+
+/// The brightness of the LEDs during the self-test.
+const TEST_BRIGHTNESS: u8 = 64;
+
+/// Runs the self-test on the board.
+pub fn run(board: &mut Board, delay: &mut AsmDelay) -> SelfTestReport {
+    let Board {
+        r1,
+        r2,
+        r3,
+        r4,
+        s1,
+        s2,
+        p_adc,
+        led_strip,
+        screen,
+        ..
+    } = board;
+
+    backlight(screen, WHITE);
+
+    let mut strips_lit = 0;
+    for strip in 0..LED_LAYOUT.strips {
+        let mut line = String::<16>::new();
+        write!(line, "LED strip {}/{}", strip + 1, LED_LAYOUT.strips).ok();
+        show(screen, "Self-test", &line, delay);
+
+        let write = light(led_strip, |led| {
+            if led / LED_LAYOUT.leds_per_strip == strip {
+                WHITE
+            } else {
+                BLACK
+            }
+        });
+
+        if write.written {
+            strips_lit += 1;
+        }
+
+        delay.delay_ms(STRIP_TIME_MS);
+    }
+
+    led_strip.off(LED_LAYOUT);
+
+    let report = SelfTestReport {
+        screen_present: screen.is_some(),
+        strips_lit,
+        potentiometers: [
+            check(p_adc, r1),
+            check(p_adc, r2),
+            check(p_adc, r3),
+            check(p_adc, r4),
+            check(p_adc, s1),
+            check(p_adc, s2),
+        ],
+    };
+
+    let (title, color) = if report.passed() {
+        ("Self-test: PASS", GREEN)
+    } else {
+        ("Self-test: FAIL", RED)
+    };
+
+    // Names are separated by spaces, so the list of all potentiometers does
+    // not fit on a line: keep some room and truncate it on display.
+    let mut failures = String::<32>::new();
+    for name in report.failures() {
+        write!(failures, "{} ", name).ok();
+    }
+
+    let details = match failures.trim_end() {
+        "" => "All inputs OK",
+        failures => failures.get(..16).unwrap_or(failures),
+    };
+
+    backlight(screen, color);
+    show(screen, title, details, delay);
+    light(led_strip, |_| color);
+    delay.delay_ms(RESULT_TIME_MS);
+    led_strip.off(LED_LAYOUT);
+
+    report
+}
+
+/// Returns the status of a potentiometer from its reading and calibrated
+/// range.
+fn status(reading: AnalogReading, min: u16, max: u16) -> PotentiometerStatus {
+    if reading.max - reading.min > MAX_SPREAD {
+        PotentiometerStatus::Noisy
+    } else if reading.mean < min.saturating_sub(RANGE_TOLERANCE) {
+        PotentiometerStatus::Low
+    } else if reading.mean > max.saturating_add(RANGE_TOLERANCE) {
+        PotentiometerStatus::High
+    } else {
+        PotentiometerStatus::Ok
+    }
+}
+
+fn check<P: CalibratedPotentiometer>(
+    adc: &mut ADC,
+    potentiometer: &mut P,
+) -> PotentiometerCheck {
+    let reading = AnalogReading::from_samples(
+        (0..SAMPLES).map(|_| adc.read(potentiometer).unwrap()),
+    );
+
+    PotentiometerCheck {
+        reading,
+        status: status(reading, P::MIN, P::MAX),
+    }
+}
+
+fn light(
+    led_strip: &mut LedStrip,
+    color: impl Fn(usize) -> RGB8,
+) -> FrameWrite {
+    let frame = (0..LED_LAYOUT.num_leds()).map(color);
+    led_strip.write_frame(brightness(frame, TEST_BRIGHTNESS))
+}
+
+// On error, the screen is dropped and the self-test goes on without it, as the
+// application does.
+fn backlight(screen: &mut Option<Screen>, color: RGB8) {
+    if let Some(lcd) = screen {
+        if lcd.set_rgb(color.r, color.g, color.b).is_err() {
+            *screen = None;
+        }
+    }
+}
+
+fn show(
+    screen: &mut Option<Screen>,
+    first_line: &str,
+    second_line: &str,
+    delay: &mut AsmDelay,
+) {
+    if let Some(lcd) = screen {
+        let shown = lcd
+            .clear(delay)
+            .and_then(|_| lcd.set_cursor_position(0, 0))
+            .and_then(|_| lcd.write_str(first_line))
+            .and_then(|_| lcd.set_cursor_position(0, 1))
+            .and_then(|_| lcd.write_str(second_line));
+
+        if shown.is_err() {
+            *screen = None;
+        }
+    }
+}
@@ -0,0 +1,788 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Persistence of the UI state, presets, name, shared secret and host watchdog
+//! configuration across power cycles.
+//!
+//! The UI state is saved in flash once it has not changed for a quiet period,
+//! and restored at boot. The other settings are saved on demand. To spread
+//! the wear, each save appends a record to the current page instead of
+//! rewriting it in place: only when the page is full is the other page erased,
+//! and the records continue there after a copy of the current values. At
+//! boot, the valid record with the highest sequence number wins for each key.
+//!
+//! A record is made of [`RECORD_SIZE`] bytes:
+//!
+//! | Offset | Size | Content                                         |
+//! |--------|------|-------------------------------------------------|
+//! | 0      | 1    | [`MAGIC`]                                       |
+//! | 1      | 1    | The length of the payload                       |
+//! | 2      | 1    | The key                                         |
+//! | 3      | 1    | Reserved, zero                                  |
+//! | 4      | 4    | The sequence number, little endian              |
+//! | 8      | 12   | The payload                                     |
+//! | 20     | 4    | The CRC-32 of the first 20 bytes, little endian |
+//!
+//! The key 0 holds the UI state and the keys 1 to 8 the presets, all encoded
+//! with postcard. The key 9 holds the name, in ASCII.
+//!
+//! The shared secret and the host watchdog configuration do not fit in a
+//! record: they are split in chunks of [`CHUNK_SIZE`] bytes, each chunk being
+//! preceded by a generation byte incremented on each save. The keys 10 to 12
+//! hold the shared secret, and the keys 13 and 14 the host watchdog
+//! configuration encoded with postcard. A value whose chunks do not share the
+//! same generation has been torn by a power loss and is ignored.
+//!
+//! A record torn by a power loss fails its CRC, or the ECC check of the flash
+//! when read, and is skipped.
+
+use core::ops::Range;
+
+use heapless::Vec;
+use totem_board::{
+    constants::{FLASH_PAGE_SIZE, SETTINGS_PAGES},
+    storage::{SettingsFlash, StorageError},
+};
+use totem_protocol::{
+    auth::SECRET_MAX_LEN,
+    host_link::HostWatchdogConfig,
+    identity::{Name, MAX_NAME_LEN},
+    preset::{Presets, PRESET_COUNT},
+};
+use totem_ui::state::UIState;
+
+/// The time the UI state must stay unchanged before being saved, in
+/// milliseconds.
+pub const QUIET_PERIOD_MS: u32 = 5_000;
+
+/// The size of a record, in bytes.
+pub const RECORD_SIZE: usize = 24;
+
+/// The first byte of a record.
+pub const MAGIC: u8 = 0x5E;
+
+/// The key of the UI state.
+const UI_STATE_KEY: usize = 0;
+
+/// The key of the first preset.
+const FIRST_PRESET_KEY: usize = 1;
+
+/// The key of the name.
+const NAME_KEY: usize = FIRST_PRESET_KEY + PRESET_COUNT;
+
+/// The keys of the chunks of the shared secret.
+const SECRET_KEYS: Range<usize> =
+    NAME_KEY + 1..NAME_KEY + 1 + SECRET_MAX_LEN.div_ceil(CHUNK_SIZE);
+
+/// The keys of the chunks of the host watchdog configuration.
+const HOST_WATCHDOG_KEYS: Range<usize> =
+    SECRET_KEYS.end..SECRET_KEYS.end + HOST_WATCHDOG_CHUNKS;
+
+/// The number of chunks of the host watchdog configuration.
+const HOST_WATCHDOG_CHUNKS: usize = 2;
+
+/// The maximum size of the encoded host watchdog configuration.
+const HOST_WATCHDOG_MAX_LEN: usize = HOST_WATCHDOG_CHUNKS * CHUNK_SIZE;
+
+/// The number of keys.
+const KEYS: usize = HOST_WATCHDOG_KEYS.end;
+
+/// The number of records per page.
+const SLOTS_PER_PAGE: usize = FLASH_PAGE_SIZE / RECORD_SIZE;
+
+/// The value of an erased flash byte.
+const ERASED: u8 = 0xFF;
+
+/// The offset of the key in a record.
+const KEY_OFFSET: usize = 2;
+
+/// The offset of the sequence number in a record.
+const SEQUENCE_OFFSET: usize = 4;
+
+/// The offset of the payload in a record.
+const PAYLOAD_OFFSET: usize = 8;
+
+/// The offset of the CRC in a record.
+const CRC_OFFSET: usize = 20;
+
+/// The size of the payload area of a record.
+const PAYLOAD_SIZE: usize = CRC_OFFSET - PAYLOAD_OFFSET;
+
+/// The size of a chunk of a value split over several records, after its
+/// generation byte.
+pub const CHUNK_SIZE: usize = PAYLOAD_SIZE - 1;
+
+const _: () = assert!(MAX_NAME_LEN <= PAYLOAD_SIZE);
+
+/// The flash pages backing the settings.
+pub trait SettingsPages {
+    /// The error that can occur when accessing the pages.
+    type Error;
+
+    /// Reads bytes from a page, starting at `offset`.
+    ///
+    /// An error is returned if the bytes cannot be read reliably, for
+    /// instance when they fail the ECC check of the flash.
+    fn read(
+        &mut self,
+        page: usize,
+        offset: usize,
+        buffer: &mut [u8],
+    ) -> Result<(), Self::Error>;
+
+    /// Erases a page.
+    fn erase(&mut self, page: usize) -> Result<(), Self::Error>;
+
+    /// Writes bytes to an erased area of a page, starting at `offset`.
+    fn write(
+        &mut self,
+        page: usize,
+        offset: usize,
+        data: &[u8],
+    ) -> Result<(), Self::Error>;
+}
+
+/// The payload of a record.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Payload {
+    /// The length of the payload.
+    len: usize,
+    /// The payload area, padded with zeros.
+    bytes: [u8; PAYLOAD_SIZE],
+}
+
+/// The settings store.
+pub struct Settings<P> {
+    /// The flash pages.
+    pages: P,
+    /// The page where to write the next record.
+    page: usize,
+    /// The slot where to write the next record.
+    slot: usize,
+    /// The sequence number of the last record.
+    sequence: u32,
+    /// The last saved value for each key.
+    values: [Option<Payload>; KEYS],
+    /// The UI state waiting for the end of the quiet period, with the time of
+    /// its last change.
+    pending: Option<(UIState, u32)>,
+}
+
+impl<P: SettingsPages> Settings<P> {
+    /// Opens the settings store, looking for the last saved values.
+    pub fn new(mut pages: P) -> Self {
+        let mut used = [0; SETTINGS_PAGES];
+        let mut newest: [Option<(u32, Payload)>; KEYS] = [None; KEYS];
+        let mut last: Option<(usize, u32)> = None;
+
+        for (page, used) in used.iter_mut().enumerate() {
+            for slot in 0..SLOTS_PER_PAGE {
+                let mut record = [0; RECORD_SIZE];
+                let read = pages.read(page, slot * RECORD_SIZE, &mut record);
+
+                // Records are appended: the first free slot ends the page.
+                if read.is_ok() && record.iter().all(|&byte| byte == ERASED) {
+                    break;
+                }
+
+                *used = slot + 1;
+
+                // A record which cannot be read, like one torn by a power loss
+                // failing its ECC check, is skipped.
+                if read.is_err() {
+                    continue;
+                }
+
+                if let Some((key, sequence, payload)) = decode(&record) {
+                    let newest = &mut newest[key];
+                    if newest.is_none_or(|(newest, _)| sequence > newest) {
+                        *newest = Some((sequence, payload));
+                    }
+
+                    if last.is_none_or(|(_, last)| sequence > last) {
+                        last = Some((page, sequence));
+                    }
+                }
+            }
+        }
+
+        // Continue after the last record.
+        let (page, sequence) = last.unwrap_or_default();
+
+        Self {
+            pages,
+            page,
+            slot: used[page],
+            sequence,
+            values: newest.map(|newest| newest.map(|(_, payload)| payload)),
+            pending: None,
+        }
+    }
+
+    /// Returns the last saved UI state, if any.
+    pub fn saved(&self) -> Option<UIState> {
+        self.values[UI_STATE_KEY]?.state()
+    }
+
+    /// Returns the saved presets.
+    pub fn presets(&self) -> Presets {
+        core::array::from_fn(|slot| {
+            self.values[FIRST_PRESET_KEY + slot]?.state()
+        })
+    }
+
+    /// Returns the saved name, empty if the Totem has not been named.
+    pub fn name(&self) -> Name {
+        self.values[NAME_KEY]
+            .and_then(|payload| payload.name())
+            .unwrap_or_default()
+    }
+
+    /// Saves the name.
+    pub fn save_name(&mut self, name: Name) -> Result<(), P::Error> {
+        self.save(NAME_KEY, Payload::from_name(&name))
+    }
+
+    /// Returns the saved shared secret, if any.
+    ///
+    /// An empty secret means that authentication has been disabled.
+    pub fn secret(&self) -> Option<Vec<u8, SECRET_MAX_LEN>> {
+        self.chunked(SECRET_KEYS)
+    }
+
+    /// Saves the shared secret.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `secret` is longer than [`SECRET_MAX_LEN`].
+    pub fn save_secret(&mut self, secret: &[u8]) -> Result<(), P::Error> {
+        assert!(secret.len() <= SECRET_MAX_LEN);
+        self.save_chunked(SECRET_KEYS, secret)
+    }
+
+    /// Returns the saved configuration of the host watchdog, if any.
+    pub fn host_watchdog(&self) -> Option<HostWatchdogConfig> {
+        let bytes =
+            self.chunked::<HOST_WATCHDOG_MAX_LEN>(HOST_WATCHDOG_KEYS)?;
+
+        postcard::from_bytes(&bytes)
+            .ok()
+            .filter(HostWatchdogConfig::is_valid)
+    }
+
+    /// Saves the configuration of the host watchdog.
+    pub fn save_host_watchdog(
+        &mut self,
+        config: HostWatchdogConfig,
+    ) -> Result<(), P::Error> {
+        let mut bytes = [0; HOST_WATCHDOG_MAX_LEN];
+
+        // The chunks are large enough for any configuration.
+        let bytes = postcard::to_slice(&config, &mut bytes)
+            .map(|bytes| &*bytes)
+            .unwrap_or_default();
+
+        self.save_chunked(HOST_WATCHDOG_KEYS, bytes)
+    }
+
+    /// Saves a UI state in a preset slot.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `slot` is not lower than [`PRESET_COUNT`].
+    pub fn save_preset(
+        &mut self,
+        slot: usize,
+        state: UIState,
+    ) -> Result<(), P::Error> {
+        assert!(slot < PRESET_COUNT);
+        self.save(FIRST_PRESET_KEY + slot, Payload::from_state(&state))
+    }
+
+    /// Notes the current UI state at `now_ms`.
+    ///
+    /// The state is saved by [`Settings::poll`] once it has not changed for
+    /// [`QUIET_PERIOD_MS`].
+    pub fn update(&mut self, state: UIState, now_ms: u32) {
+        if self.saved() == Some(state) {
+            self.pending = None;
+        } else if self.pending.is_none_or(|(pending, _)| pending != state) {
+            self.pending = Some((state, now_ms));
+        }
+    }
+
+    /// Saves the pending UI state if the quiet period is over at `now_ms`.
+    ///
+    /// Returns whether the state has been saved.
+    pub fn poll(&mut self, now_ms: u32) -> Result<bool, P::Error> {
+        match self.pending {
+            Some((state, since_ms))
+                if now_ms.wrapping_sub(since_ms) >= QUIET_PERIOD_MS =>
+            {
+                self.pending = None;
+                self.save(UI_STATE_KEY, Payload::from_state(&state))?;
+                Ok(true)
+            }
+
+            _ => Ok(false),
+        }
+    }
+
+    /// Returns a value split in chunks over `keys`, if all of them come from
+    /// the same save.
+    fn chunked<const N: usize>(
+        &self,
+        keys: Range<usize>,
+    ) -> Option<Vec<u8, N>> {
+        let chunks = &self.values[keys];
+        let generation = chunks[0]?.bytes[0];
+
+        let mut value = Vec::new();
+        for chunk in chunks {
+            let chunk = chunk.as_ref()?.as_bytes();
+            if chunk[0] != generation {
+                return None;
+            }
+
+            value.extend_from_slice(&chunk[1..]).ok()?;
+        }
+
+        Some(value)
+    }
+
+    /// Saves a value split in chunks over `keys`.
+    fn save_chunked(
+        &mut self,
+        keys: Range<usize>,
+        value: &[u8],
+    ) -> Result<(), P::Error> {
+        let generation = self.values[keys.start]
+            .map_or(0, |chunk| chunk.bytes[0].wrapping_add(1));
+
+        let mut chunks = value.chunks(CHUNK_SIZE);
+        for key in keys {
+            let chunk = chunks.next().unwrap_or_default();
+            self.save(key, Payload::from_chunk(generation, chunk))?;
+        }
+
+        Ok(())
+    }
+
+    /// Saves the value of a key, switching to the other page if needed.
+    fn save(&mut self, key: usize, payload: Payload) -> Result<(), P::Error> {
+        if self.slot == SLOTS_PER_PAGE {
+            self.page = (self.page + 1) % SETTINGS_PAGES;
+            self.slot = 0;
+            self.pages.erase(self.page)?;
+
+            // Carry the other values over, as the next switch erases the
+            // current page.
+            let values = self.values;
+            let others = values.iter().enumerate().filter(|&(i, _)| i != key);
+            for (other, value) in others {
+                if let Some(value) = value {
+                    self.append(other, value)?;
+                }
+            }
+        }
+
+        self.append(key, &payload)?;
+        self.values[key] = Some(payload);
+        Ok(())
+    }
+
+    /// Appends a record to the current page.
+    fn append(
+        &mut self,
+        key: usize,
+        payload: &Payload,
+    ) -> Result<(), P::Error> {
+        let sequence = self.sequence.wrapping_add(1);
+        let record = encode(key, sequence, payload);
+        let offset = self.slot * RECORD_SIZE;
+
+        // A failed write may leave the slot partially programmed: never reuse
+        // it.
+        self.slot += 1;
+        self.pages.write(self.page, offset, &record)?;
+
+        self.sequence = sequence;
+        Ok(())
+    }
+}
+
+impl Payload {
+    /// Creates a payload from a UI state.
+    fn from_state(state: &UIState) -> Self {
+        let mut bytes = [0; PAYLOAD_SIZE];
+
+        // The payload area is large enough for any UI state.
+        let len = postcard::to_slice(state, &mut bytes)
+            .map(|payload| payload.len())
+            .unwrap_or_default();
+
+        Self { len, bytes }
+    }
+
+    /// Creates a payload from a name.
+    fn from_name(name: &Name) -> Self {
+        let name = name.as_str().as_bytes();
+        let mut bytes = [0; PAYLOAD_SIZE];
+        bytes[..name.len()].copy_from_slice(name);
+
+        Self {
+            len: name.len(),
+            bytes,
+        }
+    }
+
+    /// Creates a payload from a chunk of a value, with its generation.
+    fn from_chunk(generation: u8, chunk: &[u8]) -> Self {
+        let mut bytes = [0; PAYLOAD_SIZE];
+        bytes[0] = generation;
+        bytes[1..=chunk.len()].copy_from_slice(chunk);
+
+        Self {
+            len: chunk.len() + 1,
+            bytes,
+        }
+    }
+
+    /// Returns the bytes of the payload.
+    fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+
+    /// Returns the UI state in the payload, if valid.
+    fn state(&self) -> Option<UIState> {
+        postcard::from_bytes(self.as_bytes())
+            .ok()
+            .filter(UIState::is_valid)
+    }
+
+    /// Returns the name in the payload, if valid.
+    fn name(&self) -> Option<Name> {
+        core::str::from_utf8(self.as_bytes())
+            .ok()
+            .and_then(Name::new)
+    }
+
+    /// Returns whether the payload holds a valid value for a key.
+    fn is_valid_for(&self, key: usize) -> bool {
+        match key {
+            NAME_KEY => self.name().is_some(),
+            _ if key >= SECRET_KEYS.start => self.len > 0,
+            _ => self.state().is_some(),
+        }
+    }
+}
+
+impl SettingsPages for SettingsFlash {
+    type Error = StorageError;
+
+    fn read(
+        &mut self,
+        page: usize,
+        offset: usize,
+        buffer: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        Self::read(self, page, offset, buffer)
+    }
+
+    fn erase(&mut self, page: usize) -> Result<(), Self::Error> {
+        Self::erase(self, page)
+    }
+
+    fn write(
+        &mut self,
+        page: usize,
+        offset: usize,
+        data: &[u8],
+    ) -> Result<(), Self::Error> {
+        Self::write(self, page, offset, data)
+    }
+}
+
+/// Encodes a record.
+fn encode(key: usize, sequence: u32, payload: &Payload) -> [u8; RECORD_SIZE] {
+    let mut record = [0; RECORD_SIZE];
+
+    record[0] = MAGIC;
+    record[1] = payload.len as u8;
+    record[KEY_OFFSET] = key as u8;
+    record[SEQUENCE_OFFSET..PAYLOAD_OFFSET]
+        .copy_from_slice(&sequence.to_le_bytes());
+    record[PAYLOAD_OFFSET..CRC_OFFSET].copy_from_slice(&payload.bytes);
+
+    let crc = crc32(&record[..CRC_OFFSET]);
+    record[CRC_OFFSET..].copy_from_slice(&crc.to_le_bytes());
+    record
+}
+
+/// Decodes a record, returning its key, sequence number and payload if it is
+/// valid.
+fn decode(record: &[u8; RECORD_SIZE]) -> Option<(usize, u32, Payload)> {
+    let crc = u32::from_le_bytes(record[CRC_OFFSET..].try_into().ok()?);
+    if record[0] != MAGIC || crc != crc32(&record[..CRC_OFFSET]) {
+        return None;
+    }
+
+    let sequence = u32::from_le_bytes(
+        record[SEQUENCE_OFFSET..PAYLOAD_OFFSET].try_into().ok()?,
+    );
+
+    let len = record[1] as usize;
+    let payload = Payload {
+        len: len.min(PAYLOAD_SIZE),
+        bytes: record[PAYLOAD_OFFSET..CRC_OFFSET].try_into().ok()?,
+    };
+
+    let key = record[KEY_OFFSET] as usize;
+    let valid = len <= PAYLOAD_SIZE && key < KEYS && payload.is_valid_for(key);
+    valid.then_some((key, sequence, payload))
+}
+
+/// Computes the CRC-32 (IEEE 802.3) of some data.
+fn crc32(data: &[u8]) -> u32 {
+    let crc = data.iter().fold(!0, |crc, &byte| {
+        (0..8).fold(crc ^ byte as u32, |crc, _| {
+            if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            }
+        })
+    });
+
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use totem_ui::state::Brightness;
+
+    use super::*;
+
+    /// Flash pages in memory.
+    #[derive(Clone)]
+    struct Pages {
+        /// The bytes of the pages.
+        bytes: [[u8; FLASH_PAGE_SIZE]; SETTINGS_PAGES],
+        /// The record slots failing the ECC check, by page.
+        bad_ecc: Vec<(usize, usize), 4>,
+    }
+
+    /// A read failing the ECC check.
+    #[derive(Debug, PartialEq, Eq)]
+    struct EccError;
+
+    impl Pages {
+        /// Returns erased pages.
+        fn erased() -> Self {
+            Self {
+                bytes: [[ERASED; FLASH_PAGE_SIZE]; SETTINGS_PAGES],
+                bad_ecc: Vec::new(),
+            }
+        }
+
+        /// Returns the bytes of a record slot.
+        fn record(&mut self, page: usize, slot: usize) -> &mut [u8] {
+            &mut self.bytes[page][slot * RECORD_SIZE..(slot + 1) * RECORD_SIZE]
+        }
+    }
+
+    impl SettingsPages for Pages {
+        type Error = EccError;
+
+        fn read(
+            &mut self,
+            page: usize,
+            offset: usize,
+            buffer: &mut [u8],
+        ) -> Result<(), Self::Error> {
+            buffer.copy_from_slice(&self.bytes[page][offset..][..buffer.len()]);
+
+            if self.bad_ecc.contains(&(page, offset / RECORD_SIZE)) {
+                Err(EccError)
+            } else {
+                Ok(())
+            }
+        }
+
+        fn erase(&mut self, page: usize) -> Result<(), Self::Error> {
+            self.bytes[page] = [ERASED; FLASH_PAGE_SIZE];
+            self.bad_ecc.retain(|&(bad_page, _)| bad_page != page);
+            Ok(())
+        }
+
+        fn write(
+            &mut self,
+            page: usize,
+            offset: usize,
+            data: &[u8],
+        ) -> Result<(), Self::Error> {
+            let area = &mut self.bytes[page][offset..][..data.len()];
+
+            // Like flash, an area can be programmed only once after an erase.
+            assert!(area.iter().all(|&byte| byte == ERASED));
+
+            area.copy_from_slice(data);
+            Ok(())
+        }
+    }
+
+    /// Returns a UI state with the given brightness.
+    fn state(brightness: u8) -> UIState {
+        UIState {
+            brightness: Brightness::new(brightness),
+            ..UIState::default()
+        }
+    }
+
+    /// Saves a UI state after its quiet period.
+    fn save_state(settings: &mut Settings<Pages>, state: UIState) {
+        settings.update(state, 0);
+        assert_eq!(settings.poll(QUIET_PERIOD_MS), Ok(true));
+    }
+
+    /// Opens the settings again from their pages, like at boot.
+    fn reboot(settings: Settings<Pages>) -> Settings<Pages> {
+        Settings::new(settings.pages)
+    }
+
+    #[test]
+    fn values_are_restored_at_boot() {
+        let mut settings = Settings::new(Pages::erased());
+        let name = Name::new("Totem").unwrap();
+        let config = HostWatchdogConfig {
+            timeout_ms: 5000,
+            failsafe: state(32),
+        };
+
+        save_state(&mut settings, state(10));
+        settings.save_preset(2, state(20)).unwrap();
+        settings.save_name(name).unwrap();
+        settings.save_secret(&[0x42; SECRET_MAX_LEN]).unwrap();
+        settings.save_host_watchdog(config).unwrap();
+
+        let settings = reboot(settings);
+        assert_eq!(settings.saved(), Some(state(10)));
+        assert_eq!(settings.presets()[2], Some(state(20)));
+        assert_eq!(settings.name(), name);
+        assert_eq!(settings.secret().unwrap(), [0x42; SECRET_MAX_LEN]);
+        assert_eq!(settings.host_watchdog(), Some(config));
+    }
+
+    #[test]
+    fn rollover_carries_the_other_values_over() {
+        let mut settings = Settings::new(Pages::erased());
+        let name = Name::new("Totem").unwrap();
+
+        settings.save_preset(0, state(1)).unwrap();
+        settings.save_name(name).unwrap();
+        settings.save_secret(b"secret").unwrap();
+
+        // Fill both pages twice, so that each has been erased.
+        let saves = 2 * SETTINGS_PAGES * SLOTS_PER_PAGE;
+        for save in 0..saves {
+            save_state(&mut settings, state(save as u8));
+        }
+
+        let settings = reboot(settings);
+        assert_eq!(settings.saved(), Some(state((saves - 1) as u8)));
+        assert_eq!(settings.presets()[0], Some(state(1)));
+        assert_eq!(settings.name(), name);
+        assert_eq!(settings.secret().unwrap(), b"secret");
+    }
+
+    #[test]
+    fn torn_records_are_skipped() {
+        let mut settings = Settings::new(Pages::erased());
+        save_state(&mut settings, state(10));
+        save_state(&mut settings, state(20));
+
+        // A power loss during the last write leaves it partially programmed.
+        let (page, slot) = (settings.page, settings.slot - 1);
+        settings.pages.record(page, slot)[RECORD_SIZE / 2..].fill(ERASED);
+
+        let mut settings = reboot(settings);
+        assert_eq!(settings.saved(), Some(state(10)));
+
+        // The torn slot is not reused.
+        save_state(&mut settings, state(30));
+        assert_eq!(reboot(settings).saved(), Some(state(30)));
+    }
+
+    #[test]
+    fn a_torn_final_record_failing_its_ecc_check_is_skipped() {
+        let mut settings = Settings::new(Pages::erased());
+        save_state(&mut settings, state(10));
+        save_state(&mut settings, state(20));
+
+        // A power loss during the last write leaves a double word with a bad
+        // ECC: whatever the bytes read, the record cannot be trusted.
+        let (page, slot) = (settings.page, settings.slot - 1);
+        settings.pages.bad_ecc.push((page, slot)).unwrap();
+
+        let mut settings = reboot(settings);
+        assert_eq!(settings.saved(), Some(state(10)));
+
+        // The torn slot is not reused.
+        save_state(&mut settings, state(30));
+        assert_eq!(reboot(settings).saved(), Some(state(30)));
+    }
+
+    #[test]
+    fn torn_chunked_values_are_ignored() {
+        let mut settings = Settings::new(Pages::erased());
+        settings.save_secret(b"first secret").unwrap();
+        settings.save_secret(b"second secret").unwrap();
+
+        // The last chunk of the second secret has been torn, leaving the one
+        // of the first secret as the newest.
+        let (page, slot) = (settings.page, settings.slot - 1);
+        settings.pages.record(page, slot)[RECORD_SIZE / 2..].fill(ERASED);
+
+        assert_eq!(reboot(settings).secret(), None);
+    }
+
+    #[test]
+    fn the_newest_sequence_number_wins_at_boot() {
+        let mut pages = Pages::erased();
+        let records = [
+            (0, 0, 7, state(70)),
+            (0, 1, 8, state(80)),
+            (1, 0, 3, state(30)),
+            (1, 1, 5, state(50)),
+        ];
+
+        for (page, slot, sequence, state) in records {
+            let record =
+                encode(UI_STATE_KEY, sequence, &Payload::from_state(&state));
+            pages.record(page, slot).copy_from_slice(&record);
+        }
+
+        let mut settings = Settings::new(pages);
+        assert_eq!(settings.saved(), Some(state(80)));
+
+        // The next record follows the newest one.
+        save_state(&mut settings, state(90));
+        assert_eq!(
+            (settings.page, settings.slot, settings.sequence),
+            (0, 3, 9)
+        );
+        assert_eq!(reboot(settings).saved(), Some(state(90)));
+    }
+}
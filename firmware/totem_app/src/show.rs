@@ -0,0 +1,406 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Show playback.
+//!
+//! The uploaded show is stored in a [`ShowPlayer`], shared by the ERCP
+//! processing task which applies the [`ShowControl`]s left by the router, and
+//! the show task which plays it. The player itself is independent of the clock:
+//! it is driven with the current time in milliseconds, which makes it possible
+//! to run it against a simulated clock.
+
+use heapless::Vec;
+use totem_protocol::{
+    show::{self, ShowAction, ShowStatus, SHOW_MAX_SIZE},
+    state::UIStatePatch,
+};
+
+/// The maximum size of a chunk of show, as carried by an ERCP Basic value.
+pub const CHUNK_MAX_LEN: usize = u8::MAX as usize;
+
+/// An error that can occur while controlling a show.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShowError {
+    /// The show does not fit in [`SHOW_MAX_SIZE`].
+    Full,
+    /// The show cannot be modified while playing.
+    Playing,
+    /// The uploaded show is invalid.
+    Invalid,
+}
+
+/// A change to the show, requested over ERCP Basic.
+// Without an allocator, the chunk cannot be stored out of the enum.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShowControl {
+    /// Clears the show, stopping it.
+    Clear,
+    /// Appends a chunk to the show.
+    Append(Vec<u8, CHUNK_MAX_LEN>),
+    /// Plays the show from a position, in milliseconds.
+    Seek(u32),
+    /// Stops the show.
+    Stop,
+}
+
+/// A show player.
+pub struct ShowPlayer {
+    /// The encoded show.
+    show: Vec<u8, SHOW_MAX_SIZE>,
+    /// The current playback, if the show is playing.
+    playback: Option<Playback>,
+}
+
+/// The state of a playing show.
+struct Playback {
+    /// The time when the show has started, or would have started when it has
+    /// been started at another position.
+    origin_ms: u32,
+    /// The offset of the next event in the encoded show, in bytes.
+    offset: usize,
+}
+
+impl ShowPlayer {
+    /// Creates an empty show player.
+    pub const fn new() -> Self {
+        Self {
+            show: Vec::new(),
+            playback: None,
+        }
+    }
+
+    /// Clears the show, stopping it.
+    pub fn clear(&mut self) {
+        self.playback = None;
+        self.show.clear();
+    }
+
+    /// Appends a chunk to the show.
+    pub fn append(&mut self, chunk: &[u8]) -> Result<(), ShowError> {
+        if self.playback.is_some() {
+            return Err(ShowError::Playing);
+        }
+
+        self.show
+            .extend_from_slice(chunk)
+            .map_err(|_| ShowError::Full)
+    }
+
+    /// Plays the show from `position_ms`, starting at `now_ms`.
+    ///
+    /// Events before the position are not played, but the UI state changes
+    /// they contain are merged in the returned patch, to be applied before
+    /// resuming.
+    pub fn seek(
+        &mut self,
+        position_ms: u32,
+        now_ms: u32,
+    ) -> Result<UIStatePatch, ShowError> {
+        if !show::is_valid(&self.show) {
+            return Err(ShowError::Invalid);
+        }
+
+        let mut patch = UIStatePatch::default();
+        let mut offset = 0;
+        let mut events = show::events(&self.show);
+
+        while let Some(Ok(event)) = events.next() {
+            if event.at_ms >= position_ms {
+                break;
+            }
+
+            if let ShowAction::Patch(event_patch) = event.action {
+                patch.merge(&event_patch);
+            }
+
+            offset = self.show.len() - events.remaining().len();
+        }
+
+        self.playback = Some(Playback {
+            origin_ms: now_ms.wrapping_sub(position_ms),
+            offset,
+        });
+
+        Ok(patch)
+    }
+
+    /// Stops the show.
+    pub fn stop(&mut self) {
+        self.playback = None;
+    }
+
+    /// Returns the next action due at `now_ms`, if any.
+    ///
+    /// This must be called until it returns `None`. The show stops after its
+    /// last event.
+    pub fn poll(&mut self, now_ms: u32) -> Option<ShowAction> {
+        let playback = self.playback.as_mut()?;
+        let position_ms = now_ms.wrapping_sub(playback.origin_ms);
+        let mut events = show::events(&self.show[playback.offset..]);
+
+        match events.next() {
+            Some(Ok(event)) if event.at_ms <= position_ms => {
+                playback.offset = self.show.len() - events.remaining().len();
+                Some(event.action)
+            }
+
+            Some(Ok(_)) => None,
+
+            _ => {
+                self.playback = None;
+                None
+            }
+        }
+    }
+
+    /// Returns the time until the next event at `now_ms`, in milliseconds, or
+    /// `None` if the show is not playing.
+    pub fn next_event_in(&self, now_ms: u32) -> Option<u32> {
+        let playback = self.playback.as_ref()?;
+        let position_ms = now_ms.wrapping_sub(playback.origin_ms);
+
+        // Past the last event, the next poll stops the show.
+        let next_ms = match show::events(&self.show[playback.offset..]).next() {
+            Some(Ok(event)) => event.at_ms,
+            _ => position_ms,
+        };
+
+        Some(next_ms.saturating_sub(position_ms))
+    }
+
+    /// Returns the playback status at `now_ms`.
+    pub fn status(&self, now_ms: u32) -> ShowStatus {
+        ShowStatus {
+            playing: self.playback.is_some(),
+            position_ms: self
+                .playback
+                .as_ref()
+                .map_or(0, |playback| now_ms.wrapping_sub(playback.origin_ms)),
+            size: self.show.len() as u16,
+        }
+    }
+}
+
+impl Default for ShowPlayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ShowControl {
+    /// Applies the change to `player` at `now_ms`.
+    ///
+    /// When seeking, the UI state at the position is returned to be applied
+    /// before resuming. Otherwise, the returned patch is empty.
+    pub fn apply(
+        &self,
+        player: &mut ShowPlayer,
+        now_ms: u32,
+    ) -> Result<UIStatePatch, ShowError> {
+        match self {
+            Self::Clear => player.clear(),
+            Self::Append(chunk) => player.append(chunk)?,
+            Self::Seek(position_ms) => {
+                return player.seek(*position_ms, now_ms)
+            }
+            Self::Stop => player.stop(),
+        }
+
+        Ok(UIStatePatch::default())
+    }
+
+    /// Returns whether the change starts, stops or clears the show, so that
+    /// the show task must run again.
+    pub fn changes_playback(&self) -> bool {
+        !matches!(self, Self::Append(_))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use totem_protocol::{
+        cue::{Cue, CueEffect},
+        show::ShowEvent,
+        state::{Brightness, Mode, UIState},
+    };
+
+    use super::*;
+
+    /// A UI state at some time of a show, in milliseconds.
+    type Timeline = Vec<(u32, UIState), 8>;
+
+    /// Returns a show changing the mode and brightness, with a flash.
+    fn show() -> Vec<u8, SHOW_MAX_SIZE> {
+        let patch = |mode, brightness: Option<u8>| {
+            ShowAction::Patch(UIStatePatch {
+                mode,
+                brightness: brightness.map(Brightness::new),
+                ..UIStatePatch::default()
+            })
+        };
+
+        let flash = ShowAction::Cue(Cue {
+            effect: CueEffect::Flash,
+            duration_ms: 100,
+        });
+
+        let events = [
+            (0, patch(Some(Mode::RandomUnicolor), None)),
+            (500, patch(None, Some(64))),
+            (500, flash),
+            (1200, patch(Some(Mode::RainbowFontain), Some(255))),
+        ];
+
+        let mut show = Vec::new();
+        for (at_ms, action) in events {
+            let mut buffer = [0; 32];
+            let event = ShowEvent { at_ms, action };
+            let encoded = event.encode(&mut buffer).unwrap();
+            show.extend_from_slice(encoded).unwrap();
+        }
+
+        show
+    }
+
+    /// Plays the show from `now_ms` like the show task does, jumping the
+    /// simulated clock to the next event after each run.
+    ///
+    /// Returns the UI state after each change, and the times of the cues.
+    fn play(
+        player: &mut ShowPlayer,
+        mut state: UIState,
+        mut now_ms: u32,
+    ) -> (Timeline, Vec<u32, 8>) {
+        let mut timeline = Timeline::new();
+        let mut cues = Vec::new();
+
+        while let Some(delay_ms) = player.next_event_in(now_ms) {
+            now_ms = now_ms.wrapping_add(delay_ms);
+
+            while let Some(action) = player.poll(now_ms) {
+                match action {
+                    ShowAction::Patch(patch) => {
+                        state.apply(&patch);
+                        timeline.push((now_ms, state)).unwrap();
+                    }
+
+                    ShowAction::Cue(_) => cues.push(now_ms).unwrap(),
+                }
+            }
+        }
+
+        (timeline, cues)
+    }
+
+    /// Returns the UI states the show goes through.
+    fn states() -> [UIState; 3] {
+        let first = UIState {
+            mode: Mode::RandomUnicolor,
+            ..UIState::default()
+        };
+
+        let second = UIState {
+            brightness: Brightness::new(64),
+            ..first
+        };
+
+        let third = UIState {
+            mode: Mode::RainbowFontain,
+            brightness: Brightness::new(255),
+            ..second
+        };
+
+        [first, second, third]
+    }
+
+    /// Returns a player loaded with the show.
+    fn player() -> ShowPlayer {
+        let mut player = ShowPlayer::new();
+        ShowControl::Append(Vec::from_slice(&show()).unwrap())
+            .apply(&mut player, 0)
+            .unwrap();
+        player
+    }
+
+    #[test]
+    fn plays_the_ui_state_timeline() {
+        let mut player = player();
+        let [first, second, third] = states();
+
+        let patch = ShowControl::Seek(0).apply(&mut player, 10_000).unwrap();
+        assert!(patch.is_empty());
+
+        let (timeline, cues) = play(&mut player, UIState::default(), 10_000);
+        assert_eq!(
+            timeline,
+            [(10_000, first), (10_500, second), (11_200, third)]
+        );
+        assert_eq!(cues, [10_500]);
+        assert!(!player.status(11_200).playing);
+    }
+
+    #[test]
+    fn seeking_applies_the_state_at_the_position() {
+        let mut player = player();
+        let [_, second, third] = states();
+
+        let patch = player.seek(800, 10_000).unwrap();
+        let mut state = UIState::default();
+        state.apply(&patch);
+        assert_eq!(state, second);
+
+        // The events before the position, including the cue, are not played.
+        let (timeline, cues) = play(&mut player, state, 10_000);
+        assert_eq!(timeline, [(10_400, third)]);
+        assert!(cues.is_empty());
+    }
+
+    #[test]
+    fn plays_across_the_clock_wrap_around() {
+        let mut player = player();
+        let [first, second, third] = states();
+        let start_ms = u32::MAX - 600;
+
+        player.seek(0, start_ms).unwrap();
+
+        let (timeline, cues) = play(&mut player, UIState::default(), start_ms);
+        assert_eq!(
+            timeline,
+            [(start_ms, first), (u32::MAX - 100, second), (599, third)]
+        );
+        assert_eq!(cues, [u32::MAX - 100]);
+    }
+
+    #[test]
+    fn the_show_is_locked_while_playing() {
+        let mut player = player();
+        player.seek(0, 0).unwrap();
+
+        assert_eq!(player.append(&[0]), Err(ShowError::Playing));
+        assert_eq!(player.status(250).position_ms, 250);
+
+        ShowControl::Stop.apply(&mut player, 250).unwrap();
+        assert!(!player.status(250).playing);
+        assert!(player.poll(1200).is_none());
+
+        // A truncated event makes the show invalid.
+        player.append(&[0x01]).unwrap();
+        assert_eq!(player.seek(0, 0), Err(ShowError::Invalid));
+
+        ShowControl::Clear.apply(&mut player, 0).unwrap();
+        assert_eq!(player.status(0).size, 0);
+    }
+}
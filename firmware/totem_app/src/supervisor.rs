@@ -0,0 +1,130 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Liveness supervision of the tasks.
+//!
+//! Each supervised task checks in with the [`Supervisor`] when it makes
+//! progress, and tells it when it becomes idle on purpose. The independent
+//! watchdog is fed only while no supervised task has stalled, i.e. missed its
+//! deadline since its last check-in: a stalled task thus leads to a watchdog
+//! reset, which is reported on the next boot.
+
+use core::fmt::{self, Display};
+
+use defmt::Format;
+
+/// The number of supervised tasks.
+const TASK_COUNT: usize = 3;
+
+/// The configuration of the supervisor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SupervisorConfig {
+    /// The timeout of the independent watchdog, in milliseconds.
+    pub watchdog_timeout_ms: u32,
+    /// The period between two checks of the tasks, in milliseconds.
+    ///
+    /// This must be shorter than the watchdog timeout.
+    pub check_period_ms: u32,
+    /// The maximum time between two check-ins of the UI task, in
+    /// milliseconds, or `None` not to supervise it.
+    pub ui_deadline_ms: Option<u32>,
+    /// The maximum time between two check-ins of the LED task while it renders
+    /// frames, in milliseconds, or `None` not to supervise it.
+    pub led_deadline_ms: Option<u32>,
+    /// The maximum time to process a received ERCP Basic frame, in
+    /// milliseconds, or `None` not to supervise it.
+    pub ercp_deadline_ms: Option<u32>,
+}
+
+/// A supervised task.
+#[derive(Debug, Format, Clone, Copy, PartialEq, Eq)]
+pub enum SupervisedTask {
+    /// The UI task, which runs periodically.
+    Ui,
+    /// The LED task, which runs periodically while rendering frames.
+    Led,
+    /// The ERCP Basic path, which runs when a frame has been received.
+    Ercp,
+}
+
+/// A supervisor of the liveness of the tasks.
+pub struct Supervisor {
+    /// The configuration.
+    config: SupervisorConfig,
+    /// The time of the last check-in of each task, or `None` while it is idle.
+    last_check_in_ms: [Option<u32>; TASK_COUNT],
+}
+
+impl Supervisor {
+    /// Creates a supervisor with all tasks idle.
+    pub const fn new(config: SupervisorConfig) -> Self {
+        Self {
+            config,
+            last_check_in_ms: [None; TASK_COUNT],
+        }
+    }
+
+    /// Records the progress of `task` at `now_ms`.
+    ///
+    /// The task is then expected to check in again before its deadline.
+    pub fn check_in(&mut self, task: SupervisedTask, now_ms: u32) {
+        self.last_check_in_ms[task as usize] = Some(now_ms);
+    }
+
+    /// Records that `task` has nothing more to do, and is not expected to
+    /// check in until it resumes.
+    pub fn idle(&mut self, task: SupervisedTask) {
+        self.last_check_in_ms[task as usize] = None;
+    }
+
+    /// Returns the first task which has missed its deadline at `now_ms`, if
+    /// any.
+    pub fn stalled(&self, now_ms: u32) -> Option<SupervisedTask> {
+        [
+            SupervisedTask::Ui,
+            SupervisedTask::Led,
+            SupervisedTask::Ercp,
+        ]
+        .into_iter()
+        .find(|&task| {
+            match (self.deadline_ms(task), self.last_check_in_ms[task as usize])
+            {
+                (Some(deadline_ms), Some(last_check_in_ms)) => {
+                    now_ms.wrapping_sub(last_check_in_ms) > deadline_ms
+                }
+                _ => false,
+            }
+        })
+    }
+
+    /// Returns the deadline of `task`, if supervised.
+    fn deadline_ms(&self, task: SupervisedTask) -> Option<u32> {
+        match task {
+            SupervisedTask::Ui => self.config.ui_deadline_ms,
+            SupervisedTask::Led => self.config.led_deadline_ms,
+            SupervisedTask::Ercp => self.config.ercp_deadline_ms,
+        }
+    }
+}
+
+impl Display for SupervisedTask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ui => write!(f, "UI"),
+            Self::Led => write!(f, "LED"),
+            Self::Ercp => write!(f, "ERCP"),
+        }
+    }
+}
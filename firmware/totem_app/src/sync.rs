@@ -0,0 +1,103 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Synchronisation with other Totems.
+//!
+//! The role of the Totem is selected at build time with the `sync_leader` and
+//! `sync_follower` features. Without any of them, the Totem runs standalone.
+
+use totem_sync::{Correction, Follower, Leader, SyncMessage};
+use totem_ui::state::UIState;
+
+/// The synchronisation of the Totem with the other ones.
+pub struct Synchroniser {
+    /// The role of the Totem in the group.
+    role: Role,
+    /// The phase of the running effect, in frames since its start.
+    phase: u32,
+}
+
+/// The role of the Totem in a synchronised group.
+enum Role {
+    /// The Totem runs on its own.
+    Standalone,
+    /// The Totem broadcasts its state to the followers.
+    Leader(Leader),
+    /// The Totem follows the state of a leader.
+    Follower(Follower),
+}
+
+impl Synchroniser {
+    /// Creates a synchroniser with the role selected at build time.
+    ///
+    /// `frame_period_ms` is the period between two frames of the LED effects.
+    pub fn new(frame_period_ms: u32) -> Self {
+        let role = if cfg!(feature = "sync_leader") {
+            Role::Leader(Leader::new())
+        } else if cfg!(feature = "sync_follower") {
+            Role::Follower(Follower::new(frame_period_ms))
+        } else {
+            Role::Standalone
+        };
+
+        Self { role, phase: 0 }
+    }
+
+    /// Resets the phase when a new effect starts.
+    pub fn reset_phase(&mut self) {
+        self.phase = 0;
+    }
+
+    /// Advances the phase by `frames`.
+    pub fn advance(&mut self, frames: u32) {
+        self.phase = self.phase.wrapping_add(frames);
+    }
+
+    /// Returns the message to broadcast at `now_ms`, if any.
+    pub fn broadcast(
+        &mut self,
+        now_ms: u32,
+        state: UIState,
+    ) -> Option<SyncMessage> {
+        match &mut self.role {
+            Role::Leader(leader) => leader.poll(now_ms, state, self.phase),
+            _ => None,
+        }
+    }
+
+    /// Handles a message received from the leader at `now_ms`.
+    pub fn receive(&mut self, message: &SyncMessage, now_ms: u32) {
+        if let Role::Follower(follower) = &mut self.role {
+            follower.receive(message, now_ms);
+        }
+    }
+
+    /// Returns the UI state of the leader, if following one.
+    pub fn state(&self, now_ms: u32) -> Option<UIState> {
+        match &self.role {
+            Role::Follower(follower) => follower.state(now_ms),
+            _ => None,
+        }
+    }
+
+    /// Returns the correction to apply to the effect before rendering a frame
+    /// at `now_ms`.
+    pub fn correction(&self, now_ms: u32) -> Correction {
+        match &self.role {
+            Role::Follower(follower) => follower.correction(self.phase, now_ms),
+            _ => Correction::None,
+        }
+    }
+}
@@ -0,0 +1,219 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Telemetry collection for the Totem application firmware.
+
+use totem_protocol::{
+    self_test::SelfTestReport,
+    telemetry::{AdcReadings, ErrorCounters, FrameTiming, Telemetry},
+};
+
+use crate::{frame_clock::FrameTick, led_strip::FrameWrite};
+
+/// The window over which the frame rate is measured, in milliseconds.
+const FRAME_RATE_WINDOW_MS: u32 = 1000;
+
+/// A monitor collecting the health metrics of the firmware.
+#[derive(Default)]
+pub struct Monitor {
+    /// The start of the current frame rate measurement window.
+    window_start_ms: u32,
+    /// The number of frames in the current window.
+    window_frames: u32,
+    /// The frame rate measured over the last complete window.
+    frame_rate: u32,
+    /// The maximum execution time of the LED task.
+    max_led_task_time_us: u32,
+    /// The number of failed task spawns.
+    spawn_failures: u32,
+    /// The number of ERCP Basic reception errors.
+    ercp_rx_errors: u32,
+    /// The number of ERCP Basic processing errors.
+    ercp_process_errors: u32,
+    /// The number of failed attempts to write a frame to the LED strip.
+    led_write_errors: u32,
+    /// The number of frames dropped after all write attempts have failed.
+    led_frames_dropped: u32,
+    /// The jitter of the frames in the current window.
+    jitter_window: JitterWindow,
+    /// The mean jitter over the last complete window, in microseconds.
+    mean_jitter_us: u32,
+    /// The maximum jitter over the last complete window, in microseconds.
+    max_jitter_us: u32,
+    /// The maximum jitter since boot, in microseconds.
+    peak_jitter_us: u32,
+    /// The number of frames skipped since boot.
+    skipped_frames: u32,
+    /// The report of the power-on self-test, if it has run.
+    self_test: Option<SelfTestReport>,
+}
+
+/// The jitter of the frames over a measurement window.
+#[derive(Default)]
+struct JitterWindow {
+    /// The start of the window.
+    start_ms: u32,
+    /// The number of frames in the window.
+    frames: u32,
+    /// The sum of the jitters in the window, in microseconds.
+    sum_us: u32,
+    /// The maximum jitter in the window, in microseconds.
+    max_us: u32,
+}
+
+impl Monitor {
+    /// Creates a new monitor.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records an LED frame rendered at `now_ms`, whose computation took
+    /// `execution_time_us`.
+    pub fn record_frame(&mut self, now_ms: u32, execution_time_us: u32) {
+        self.max_led_task_time_us =
+            self.max_led_task_time_us.max(execution_time_us);
+
+        let elapsed = now_ms.wrapping_sub(self.window_start_ms);
+        if elapsed >= FRAME_RATE_WINDOW_MS {
+            self.frame_rate = self.window_frames * 1000 / elapsed;
+            self.window_start_ms = now_ms;
+            self.window_frames = 0;
+        }
+
+        self.window_frames += 1;
+    }
+
+    /// Records the timing of a frame started at `now_ms`.
+    pub fn record_frame_tick(&mut self, now_ms: u32, tick: FrameTick) {
+        let window = &mut self.jitter_window;
+
+        let elapsed = now_ms.wrapping_sub(window.start_ms);
+        if elapsed >= FRAME_RATE_WINDOW_MS {
+            self.mean_jitter_us =
+                window.sum_us.checked_div(window.frames).unwrap_or(0);
+            self.max_jitter_us = window.max_us;
+            *window = JitterWindow {
+                start_ms: now_ms,
+                ..Default::default()
+            };
+        }
+
+        window.frames += 1;
+        window.sum_us = window.sum_us.saturating_add(tick.jitter_us);
+        window.max_us = window.max_us.max(tick.jitter_us);
+
+        self.peak_jitter_us = self.peak_jitter_us.max(tick.jitter_us);
+        self.skipped_frames = self.skipped_frames.saturating_add(tick.skipped);
+    }
+
+    /// Records the outcome of writing a frame to the LED strip.
+    pub fn record_frame_write(&mut self, write: FrameWrite) {
+        self.led_write_errors =
+            self.led_write_errors.saturating_add(write.failed_attempts);
+
+        if !write.written {
+            self.led_frames_dropped = self.led_frames_dropped.saturating_add(1);
+        }
+    }
+
+    /// Records a failed task spawn.
+    pub fn record_spawn_failure(&mut self) {
+        self.spawn_failures = self.spawn_failures.saturating_add(1);
+    }
+
+    /// Records an error while receiving an ERCP Basic frame.
+    pub fn record_ercp_rx_error(&mut self) {
+        self.ercp_rx_errors = self.ercp_rx_errors.saturating_add(1);
+    }
+
+    /// Records an error while processing an ERCP Basic frame.
+    pub fn record_ercp_process_error(&mut self) {
+        self.ercp_process_errors = self.ercp_process_errors.saturating_add(1);
+    }
+
+    /// Records the report of the power-on self-test.
+    pub fn record_self_test(&mut self, report: SelfTestReport) {
+        self.self_test = Some(report);
+    }
+
+    /// Returns the report of the power-on self-test, if it has run.
+    pub fn self_test(&self) -> Option<SelfTestReport> {
+        self.self_test
+    }
+
+    /// Builds the error counters.
+    pub fn error_counters(&self, screen_failed: bool) -> ErrorCounters {
+        ErrorCounters {
+            screen_failed,
+            led_write_errors: self.led_write_errors,
+            led_frames_dropped: self.led_frames_dropped,
+            spawn_failures: self.spawn_failures,
+        }
+    }
+
+    /// Builds the frame timing at `now_ms`, for frames with a period of
+    /// `period_us`.
+    pub fn frame_timing(&self, now_ms: u32, period_us: u32) -> FrameTiming {
+        // As for the frame rate, the jitter is not measured anymore when no
+        // frame has been rendered for a whole window.
+        let (mean_jitter_us, max_jitter_us) = if now_ms
+            .wrapping_sub(self.jitter_window.start_ms)
+            < 2 * FRAME_RATE_WINDOW_MS
+        {
+            (self.mean_jitter_us, self.max_jitter_us)
+        } else {
+            (0, 0)
+        };
+
+        FrameTiming {
+            period_us,
+            mean_jitter_us,
+            max_jitter_us,
+            peak_jitter_us: self.peak_jitter_us,
+            skipped_frames: self.skipped_frames,
+        }
+    }
+
+    /// Builds the telemetry at `now_ms`.
+    pub fn telemetry(
+        &self,
+        now_ms: u32,
+        target_frame_rate: u32,
+        screen_present: bool,
+        adc_readings: Option<AdcReadings>,
+    ) -> Telemetry {
+        // If no frame has been rendered for a whole window, the LEDs are not
+        // refreshed anymore, for instance in Off mode.
+        let frame_rate = if now_ms.wrapping_sub(self.window_start_ms)
+            < 2 * FRAME_RATE_WINDOW_MS
+        {
+            self.frame_rate
+        } else {
+            0
+        };
+
+        Telemetry {
+            uptime_ms: now_ms,
+            target_frame_rate,
+            frame_rate,
+            max_led_task_time_us: self.max_led_task_time_us,
+            spawn_failures: self.spawn_failures,
+            ercp_rx_errors: self.ercp_rx_errors,
+            ercp_process_errors: self.ercp_process_errors,
+            screen_present,
+            adc_readings,
+        }
+    }
+}
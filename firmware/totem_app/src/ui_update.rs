@@ -0,0 +1,64 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Coalescing of the UI state updates received over ERCP Basic.
+//!
+//! UI state updates are not applied while processing ERCP Basic frames, but
+//! merged into a [`PendingUpdate`] that the UI task applies on its next run.
+//! This way, the latest value of each field always wins, even when the host
+//! sends updates faster than the UI task runs.
+
+use totem_ui::state::UIStatePatch;
+
+/// The maximum time an update can stay pending before the UI is considered
+/// busy, in milliseconds.
+const MAX_LATENCY_MS: u32 = 100;
+
+/// A UI state update waiting to be applied.
+#[derive(Default)]
+pub struct PendingUpdate {
+    /// The merged patch.
+    patch: Option<UIStatePatch>,
+    /// The time when the oldest unapplied change has been received.
+    since_ms: u32,
+}
+
+impl PendingUpdate {
+    /// Creates an empty pending update.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns whether the UI is lagging behind, i.e. an update has been
+    /// pending for longer than the maximum latency at `now_ms`.
+    pub fn is_busy(&self, now_ms: u32) -> bool {
+        self.patch.is_some()
+            && now_ms.wrapping_sub(self.since_ms) > MAX_LATENCY_MS
+    }
+
+    /// Merges a patch received at `now_ms` into the pending update.
+    pub fn merge(&mut self, patch: &UIStatePatch, now_ms: u32) {
+        if self.patch.is_none() {
+            self.since_ms = now_ms;
+        }
+
+        self.patch.get_or_insert_with(Default::default).merge(patch);
+    }
+
+    /// Takes the pending update, if any.
+    pub fn take(&mut self) -> Option<UIStatePatch> {
+        self.patch.take()
+    }
+}
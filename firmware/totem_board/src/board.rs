@@ -25,8 +25,10 @@ use crate::{
     i2c::{self, I2c},
     peripheral::*,
     prelude::*,
+    rng::RngExt as _,
     serial::{self, Serial},
     spi::Spi,
+    storage::SettingsFlash,
 };
 
 /// The Totem board.
@@ -57,6 +59,14 @@ pub struct Board {
     pub screen: Option<Screen>,
     /// The serial for ERCP Basic.
     pub ercp_serial: ErcpSerial,
+    /// The serial for the Totem synchronisation.
+    pub sync_serial: SyncSerial,
+    /// The flash pages for the settings.
+    pub settings_flash: SettingsFlash,
+    /// The independent watchdog, not started yet.
+    pub watchdog: Watchdog,
+    /// The hardware random number generator.
+    pub rng: Rng,
 }
 
 impl Board {
@@ -69,7 +79,12 @@ impl Board {
         let mut rcc = dp.RCC.constrain();
         let mut flash = dp.FLASH.constrain();
         let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
-        let clocks = rcc.cfgr.sysclk(80.MHz()).freeze(&mut flash.acr, &mut pwr);
+        // The HSI48 oscillator clocks the random number generator.
+        let clocks = rcc
+            .cfgr
+            .sysclk(80.MHz())
+            .hsi48(true)
+            .freeze(&mut flash.acr, &mut pwr);
 
         let mut gpioa = dp.GPIOA.split(&mut rcc.ahb2);
         let mut gpiob = dp.GPIOB.split(&mut rcc.ahb2);
@@ -133,6 +148,18 @@ impl Board {
             &mut gpioa.afrl,
         );
 
+        let sync_tx = gpioa.pa9.into_alternate(
+            &mut gpioa.moder,
+            &mut gpioa.otyper,
+            &mut gpioa.afrh,
+        );
+
+        let sync_rx = gpioa.pa10.into_alternate(
+            &mut gpioa.moder,
+            &mut gpioa.otyper,
+            &mut gpioa.afrh,
+        );
+
         let mut delay = AsmDelay::new(clocks.sysclk().to_Hz());
 
         let p_adc = ADC::new(
@@ -169,7 +196,25 @@ impl Board {
 
         ercp_serial.listen(serial::Event::Rxne);
 
+        let mut sync_serial = Serial::usart1(
+            dp.USART1,
+            (sync_tx, sync_rx),
+            serial::Config::default().baudrate(115_200.bps()),
+            clocks,
+            &mut rcc.apb2,
+        );
+
+        sync_serial.listen(serial::Event::Rxne);
+
+        let rng = dp.RNG.enable(&mut rcc.ahb2, clocks, &mut rcc.crrcr);
+
         let led_strip = Ws2812::new(led_spi, led_buffer);
+        let settings_flash = SettingsFlash::new(flash);
+
+        // Freeze the watchdog while the core is halted by a debugger, so that
+        // breakpoints do not reset the board.
+        let watchdog = Watchdog::new(dp.IWDG);
+        watchdog.stop_on_debug(&dp.DBGMCU, true);
 
         // If the screen is not available for any reason, this would fail. Then,
         // let’s just map the Result to an Option, and assume that there may or
@@ -197,6 +242,10 @@ impl Board {
             led_strip,
             screen,
             ercp_serial,
+            sync_serial,
+            settings_flash,
+            watchdog,
+            rng,
         }
     }
 }
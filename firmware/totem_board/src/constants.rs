@@ -15,17 +15,38 @@
 
 //! Constants of the Totem board.
 
-/// The number of LEDs per strip.
-pub const LEDS_PER_STRIP: usize = 13;
+use crate::layout::{LedLayout, MAX_NUM_LEDS};
 
-/// The number of LEDs per half strip.
-pub const LEDS_PER_HALF_STRIP: usize = 7;
+/// The LED layout of the board: 2 strips of 13 LEDs on each of the 4 sides.
+#[cfg(not(feature = "profile_20"))]
+pub const LED_LAYOUT: LedLayout = LedLayout {
+    leds_per_strip: 13,
+    strips: 8,
+};
 
-/// The number of LED strips per side.
-pub const STRIPS_PER_SIDE: usize = 2;
+/// The LED layout of the board: 2 strips of 20 LEDs on each of the 4 sides.
+#[cfg(feature = "profile_20")]
+pub const LED_LAYOUT: LedLayout = LedLayout {
+    leds_per_strip: 20,
+    strips: 8,
+};
 
-/// The total number of LEDs.
-pub const NUM_LEDS: usize = LEDS_PER_STRIP * STRIPS_PER_SIDE * 4;
+const _: () = assert!(LED_LAYOUT.is_valid());
 
-/// The size of the buffer for the LED driver.
-pub const LED_BUFFER_SIZE: usize = NUM_LEDS * 12 + 20;
+/// The size of the buffer for the LED driver, large enough for any layout.
+pub const LED_BUFFER_SIZE: usize = MAX_NUM_LEDS * 12 + 20;
+
+/// The start address of the flash.
+pub const FLASH_ORIGIN: usize = 0x0800_0000;
+
+/// The size of a flash page, in bytes.
+pub const FLASH_PAGE_SIZE: usize = 2048;
+
+/// The first flash page reserved for the settings.
+///
+/// The settings use the last pages of the second bank, which are excluded from
+/// the firmware in `memory.x`.
+pub const SETTINGS_FIRST_PAGE: usize = 510;
+
+/// The number of flash pages reserved for the settings.
+pub const SETTINGS_PAGES: usize = 2;
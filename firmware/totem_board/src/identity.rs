@@ -0,0 +1,33 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Identity of the board.
+
+use crate::signature::Uid;
+
+/// Returns the 96-bit unique ID of the microcontroller.
+///
+/// The bytes are in the order of the unique ID registers: the wafer
+/// coordinates, the wafer number and the lot number.
+pub fn unique_id() -> [u8; 12] {
+    let uid = Uid::get();
+    let mut bytes = [0; 12];
+
+    bytes[0..2].copy_from_slice(&uid.x().to_le_bytes());
+    bytes[2..4].copy_from_slice(&uid.y().to_le_bytes());
+    bytes[4] = uid.waf_num();
+    bytes[5..].copy_from_slice(&uid.lot_num().as_bytes()[..7]);
+    bytes
+}
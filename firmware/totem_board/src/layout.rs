@@ -0,0 +1,57 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Layouts of the LED strips.
+//!
+//! The LEDs of a Totem are chained in strips of the same length. The layout
+//! varies between Totem builds: buffers are sized for the maximum layout, and
+//! effects are rendered for the actual one, chosen by the board profile.
+//!
+//! The board profile is selected at build time with a cargo feature. The
+//! layout cannot be chosen at runtime, as the chasers of `led_effects` take
+//! their number of LEDs as a const generic.
+
+/// The maximum number of LEDs per strip.
+pub const MAX_LEDS_PER_STRIP: usize = 32;
+
+/// The maximum number of strips.
+pub const MAX_STRIPS: usize = 8;
+
+/// The maximum total number of LEDs.
+pub const MAX_NUM_LEDS: usize = MAX_LEDS_PER_STRIP * MAX_STRIPS;
+
+/// A layout of the LED strips.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LedLayout {
+    /// The number of LEDs per strip.
+    pub leds_per_strip: usize,
+    /// The number of strips.
+    pub strips: usize,
+}
+
+impl LedLayout {
+    /// Returns the total number of LEDs.
+    pub const fn num_leds(&self) -> usize {
+        self.leds_per_strip * self.strips
+    }
+
+    /// Returns whether the layout fits in the maximum one.
+    pub const fn is_valid(&self) -> bool {
+        self.leds_per_strip > 0
+            && self.leds_per_strip <= MAX_LEDS_PER_STRIP
+            && self.strips > 0
+            && self.strips <= MAX_STRIPS
+    }
+}
@@ -21,7 +21,7 @@
 #![warn(clippy::use_self)]
 #![deny(missing_docs)]
 #![deny(unused_must_use)]
-#![forbid(unsafe_code)]
+#![deny(unsafe_code)]
 
 pub use stm32l4xx_hal as hal;
 
@@ -32,4 +32,9 @@ pub use cortex_m::*;
 
 pub mod board;
 pub mod constants;
+pub mod identity;
+pub mod layout;
 pub mod peripheral;
+pub mod reset;
+pub mod retained;
+pub mod storage;
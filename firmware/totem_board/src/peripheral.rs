@@ -21,13 +21,16 @@ use ws2812_spi::prerendered::Ws2812;
 use crate::{
     adc::{Channel, ADC},
     gpio::{
-        Alternate, Analog, Input, OpenDrain, PullDown, PushPull, PA0, PA1, PA2,
-        PA3, PA4, PA5, PA6, PA7, PB0, PB8, PB9, PC0, PC1, PC2, PC3, PC4,
+        Alternate, Analog, Input, OpenDrain, PullDown, PushPull, PA0, PA1,
+        PA10, PA2, PA3, PA4, PA5, PA6, PA7, PA9, PB0, PB8, PB9, PC0, PC1, PC2,
+        PC3, PC4,
     },
     i2c::I2c,
-    serial::Serial,
+    rng,
+    serial::{Rx, Serial, Tx},
     spi::Spi,
-    I2C1, SPI1, USART2,
+    watchdog::IndependentWatchdog,
+    I2C1, SPI1, USART1, USART2,
 };
 
 /// The pin for the first potentiometer.
@@ -78,6 +81,12 @@ pub type ERCP_TX = PA2<Alternate<PushPull, 7>>;
 /// The ERCP Basic Rx line.
 pub type ERCP_RX = PA3<Alternate<PushPull, 7>>;
 
+/// The Totem synchronisation Tx line.
+pub type SYNC_TX = PA9<Alternate<PushPull, 7>>;
+
+/// The Totem synchronisation Rx line.
+pub type SYNC_RX = PA10<Alternate<PushPull, 7>>;
+
 /// The ADC for potentiometers.
 pub type P_ADC = ADC;
 
@@ -90,6 +99,9 @@ pub type SCREEN_I2C = I2C1;
 /// The UART peripheral for ERCP Basic.
 pub type ERCP_UART = USART2;
 
+/// The UART peripheral for the Totem synchronisation.
+pub type SYNC_UART = USART1;
+
 /// The SPI for driving LEDs.
 pub type LedSpi = Spi<LED_SPI, (LED_SCK, LED_MISO, LED_MOSI)>;
 
@@ -99,12 +111,27 @@ pub type ScreenI2c = I2c<SCREEN_I2C, (SCREEN_SCL, SCREEN_SDA)>;
 /// The serial for ERCP Basic.
 pub type ErcpSerial = Serial<ERCP_UART, (ERCP_TX, ERCP_RX)>;
 
+/// The serial for the Totem synchronisation.
+pub type SyncSerial = Serial<SYNC_UART, (SYNC_TX, SYNC_RX)>;
+
+/// The transmitting half of the serial for the Totem synchronisation.
+pub type SyncTx = Tx<SYNC_UART>;
+
+/// The receiving half of the serial for the Totem synchronisation.
+pub type SyncRx = Rx<SYNC_UART>;
+
 /// The LED strip driver.
 pub type LedStrip = Ws2812<'static, LedSpi>;
 
 /// The LCD screen driver.
 pub type Screen = Lcd<ScreenI2c>;
 
+/// The independent watchdog.
+pub type Watchdog = IndependentWatchdog;
+
+/// The hardware random number generator.
+pub type Rng = rng::Rng;
+
 /// The LCD screen I²C address.
 pub const SCREEN_LCD_ADDRESS: u8 = 0x7C >> 1;
 
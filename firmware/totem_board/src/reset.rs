@@ -0,0 +1,57 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Reset flags of the microcontroller.
+
+use crate::pac::RCC;
+
+/// The reset flags of the microcontroller.
+///
+/// Several flags can be set at once: for instance, the reset pin flag is set
+/// on any reset, and the brown-out flag on power-on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResetFlags {
+    /// An illegal low-power mode has been entered.
+    pub low_power: bool,
+    /// The window watchdog has expired.
+    pub window_watchdog: bool,
+    /// The independent watchdog has expired.
+    pub independent_watchdog: bool,
+    /// The firmware has requested a reset.
+    pub software: bool,
+    /// The supply voltage has dropped below the brown-out threshold.
+    pub brown_out: bool,
+    /// The reset pin has been pulled low.
+    pub pin: bool,
+}
+
+impl ResetFlags {
+    /// Reads the reset flags, then clears them so that the next reset reports
+    /// only its own.
+    pub fn take(rcc: &RCC) -> Self {
+        let csr = rcc.csr.read();
+        let flags = Self {
+            low_power: csr.lpwrstf().bit_is_set(),
+            window_watchdog: csr.wwdgrstf().bit_is_set(),
+            independent_watchdog: csr.iwdgrstf().bit_is_set(),
+            software: csr.sftrstf().bit_is_set(),
+            brown_out: csr.borrstf().bit_is_set(),
+            pin: csr.pinrstf().bit_is_set(),
+        };
+
+        rcc.csr.modify(|_, w| w.rmvf().set_bit());
+        flags
+    }
+}
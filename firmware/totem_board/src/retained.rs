@@ -0,0 +1,57 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! RAM retained across resets.
+//!
+//! The retained region lives in the `.uninit` section, which the runtime does
+//! not initialise at boot. Its content survives a reset, but is random after a
+//! power-on: users must validate it, for instance with a magic number.
+
+#![allow(unsafe_code)]
+
+use core::{
+    mem::MaybeUninit,
+    ptr::{self, addr_of, addr_of_mut},
+};
+
+use cortex_m::interrupt;
+
+/// The size of the retained region, in bytes.
+pub const RETAINED_SIZE: usize = 128;
+
+/// The retained region.
+#[link_section = ".uninit.RETAINED"]
+static mut RETAINED: MaybeUninit<[u8; RETAINED_SIZE]> = MaybeUninit::uninit();
+
+/// Reads the retained region.
+pub fn read() -> [u8; RETAINED_SIZE] {
+    // SAFETY: Accesses are done with interrupts disabled, so they cannot race.
+    // The region is plain RAM: after a power-on, it holds random bytes which
+    // are all valid `u8`s.
+    interrupt::free(|_| unsafe {
+        ptr::read_volatile(addr_of!(RETAINED).cast::<[u8; RETAINED_SIZE]>())
+    })
+}
+
+/// Writes the retained region.
+pub fn write(bytes: &[u8; RETAINED_SIZE]) {
+    // SAFETY: Accesses are done with interrupts disabled, so they cannot race.
+    interrupt::free(|_| unsafe {
+        ptr::write_volatile(
+            addr_of_mut!(RETAINED).cast::<[u8; RETAINED_SIZE]>(),
+            *bytes,
+        )
+    })
+}
@@ -0,0 +1,143 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Flash storage for the settings.
+//!
+//! The settings pages lie in the second bank of the flash, while the firmware
+//! runs from the first one: they can be erased and written without stalling
+//! the CPU.
+//!
+//! Each double word of the flash is protected by an ECC. Reading a double word
+//! with two bad bits, like one torn by a power loss while being programmed,
+//! raises a non-maskable interrupt. The handler defined here clears the error
+//! so that the read completes, and [`SettingsFlash::read`] reports it as
+//! [`StorageError::Ecc`] instead of letting the default handler hang.
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use cortex_m_rt::exception;
+
+use crate::{
+    constants::{
+        FLASH_ORIGIN, FLASH_PAGE_SIZE, SETTINGS_FIRST_PAGE, SETTINGS_PAGES,
+    },
+    flash::{self, FlashPage, Read as _, UnlockedFlash, WriteErase as _},
+    pac::{flash::ECCR, FLASH},
+};
+
+/// Whether a double ECC error has been detected since the last read.
+static ECC_ERROR: AtomicBool = AtomicBool::new(false);
+
+/// An error that can occur when accessing the settings pages.
+#[derive(Debug)]
+pub enum StorageError {
+    /// The flash controller has reported an error.
+    Flash(flash::Error),
+    /// The data read has failed its ECC check.
+    Ecc,
+}
+
+/// The flash pages reserved for the settings.
+pub struct SettingsFlash {
+    /// The flash peripheral.
+    flash: flash::Parts,
+}
+
+impl SettingsFlash {
+    /// Creates the settings storage from the flash peripheral.
+    pub fn new(flash: flash::Parts) -> Self {
+        Self { flash }
+    }
+
+    /// Reads bytes from a settings page, starting at `offset`.
+    ///
+    /// The content of `buffer` is not reliable if the read fails its ECC
+    /// check.
+    pub fn read(
+        &mut self,
+        page: usize,
+        offset: usize,
+        buffer: &mut [u8],
+    ) -> Result<(), StorageError> {
+        ECC_ERROR.store(false, Ordering::SeqCst);
+        self.unlock()?.read(address(page, offset), buffer);
+
+        if ECC_ERROR.swap(false, Ordering::SeqCst) {
+            Err(StorageError::Ecc)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Erases a settings page.
+    pub fn erase(&mut self, page: usize) -> Result<(), StorageError> {
+        debug_assert!(page < SETTINGS_PAGES);
+        self.unlock()?
+            .erase_page(FlashPage(SETTINGS_FIRST_PAGE + page))
+            .map_err(StorageError::Flash)
+    }
+
+    /// Writes bytes to a settings page, starting at `offset`.
+    ///
+    /// The flash is written by double words: `offset` must be aligned on 8
+    /// bytes, and the destination must have been erased.
+    pub fn write(
+        &mut self,
+        page: usize,
+        offset: usize,
+        data: &[u8],
+    ) -> Result<(), StorageError> {
+        self.unlock()?
+            .write(address(page, offset), data)
+            .map_err(StorageError::Flash)
+    }
+
+    /// Unlocks the flash, which is locked again when dropping the result.
+    fn unlock(&mut self) -> Result<UnlockedFlash<'_>, StorageError> {
+        let flash::Parts { keyr, sr, cr, .. } = &mut self.flash;
+        keyr.unlock_flash(sr, cr).map_err(StorageError::Flash)
+    }
+}
+
+/// Handles the non-maskable interrupts.
+///
+/// A double ECC error is cleared and recorded for [`SettingsFlash::read`].
+/// There is no other expected cause of NMI.
+#[exception]
+fn NonMaskableInt() {
+    let eccr = eccr();
+
+    if eccr.read().eccd().bit_is_set() {
+        // The flag is cleared by writing 1.
+        eccr.modify(|_, w| w.eccd().set_bit());
+        ECC_ERROR.store(true, Ordering::SeqCst);
+    } else {
+        panic!("Unexpected non-maskable interrupt.");
+    }
+}
+
+/// Returns the ECC register of the flash.
+#[allow(unsafe_code)]
+fn eccr() -> &'static ECCR {
+    // SAFETY: The register is only accessed by the NMI handler, and the HAL
+    // does not expose it.
+    unsafe { &(*FLASH::ptr()).eccr }
+}
+
+/// Returns the address of a byte in a settings page.
+fn address(page: usize, offset: usize) -> usize {
+    debug_assert!(page < SETTINGS_PAGES && offset < FLASH_PAGE_SIZE);
+    FLASH_ORIGIN + (SETTINGS_FIRST_PAGE + page) * FLASH_PAGE_SIZE + offset
+}
@@ -0,0 +1,102 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Challenge / response authentication for Totem commands.
+//!
+//! When a shared secret is configured on the Totem, commands changing its
+//! state are rejected with a
+//! [`UNAUTHENTICATED`](crate::nack_reason::UNAUTHENTICATED) NACK until the
+//! host authenticates:
+//!
+//! 1. the host sends an Auth_Challenge command, to which the Totem replies
+//!    with a random nonce of [`NONCE_LEN`] bytes;
+//! 2. the host sends an Auth_Response command with the HMAC-SHA256 of the
+//!    nonce, keyed with the shared secret (see [`response`]);
+//! 3. the Totem replies with an ACK if the response is valid, opening the
+//!    session until an Auth_Logout command or a reset.
+//!
+//! Each nonce can be used only once: a new challenge is required after any
+//! response, valid or not.
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+/// The length of a challenge nonce.
+pub const NONCE_LEN: usize = 16;
+/// The length of a challenge response.
+pub const RESPONSE_LEN: usize = 32;
+/// The maximum length of the shared secret.
+pub const SECRET_MAX_LEN: usize = 32;
+
+/// Computes the response to a challenge.
+pub fn response(secret: &[u8], nonce: &[u8]) -> [u8; RESPONSE_LEN] {
+    let mut response = [0; RESPONSE_LEN];
+    response.copy_from_slice(&hmac(secret, nonce).finalize().into_bytes());
+    response
+}
+
+/// Verifies the response to a challenge in constant time.
+pub fn verify(secret: &[u8], nonce: &[u8], response: &[u8]) -> bool {
+    hmac(secret, nonce).verify_slice(response).is_ok()
+}
+
+fn hmac(secret: &[u8], nonce: &[u8]) -> Hmac<Sha256> {
+    // HMAC accepts keys of any length, so this cannot fail.
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).unwrap();
+    mac.update(nonce);
+    mac
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_response_is_verified_with_the_same_secret() {
+        let nonce = [0x5A; NONCE_LEN];
+        let response = response(b"secret", &nonce);
+
+        assert!(verify(b"secret", &nonce, &response));
+        assert!(!verify(b"other", &nonce, &response));
+        assert!(!verify(b"secret", &[0xA5; NONCE_LEN], &response));
+    }
+
+    #[test]
+    fn a_wrong_or_truncated_response_is_rejected() {
+        let nonce = [0x5A; NONCE_LEN];
+        let mut response = response(b"secret", &nonce);
+
+        assert!(!verify(b"secret", &nonce, &response[..RESPONSE_LEN - 1]));
+        assert!(!verify(b"secret", &nonce, &[]));
+
+        response[RESPONSE_LEN - 1] ^= 0x80;
+        assert!(!verify(b"secret", &nonce, &response));
+    }
+
+    #[test]
+    fn the_response_is_the_hmac_sha256_of_the_nonce() {
+        // RFC 4231, test case 2.
+        let expected = [
+            0x5b, 0xdc, 0xc1, 0x46, 0xbf, 0x60, 0x75, 0x4e, 0x6a, 0x04, 0x24,
+            0x26, 0x08, 0x95, 0x75, 0xc7, 0x5a, 0x00, 0x3f, 0x08, 0x9d, 0x27,
+            0x39, 0x83, 0x9d, 0xec, 0x58, 0xb9, 0x64, 0xec, 0x38, 0x43,
+        ];
+
+        assert_eq!(
+            response(b"Jefe", b"what do ya want for nothing?"),
+            expected
+        );
+    }
+}
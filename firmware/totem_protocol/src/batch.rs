@@ -0,0 +1,103 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Batches: several UI state changes applied together.
+//!
+//! A batch is a sequence of [`BatchItem`]s, each encoded with postcard and
+//! concatenated. The Totem applies either all of them or none: the items are
+//! merged into a single patch, which the UI task turns into a single state
+//! change, so that no intermediate combination is ever rendered.
+//!
+//! The Totem replies with one status per item: [`OK`] if the item is valid, or
+//! the NACK reason it has been rejected for otherwise. The batch has been
+//! applied only if all the statuses are [`OK`]. As the next item cannot be
+//! found after an item that cannot be decoded, the statuses stop there.
+
+use defmt::Format;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    codec::{self, Sequence},
+    state::{Brightness, Mode, ScreenState, Speed, Temperature, UIStatePatch},
+    EncodeError,
+};
+
+/// The maximum number of items in a batch.
+pub const MAX_ITEMS: usize = 32;
+
+/// The status of a valid item.
+pub const OK: u8 = 0x00;
+
+/// An item of a batch.
+#[derive(Debug, Format, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum BatchItem {
+    /// Sets the mode.
+    Mode(Mode),
+    /// Sets the brightness.
+    Brightness(Brightness),
+    /// Sets the speed.
+    Speed(Speed),
+    /// Sets the color temperature.
+    Temperature(Temperature),
+    /// Sets the screen state.
+    ScreenState(ScreenState),
+}
+
+impl BatchItem {
+    /// Encodes the item in `buffer`, to be appended to a batch.
+    pub fn encode<'b>(
+        &self,
+        buffer: &'b mut [u8],
+    ) -> Result<&'b [u8], EncodeError> {
+        codec::encode(self, buffer)
+    }
+
+    /// Returns whether the item is valid.
+    pub fn is_valid(&self) -> bool {
+        UIStatePatch::from(*self).is_valid()
+    }
+}
+
+impl From<BatchItem> for UIStatePatch {
+    fn from(item: BatchItem) -> Self {
+        let mut patch = Self::default();
+
+        match item {
+            BatchItem::Mode(mode) => patch.mode = Some(mode),
+            BatchItem::Brightness(brightness) => {
+                patch.brightness = Some(brightness)
+            }
+            BatchItem::Speed(speed) => patch.speed = Some(speed),
+            BatchItem::Temperature(temperature) => {
+                patch.temperature = Some(temperature)
+            }
+            BatchItem::ScreenState(screen_state) => {
+                patch.screen_state = Some(screen_state)
+            }
+        }
+
+        patch
+    }
+}
+
+/// Returns an iterator over the items of an encoded batch.
+pub fn items(batch: &[u8]) -> Sequence<'_, BatchItem> {
+    Sequence::new(batch)
+}
+
+/// Returns whether a batch has been applied, given the statuses of its items.
+pub fn is_applied(statuses: &[u8]) -> bool {
+    statuses.iter().all(|&status| status == OK)
+}
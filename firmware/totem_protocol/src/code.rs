@@ -0,0 +1,121 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! ERCP Basic command codes of the Totem protocol.
+//!
+//! Codes below `0x20` are reserved for ERCP Basic built-in commands. The reply
+//! to a command, if any, uses the next code.
+
+/// The UI_Update ERCP Basic command code.
+pub const UI_UPDATE: u8 = 0x20;
+/// The UI_Patch ERCP Basic command code.
+pub const UI_PATCH: u8 = 0x21;
+/// The Set_Mode ERCP Basic command code.
+pub const SET_MODE: u8 = 0x22;
+/// The Set_Brightness ERCP Basic command code.
+pub const SET_BRIGHTNESS: u8 = 0x23;
+/// The Set_Speed ERCP Basic command code.
+pub const SET_SPEED: u8 = 0x24;
+/// The Set_Temperature ERCP Basic command code.
+pub const SET_TEMPERATURE: u8 = 0x25;
+/// The Set_Screen_State ERCP Basic command code.
+pub const SET_SCREEN_STATE: u8 = 0x26;
+/// The Set_Host_Watchdog ERCP Basic command code.
+pub const SET_HOST_WATCHDOG: u8 = 0x27;
+/// The Batch ERCP Basic command code.
+pub const BATCH: u8 = 0x28;
+/// The Batch_Reply ERCP Basic command code.
+pub const BATCH_REPLY: u8 = 0x29;
+/// The Host_Link_Status ERCP Basic command code.
+pub const HOST_LINK_STATUS: u8 = 0x2A;
+/// The Host_Link_Status_Reply ERCP Basic command code.
+pub const HOST_LINK_STATUS_REPLY: u8 = 0x2B;
+
+/// The Telemetry ERCP Basic command code.
+pub const TELEMETRY: u8 = 0x30;
+/// The Telemetry_Reply ERCP Basic command code.
+pub const TELEMETRY_REPLY: u8 = 0x31;
+/// The Log_Drain ERCP Basic command code.
+pub const LOG_DRAIN: u8 = 0x32;
+/// The Log_Drain_Reply ERCP Basic command code.
+pub const LOG_DRAIN_REPLY: u8 = 0x33;
+/// The Inputs ERCP Basic command code.
+pub const INPUTS: u8 = 0x34;
+/// The Inputs_Reply ERCP Basic command code.
+pub const INPUTS_REPLY: u8 = 0x35;
+/// The Protocol_Version ERCP Basic command code.
+pub const PROTOCOL_VERSION: u8 = 0x36;
+/// The Protocol_Version_Reply ERCP Basic command code.
+pub const PROTOCOL_VERSION_REPLY: u8 = 0x37;
+/// The Self_Test ERCP Basic command code.
+pub const SELF_TEST: u8 = 0x38;
+/// The Self_Test_Reply ERCP Basic command code.
+pub const SELF_TEST_REPLY: u8 = 0x39;
+/// The Reset_Report ERCP Basic command code.
+pub const RESET_REPORT: u8 = 0x3A;
+/// The Reset_Report_Reply ERCP Basic command code.
+pub const RESET_REPORT_REPLY: u8 = 0x3B;
+/// The Error_Counters ERCP Basic command code.
+pub const ERROR_COUNTERS: u8 = 0x3C;
+/// The Error_Counters_Reply ERCP Basic command code.
+pub const ERROR_COUNTERS_REPLY: u8 = 0x3D;
+/// The Frame_Timing ERCP Basic command code.
+pub const FRAME_TIMING: u8 = 0x3E;
+/// The Frame_Timing_Reply ERCP Basic command code.
+pub const FRAME_TIMING_REPLY: u8 = 0x3F;
+
+/// The Auth_Challenge ERCP Basic command code.
+pub const AUTH_CHALLENGE: u8 = 0x40;
+/// The Auth_Challenge_Reply ERCP Basic command code.
+pub const AUTH_CHALLENGE_REPLY: u8 = 0x41;
+/// The Auth_Response ERCP Basic command code.
+pub const AUTH_RESPONSE: u8 = 0x42;
+/// The Auth_Logout ERCP Basic command code.
+pub const AUTH_LOGOUT: u8 = 0x43;
+/// The Auth_Set_Secret ERCP Basic command code.
+pub const AUTH_SET_SECRET: u8 = 0x44;
+
+/// The Cue ERCP Basic command code.
+pub const CUE: u8 = 0x50;
+/// The Show_Clear ERCP Basic command code.
+pub const SHOW_CLEAR: u8 = 0x51;
+/// The Show_Upload ERCP Basic command code.
+pub const SHOW_UPLOAD: u8 = 0x52;
+/// The Show_Start ERCP Basic command code.
+pub const SHOW_START: u8 = 0x53;
+/// The Show_Stop ERCP Basic command code.
+pub const SHOW_STOP: u8 = 0x54;
+/// The Show_Seek ERCP Basic command code.
+pub const SHOW_SEEK: u8 = 0x55;
+/// The Show_Status ERCP Basic command code.
+pub const SHOW_STATUS: u8 = 0x56;
+/// The Show_Status_Reply ERCP Basic command code.
+pub const SHOW_STATUS_REPLY: u8 = 0x57;
+
+/// The Preset_Save ERCP Basic command code.
+pub const PRESET_SAVE: u8 = 0x60;
+/// The Preset_Recall ERCP Basic command code.
+pub const PRESET_RECALL: u8 = 0x61;
+/// The Presets ERCP Basic command code.
+pub const PRESETS: u8 = 0x62;
+/// The Presets_Reply ERCP Basic command code.
+pub const PRESETS_REPLY: u8 = 0x63;
+
+/// The Identity ERCP Basic command code.
+pub const IDENTITY: u8 = 0x70;
+/// The Identity_Reply ERCP Basic command code.
+pub const IDENTITY_REPLY: u8 = 0x71;
+/// The Set_Name ERCP Basic command code.
+pub const SET_NAME: u8 = 0x72;
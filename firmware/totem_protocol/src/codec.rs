@@ -0,0 +1,133 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Encoding helpers shared by requests and responses.
+
+use core::marker::PhantomData;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{DecodeError, EncodeError};
+
+/// Encodes a value with postcard.
+pub fn encode<'b>(
+    value: &impl Serialize,
+    buffer: &'b mut [u8],
+) -> Result<&'b [u8], EncodeError> {
+    postcard::to_slice(value, buffer)
+        .map(|value| &*value)
+        .map_err(|_| EncodeError::BufferTooSmall)
+}
+
+/// Copies raw bytes in the buffer.
+pub fn copy<'b>(
+    bytes: &[u8],
+    buffer: &'b mut [u8],
+) -> Result<&'b [u8], EncodeError> {
+    let buffer = buffer
+        .get_mut(..bytes.len())
+        .ok_or(EncodeError::BufferTooSmall)?;
+
+    buffer.copy_from_slice(bytes);
+    Ok(buffer)
+}
+
+/// Decodes a value with postcard, rejecting trailing bytes.
+pub fn decode<'a, T: Deserialize<'a>>(
+    value: &'a [u8],
+) -> Result<T, DecodeError> {
+    match postcard::take_from_bytes(value) {
+        Ok((decoded, [])) => Ok(decoded),
+        Ok(_) => Err(DecodeError::TrailingBytes),
+        Err(error) => Err(decode_error(error)),
+    }
+}
+
+/// Decodes the first value of a sequence with postcard, returning the
+/// remaining bytes.
+pub fn take<'a, T: Deserialize<'a>>(
+    value: &'a [u8],
+) -> Result<(T, &'a [u8]), DecodeError> {
+    postcard::take_from_bytes(value).map_err(decode_error)
+}
+
+/// An iterator over values encoded with postcard and concatenated, like the
+/// items of a batch or the events of a show.
+///
+/// The iteration stops after the first value that cannot be decoded, as the
+/// next one cannot be found.
+pub struct Sequence<'a, T> {
+    /// The bytes of the values not decoded yet.
+    remaining: &'a [u8],
+    /// The type of the values.
+    values: PhantomData<T>,
+}
+
+impl<'a, T> Sequence<'a, T> {
+    /// Creates an iterator over the values encoded in `bytes`.
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            remaining: bytes,
+            values: PhantomData,
+        }
+    }
+
+    /// Returns the bytes of the values not decoded yet.
+    pub fn remaining(&self) -> &'a [u8] {
+        self.remaining
+    }
+}
+
+impl<'a, T: Deserialize<'a>> Iterator for Sequence<'a, T> {
+    type Item = Result<T, DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining.is_empty() {
+            return None;
+        }
+
+        match take(self.remaining) {
+            Ok((value, remaining)) => {
+                self.remaining = remaining;
+                Some(Ok(value))
+            }
+
+            Err(error) => {
+                self.remaining = &[];
+                Some(Err(error))
+            }
+        }
+    }
+}
+
+/// Checks that a value is empty.
+pub fn expect_empty(value: &[u8]) -> Result<(), DecodeError> {
+    if value.is_empty() {
+        Ok(())
+    } else {
+        Err(DecodeError::TrailingBytes)
+    }
+}
+
+/// Converts a postcard error to a decode error.
+fn decode_error(error: postcard::Error) -> DecodeError {
+    match error {
+        postcard::Error::DeserializeUnexpectedEnd => DecodeError::Truncated,
+        // Derived implementations report unknown variants as custom errors.
+        postcard::Error::DeserializeBadEnum
+        | postcard::Error::SerdeDeCustom => DecodeError::UnknownVariant,
+        _ => DecodeError::InvalidValue,
+    }
+}
@@ -0,0 +1,66 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Cues: one-shot effects layered over the running mode.
+//!
+//! A cue overrides or blends over the output of the current mode for its
+//! duration, then releases it. Firing a cue while another one is running
+//! replaces it.
+
+use defmt::Format;
+use serde::{Deserialize, Serialize};
+
+/// A one-shot effect.
+#[derive(Debug, Format, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cue {
+    /// The effect.
+    pub effect: CueEffect,
+    /// The duration of the effect, in milliseconds.
+    pub duration_ms: u16,
+}
+
+/// The effect of a cue.
+#[derive(Debug, Format, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CueEffect {
+    /// A white flash fading out over the running mode.
+    Flash,
+    /// A burst of colour fading out over the running mode.
+    Burst(Color),
+    /// All LEDs off.
+    Blackout,
+}
+
+/// An RGB colour.
+#[derive(
+    Debug, Format, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub struct Color {
+    /// The red component.
+    pub r: u8,
+    /// The green component.
+    pub g: u8,
+    /// The blue component.
+    pub b: u8,
+}
+
+impl Cue {
+    /// The maximum duration of a cue, in milliseconds.
+    pub const MAX_DURATION_MS: u16 = 10_000;
+
+    /// Returns whether the cue is valid.
+    pub fn is_valid(&self) -> bool {
+        (1..=Self::MAX_DURATION_MS).contains(&self.duration_ms)
+    }
+}
@@ -0,0 +1,40 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Errors of the Totem protocol.
+
+use defmt::Format;
+
+/// An error that can occur while encoding a message.
+#[derive(Debug, Format, Clone, Copy, PartialEq, Eq)]
+pub enum EncodeError {
+    /// The buffer is too small to hold the encoded value.
+    BufferTooSmall,
+}
+
+/// An error that can occur while decoding a message.
+#[derive(Debug, Format, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The command code is not part of the protocol.
+    UnknownCommand(u8),
+    /// The value is truncated.
+    Truncated,
+    /// The value contains an unknown enum variant.
+    UnknownVariant,
+    /// The value is followed by unexpected bytes.
+    TrailingBytes,
+    /// The value is invalid for the command.
+    InvalidValue,
+}
@@ -0,0 +1,61 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Supervision of the link with the host.
+//!
+//! When the UI is driven by the host, the Totem can require it to send
+//! commands regularly: when no valid command has been received for the
+//! configured timeout, the Totem considers the host lost and reverts to a
+//! failsafe UI state, instead of keeping the last one forever.
+
+use defmt::Format;
+use serde::{Deserialize, Serialize};
+
+use crate::state::UIState;
+
+/// The configuration of the host link watchdog.
+#[derive(
+    Debug, Format, Default, Clone, Copy, PartialEq, Serialize, Deserialize,
+)]
+pub struct HostWatchdogConfig {
+    /// The time without any valid command after which the host is considered
+    /// lost, in milliseconds, or 0 to disable the watchdog.
+    pub timeout_ms: u32,
+    /// The UI state to revert to when the host is lost.
+    pub failsafe: UIState,
+}
+
+/// The status of the link with the host.
+#[derive(
+    Debug, Format, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub struct HostLinkStatus {
+    /// The number of times the host link watchdog has expired.
+    pub losses: u32,
+    /// Whether the UI is in the failsafe state after the host has been lost.
+    pub failsafe_active: bool,
+}
+
+impl HostWatchdogConfig {
+    /// Returns whether the watchdog is enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.timeout_ms != 0
+    }
+
+    /// Returns whether the configuration is valid.
+    pub fn is_valid(&self) -> bool {
+        self.failsafe.is_valid()
+    }
+}
@@ -0,0 +1,127 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Identity of a Totem.
+//!
+//! Each Totem has a unique [`DeviceId`], derived from the unique ID of its
+//! microcontroller, and a [`Name`] set by the user and saved in flash. Both are
+//! reported by the Identity command, so that a host can tell Totems apart.
+
+use core::fmt::{self, Display};
+
+use defmt::Format;
+use serde::{Deserialize, Serialize};
+
+/// The maximum length of a name, in characters.
+pub const MAX_NAME_LEN: usize = 12;
+
+/// The unique ID of a Totem.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize,
+)]
+pub struct DeviceId(pub u32);
+
+/// The name of a Totem.
+///
+/// A name is made of up to [`MAX_NAME_LEN`] printable ASCII characters, so that
+/// it can be displayed on the LCD screen. An empty name means the Totem has not
+/// been named.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Name {
+    /// The length of the name.
+    len: u8,
+    /// The characters of the name, padded with zeros.
+    bytes: [u8; MAX_NAME_LEN],
+}
+
+/// The identity of a Totem.
+#[derive(Debug, Format, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Identity<'a> {
+    /// The unique ID.
+    pub id: DeviceId,
+    /// The name, empty if the Totem has not been named.
+    pub name: &'a str,
+}
+
+impl DeviceId {
+    /// Derives the ID from the 96-bit unique ID of the microcontroller.
+    ///
+    /// The unique ID is hashed with FNV-1a to 32 bits, which are short enough
+    /// to be displayed while keeping collisions unlikely.
+    pub fn from_uid(uid: &[u8; 12]) -> Self {
+        let hash = uid.iter().fold(0x811C_9DC5, |hash: u32, &byte| {
+            (hash ^ byte as u32).wrapping_mul(0x0100_0193)
+        });
+
+        Self(hash)
+    }
+}
+
+impl Display for DeviceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:08X}", self.0)
+    }
+}
+
+impl Format for DeviceId {
+    fn format(&self, fmt: defmt::Formatter) {
+        defmt::write!(fmt, "{=u32:08X}", self.0);
+    }
+}
+
+impl Name {
+    /// Creates a name, returning `None` if it is too long or contains
+    /// characters other than printable ASCII ones.
+    pub fn new(name: &str) -> Option<Self> {
+        let valid = name.len() <= MAX_NAME_LEN
+            && name
+                .bytes()
+                .all(|byte| byte == b' ' || byte.is_ascii_graphic());
+
+        valid.then(|| {
+            let mut bytes = [0; MAX_NAME_LEN];
+            bytes[..name.len()].copy_from_slice(name.as_bytes());
+
+            Self {
+                len: name.len() as u8,
+                bytes,
+            }
+        })
+    }
+
+    /// Returns the name as a string slice.
+    pub fn as_str(&self) -> &str {
+        // The bytes are checked to be ASCII on creation.
+        core::str::from_utf8(&self.bytes[..self.len as usize])
+            .unwrap_or_default()
+    }
+
+    /// Returns whether the name is empty, i.e. the Totem has not been named.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+impl Format for Name {
+    fn format(&self, fmt: defmt::Formatter) {
+        defmt::write!(fmt, "{=str}", self.as_str());
+    }
+}
@@ -0,0 +1,93 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Raw readings of the inputs of the Totem board.
+
+use defmt::Format;
+use serde::{Deserialize, Serialize};
+
+/// Raw readings of all the inputs of the board.
+///
+/// Analog inputs are named after the board pins: R1 to R4 are the
+/// potentiometers, S1 and S2 the sliders. B1 and B2 are the buttons.
+#[derive(
+    Debug, Format, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub struct InputReadings {
+    /// The reading of the R1 potentiometer.
+    pub r1: AnalogReading,
+    /// The reading of the R2 potentiometer.
+    pub r2: AnalogReading,
+    /// The reading of the R3 potentiometer.
+    pub r3: AnalogReading,
+    /// The reading of the R4 potentiometer.
+    pub r4: AnalogReading,
+    /// The reading of the S1 slider.
+    pub s1: AnalogReading,
+    /// The reading of the S2 slider.
+    pub s2: AnalogReading,
+    /// The reading of the microphone.
+    pub microphone: AnalogReading,
+    /// Whether the B1 button is high.
+    pub b1: bool,
+    /// Whether the B2 button is high.
+    pub b2: bool,
+}
+
+/// The reading of an analog input over a series of ADC samples.
+#[derive(
+    Debug, Format, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub struct AnalogReading {
+    /// The last sample.
+    pub raw: u16,
+    /// The mean of the samples.
+    pub mean: u16,
+    /// The minimum sample.
+    pub min: u16,
+    /// The maximum sample.
+    pub max: u16,
+}
+
+impl AnalogReading {
+    /// Computes the reading from a series of samples.
+    ///
+    /// Returns the default reading if there are no samples.
+    pub fn from_samples(samples: impl IntoIterator<Item = u16>) -> Self {
+        let mut count = 0;
+        let mut sum = 0;
+        let mut reading = Self {
+            raw: 0,
+            mean: 0,
+            min: u16::MAX,
+            max: u16::MIN,
+        };
+
+        for sample in samples {
+            count += 1;
+            sum += sample as u32;
+            reading.raw = sample;
+            reading.min = reading.min.min(sample);
+            reading.max = reading.max.max(sample);
+        }
+
+        if count == 0 {
+            return Self::default();
+        }
+
+        reading.mean = (sum / count) as u16;
+        reading
+    }
+}
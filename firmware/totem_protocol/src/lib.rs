@@ -0,0 +1,73 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! The protocol between the Totem and its host tools.
+//!
+//! The host sends [`Request`]s to the Totem over ERCP Basic, and the Totem
+//! replies with a [`Response`], or an ERCP Basic ACK or NACK for requests that
+//! do not return any data. Command codes are listed in [`code`], and payloads
+//! are serialised with postcard.
+//!
+//! # Compatibility rules
+//!
+//! The protocol has a single [`VERSION`], which the host can query with
+//! [`Request::ProtocolVersion`]. To keep older hosts and Totems working
+//! together:
+//!
+//! * a command code is never reused nor reassigned, even when its command is
+//!   removed;
+//! * the payload of an existing command never changes: changing a payload
+//!   requires a new command with its own code;
+//! * adding a command bumps [`VERSION`], and the variant documents the
+//!   version since which it is available;
+//! * a Totem replies to requests it does not know with an ERCP Basic
+//!   `UNKNOWN_COMMAND` NACK, so that a newer host can fall back on the older
+//!   commands.
+
+#![no_std]
+#![warn(clippy::redundant_pub_crate)]
+#![warn(clippy::use_self)]
+#![deny(missing_docs)]
+#![deny(unused_must_use)]
+#![forbid(unsafe_code)]
+
+pub mod auth;
+pub mod batch;
+pub mod code;
+pub mod cue;
+pub mod host_link;
+pub mod identity;
+pub mod inputs;
+pub mod log;
+pub mod nack_reason;
+pub mod preset;
+pub mod reset;
+pub mod self_test;
+pub mod show;
+pub mod state;
+pub mod telemetry;
+
+mod codec;
+mod error;
+mod request;
+mod response;
+
+pub use codec::Sequence;
+pub use error::{DecodeError, EncodeError};
+pub use request::Request;
+pub use response::Response;
+
+/// The version of the protocol.
+pub const VERSION: u8 = 12;
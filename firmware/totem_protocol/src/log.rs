@@ -0,0 +1,67 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Log records reported by the Totem.
+
+use core::fmt::{self, Display};
+
+use defmt::Format;
+use serde::{Deserialize, Serialize};
+
+/// A log record.
+#[derive(Debug, Format, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogRecord<'a> {
+    /// The time elapsed since boot when the record was logged, in
+    /// milliseconds.
+    pub timestamp_ms: u32,
+    /// The level of the record.
+    pub level: LogLevel,
+    /// The number of records dropped before this one due to a full buffer.
+    pub dropped: u32,
+    /// The log message.
+    pub message: &'a str,
+}
+
+/// The level of a log record.
+#[derive(
+    Debug,
+    Format,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+)]
+pub enum LogLevel {
+    /// An error.
+    Error,
+    /// A warning.
+    Warn,
+    /// An information.
+    Info,
+}
+
+impl Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Error => write!(f, "ERROR"),
+            Self::Warn => write!(f, "WARN"),
+            Self::Info => write!(f, "INFO"),
+        }
+    }
+}
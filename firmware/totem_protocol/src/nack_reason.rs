@@ -0,0 +1,72 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Totem-specific NACK reasons.
+//!
+//! ERCP Basic reserves the reasons below `0x10` for its own use. Totems
+//! implementing a protocol version older than 5 reply to every invalid request
+//! with the ERCP Basic `INVALID_ARGUMENTS` reason instead of the detailed ones.
+
+/// The command requires an authenticated session.
+pub const UNAUTHENTICATED: u8 = 0x10;
+
+/// The Totem cannot keep up with the rate of commands: the command should be
+/// sent again later.
+pub const BUSY: u8 = 0x11;
+
+/// The value of the command is truncated.
+pub const TRUNCATED: u8 = 0x12;
+
+/// The value of the command contains an unknown variant, for instance a mode
+/// the Totem does not know.
+pub const UNKNOWN_VARIANT: u8 = 0x13;
+
+/// The value of the command is followed by unexpected bytes.
+pub const TRAILING_BYTES: u8 = 0x14;
+
+/// A value of the command is out of its valid range.
+pub const OUT_OF_RANGE: u8 = 0x15;
+
+/// The command is known but not supported by this Totem, for instance a UI
+/// update on a Totem using its physical UI.
+pub const UNSUPPORTED: u8 = 0x16;
+
+/// There is not enough space left to store the value of the command.
+pub const NO_SPACE: u8 = 0x17;
+
+/// The uploaded show is invalid.
+pub const INVALID_SHOW: u8 = 0x18;
+
+/// The requested item does not exist, for instance an empty preset slot.
+pub const NOT_FOUND: u8 = 0x19;
+
+/// Returns a description of a Totem-specific NACK reason.
+///
+/// Returns `None` for unknown reasons, including the ERCP Basic ones.
+pub fn description(reason: u8) -> Option<&'static str> {
+    match reason {
+        UNAUTHENTICATED => Some("authentication required"),
+        BUSY => Some("the Totem is busy, try again later"),
+        TRUNCATED => Some("truncated value"),
+        UNKNOWN_VARIANT => Some("unknown variant in the value"),
+        TRAILING_BYTES => Some("unexpected bytes after the value"),
+        OUT_OF_RANGE => Some("value out of range"),
+        UNSUPPORTED => Some("command not supported by this Totem"),
+        NO_SPACE => Some("not enough space left on the Totem"),
+        INVALID_SHOW => Some("invalid show"),
+        NOT_FOUND => Some("not found"),
+        _ => None,
+    }
+}
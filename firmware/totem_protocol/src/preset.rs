@@ -13,16 +13,17 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-//! A fake timer for ERCP Basic.
+//! Scene presets: complete looks saved on the Totem.
+//!
+//! A preset holds a full UI state. The Totem keeps [`PRESET_COUNT`] of them in
+//! flash, which can be saved from the current state and recalled over ERCP or
+//! with gestures on B2. Recalling a preset updates the UI state like a UI
+//! update does.
 
-/// A fake timer for ERCP Basic.
-pub struct FakeTimer;
+use crate::state::UIState;
 
-impl ercp_basic::Timer for FakeTimer {
-    type Instant = u8;
-    type Duration = u8;
+/// The number of preset slots.
+pub const PRESET_COUNT: usize = 8;
 
-    fn now(&mut self) -> Self::Instant {
-        0
-    }
-}
+/// The presets saved on the Totem, indexed by slot.
+pub type Presets = [Option<UIState>; PRESET_COUNT];
@@ -0,0 +1,449 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Requests sent by the host to the Totem.
+
+use defmt::Format;
+
+use crate::{
+    code::*,
+    codec,
+    cue::Cue,
+    host_link::HostWatchdogConfig,
+    state::{
+        Brightness, Mode, ScreenState, Speed, Temperature, UIState,
+        UIStatePatch,
+    },
+    DecodeError, EncodeError,
+};
+
+/// A request sent by the host to the Totem.
+///
+/// Each request is carried by an ERCP Basic command. See the crate
+/// documentation for the compatibility rules.
+#[derive(Debug, Format, Clone, Copy, PartialEq)]
+pub enum Request<'a> {
+    /// Replaces the whole UI state (since version 1).
+    UIUpdate(UIState),
+    /// Updates the fields set in the patch (since version 1).
+    UIPatch(UIStatePatch),
+    /// Sets the mode (since version 1).
+    SetMode(Mode),
+    /// Sets the brightness (since version 1).
+    SetBrightness(Brightness),
+    /// Sets the speed (since version 1).
+    SetSpeed(Speed),
+    /// Sets the color temperature (since version 1).
+    SetTemperature(Temperature),
+    /// Sets the screen state (since version 1).
+    SetScreenState(ScreenState),
+    /// Configures the host link watchdog (since version 2).
+    SetHostWatchdog(HostWatchdogConfig),
+    /// Gets the status of the link with the host (since version 2).
+    HostLinkStatus,
+    /// Applies an encoded batch of UI state changes atomically (since version
+    /// 6).
+    Batch(&'a [u8]),
+    /// Fires a cue over the running mode (since version 3).
+    Cue(Cue),
+    /// Clears the uploaded show, stopping it (since version 4).
+    ShowClear,
+    /// Appends a chunk to the uploaded show (since version 4).
+    ShowUpload(&'a [u8]),
+    /// Starts the uploaded show from the beginning (since version 4).
+    ShowStart,
+    /// Stops the show (since version 4).
+    ShowStop,
+    /// Plays the uploaded show from the given position, in milliseconds
+    /// (since version 4).
+    ShowSeek(u32),
+    /// Gets the playback status of the show (since version 4).
+    ShowStatus,
+    /// Saves the current UI state in a preset slot (since version 7).
+    PresetSave(u8),
+    /// Recalls the UI state saved in a preset slot (since version 7).
+    PresetRecall(u8),
+    /// Gets the saved presets (since version 7).
+    Presets,
+    /// Gets the identity of the Totem (since version 8).
+    Identity,
+    /// Sets the name of the Totem, an empty name clearing it (since version
+    /// 8).
+    SetName(&'a str),
+    /// Gets the telemetry (since version 1).
+    Telemetry,
+    /// Gets the oldest buffered log record (since version 1).
+    LogDrain,
+    /// Gets the raw readings of the board inputs (since version 1).
+    Inputs,
+    /// Gets the protocol version implemented by the Totem (since version 1).
+    ProtocolVersion,
+    /// Gets the report of the power-on self-test (since version 9).
+    SelfTest,
+    /// Gets the report of the last reset (since version 10).
+    ResetReport,
+    /// Gets the counters of the errors the firmware has recovered from (since
+    /// version 11).
+    ErrorCounters,
+    /// Gets the timing of the LED frames (since version 12).
+    FrameTiming,
+    /// Requests an authentication challenge (since version 1).
+    AuthChallenge,
+    /// Responds to the last authentication challenge (since version 1).
+    AuthResponse(&'a [u8]),
+    /// Closes the authenticated session (since version 1).
+    AuthLogout,
+    /// Sets the shared secret (since version 1).
+    AuthSetSecret(&'a [u8]),
+}
+
+impl<'a> Request<'a> {
+    /// Returns the ERCP Basic command code of the request.
+    pub fn code(&self) -> u8 {
+        match self {
+            Self::UIUpdate(_) => UI_UPDATE,
+            Self::UIPatch(_) => UI_PATCH,
+            Self::SetMode(_) => SET_MODE,
+            Self::SetBrightness(_) => SET_BRIGHTNESS,
+            Self::SetSpeed(_) => SET_SPEED,
+            Self::SetTemperature(_) => SET_TEMPERATURE,
+            Self::SetScreenState(_) => SET_SCREEN_STATE,
+            Self::SetHostWatchdog(_) => SET_HOST_WATCHDOG,
+            Self::HostLinkStatus => HOST_LINK_STATUS,
+            Self::Batch(_) => BATCH,
+            Self::Cue(_) => CUE,
+            Self::ShowClear => SHOW_CLEAR,
+            Self::ShowUpload(_) => SHOW_UPLOAD,
+            Self::ShowStart => SHOW_START,
+            Self::ShowStop => SHOW_STOP,
+            Self::ShowSeek(_) => SHOW_SEEK,
+            Self::ShowStatus => SHOW_STATUS,
+            Self::PresetSave(_) => PRESET_SAVE,
+            Self::PresetRecall(_) => PRESET_RECALL,
+            Self::Presets => PRESETS,
+            Self::Identity => IDENTITY,
+            Self::SetName(_) => SET_NAME,
+            Self::Telemetry => TELEMETRY,
+            Self::LogDrain => LOG_DRAIN,
+            Self::Inputs => INPUTS,
+            Self::ProtocolVersion => PROTOCOL_VERSION,
+            Self::SelfTest => SELF_TEST,
+            Self::ResetReport => RESET_REPORT,
+            Self::ErrorCounters => ERROR_COUNTERS,
+            Self::FrameTiming => FRAME_TIMING,
+            Self::AuthChallenge => AUTH_CHALLENGE,
+            Self::AuthResponse(_) => AUTH_RESPONSE,
+            Self::AuthLogout => AUTH_LOGOUT,
+            Self::AuthSetSecret(_) => AUTH_SET_SECRET,
+        }
+    }
+
+    /// Encodes the value of the ERCP Basic command in `buffer`.
+    pub fn encode<'b>(
+        &self,
+        buffer: &'b mut [u8],
+    ) -> Result<&'b [u8], EncodeError> {
+        match self {
+            Self::UIUpdate(state) => codec::encode(state, buffer),
+            Self::UIPatch(patch) => codec::encode(patch, buffer),
+            Self::SetMode(mode) => codec::encode(mode, buffer),
+            Self::SetBrightness(brightness) => {
+                codec::encode(brightness, buffer)
+            }
+            Self::SetSpeed(speed) => codec::encode(speed, buffer),
+            Self::SetTemperature(temperature) => {
+                codec::encode(temperature, buffer)
+            }
+            Self::SetScreenState(screen_state) => {
+                codec::encode(screen_state, buffer)
+            }
+            Self::SetHostWatchdog(config) => codec::encode(config, buffer),
+            Self::Cue(cue) => codec::encode(cue, buffer),
+            Self::ShowSeek(position_ms) => codec::encode(position_ms, buffer),
+            Self::PresetSave(slot) => codec::encode(slot, buffer),
+            Self::PresetRecall(slot) => codec::encode(slot, buffer),
+            Self::SetName(name) => codec::encode(name, buffer),
+            Self::HostLinkStatus
+            | Self::Telemetry
+            | Self::LogDrain
+            | Self::Inputs
+            | Self::ProtocolVersion
+            | Self::SelfTest
+            | Self::ResetReport
+            | Self::ErrorCounters
+            | Self::FrameTiming
+            | Self::AuthChallenge
+            | Self::AuthLogout
+            | Self::ShowClear
+            | Self::ShowStart
+            | Self::ShowStop
+            | Self::ShowStatus
+            | Self::Presets
+            | Self::Identity => Ok(&[]),
+            Self::Batch(batch) => codec::copy(batch, buffer),
+            Self::ShowUpload(chunk) => codec::copy(chunk, buffer),
+            Self::AuthResponse(response) => codec::copy(response, buffer),
+            Self::AuthSetSecret(secret) => codec::copy(secret, buffer),
+        }
+    }
+
+    /// Decodes a request from the code and value of an ERCP Basic command.
+    pub fn decode(code: u8, value: &'a [u8]) -> Result<Self, DecodeError> {
+        match code {
+            UI_UPDATE => codec::decode(value).map(Self::UIUpdate),
+            UI_PATCH => codec::decode(value).map(Self::UIPatch),
+            SET_MODE => codec::decode(value).map(Self::SetMode),
+            SET_BRIGHTNESS => codec::decode(value).map(Self::SetBrightness),
+            SET_SPEED => codec::decode(value).map(Self::SetSpeed),
+            SET_TEMPERATURE => codec::decode(value).map(Self::SetTemperature),
+            SET_SCREEN_STATE => codec::decode(value).map(Self::SetScreenState),
+            SET_HOST_WATCHDOG => {
+                codec::decode(value).map(Self::SetHostWatchdog)
+            }
+            HOST_LINK_STATUS => {
+                codec::expect_empty(value).map(|_| Self::HostLinkStatus)
+            }
+            BATCH => Ok(Self::Batch(value)),
+            CUE => codec::decode(value).map(Self::Cue),
+            SHOW_CLEAR => codec::expect_empty(value).map(|_| Self::ShowClear),
+            SHOW_UPLOAD => Ok(Self::ShowUpload(value)),
+            SHOW_START => codec::expect_empty(value).map(|_| Self::ShowStart),
+            SHOW_STOP => codec::expect_empty(value).map(|_| Self::ShowStop),
+            SHOW_SEEK => codec::decode(value).map(Self::ShowSeek),
+            SHOW_STATUS => codec::expect_empty(value).map(|_| Self::ShowStatus),
+            PRESET_SAVE => codec::decode(value).map(Self::PresetSave),
+            PRESET_RECALL => codec::decode(value).map(Self::PresetRecall),
+            PRESETS => codec::expect_empty(value).map(|_| Self::Presets),
+            IDENTITY => codec::expect_empty(value).map(|_| Self::Identity),
+            SET_NAME => codec::decode(value).map(Self::SetName),
+            TELEMETRY => codec::expect_empty(value).map(|_| Self::Telemetry),
+            LOG_DRAIN => codec::expect_empty(value).map(|_| Self::LogDrain),
+            INPUTS => codec::expect_empty(value).map(|_| Self::Inputs),
+            PROTOCOL_VERSION => {
+                codec::expect_empty(value).map(|_| Self::ProtocolVersion)
+            }
+            SELF_TEST => codec::expect_empty(value).map(|_| Self::SelfTest),
+            RESET_REPORT => {
+                codec::expect_empty(value).map(|_| Self::ResetReport)
+            }
+            ERROR_COUNTERS => {
+                codec::expect_empty(value).map(|_| Self::ErrorCounters)
+            }
+            FRAME_TIMING => {
+                codec::expect_empty(value).map(|_| Self::FrameTiming)
+            }
+            AUTH_CHALLENGE => {
+                codec::expect_empty(value).map(|_| Self::AuthChallenge)
+            }
+            AUTH_RESPONSE => Ok(Self::AuthResponse(value)),
+            AUTH_LOGOUT => codec::expect_empty(value).map(|_| Self::AuthLogout),
+            AUTH_SET_SECRET => Ok(Self::AuthSetSecret(value)),
+            _ => Err(DecodeError::UnknownCommand(code)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_time::duration::Milliseconds;
+
+    use super::*;
+    use crate::cue::{Color, CueEffect};
+
+    /// A UI state with a value other than the default in each field.
+    fn state() -> UIState {
+        UIState {
+            mode: Mode::RandomUnicolor,
+            brightness: Brightness::new(128),
+            speed: Speed::new(Milliseconds(500)),
+            temperature: Temperature::new(-10),
+            screen_state: ScreenState::On,
+        }
+    }
+
+    /// Checks that a request is decoded back from its encoding.
+    fn assert_round_trip(request: Request<'_>) {
+        let mut buffer = [0; 64];
+        let value = request.encode(&mut buffer).unwrap();
+        assert_eq!(Request::decode(request.code(), value), Ok(request));
+    }
+
+    /// Checks that a request is encoded as `bytes`, and decoded from them.
+    ///
+    /// The bytes are the payload sent by the hosts implementing the version
+    /// which introduced the command, which must never change.
+    fn assert_fixture(request: Request<'_>, bytes: &[u8]) {
+        let mut buffer = [0; 64];
+        assert_eq!(request.encode(&mut buffer), Ok(bytes));
+        assert_eq!(Request::decode(request.code(), bytes), Ok(request));
+    }
+
+    #[test]
+    fn every_request_round_trips() {
+        let requests = [
+            Request::UIUpdate(UIState::default()),
+            Request::UIPatch(UIStatePatch::from(state())),
+            Request::SetMode(Mode::Off),
+            Request::SetBrightness(Brightness::new(0)),
+            Request::SetSpeed(Speed::default()),
+            Request::SetTemperature(Temperature::new(Temperature::MIN)),
+            Request::SetScreenState(ScreenState::Off),
+            Request::SetHostWatchdog(HostWatchdogConfig::default()),
+            Request::HostLinkStatus,
+            Request::Batch(&[]),
+            Request::Cue(Cue {
+                effect: CueEffect::Flash,
+                duration_ms: u16::MAX,
+            }),
+            Request::ShowClear,
+            Request::ShowUpload(&[0xFF; 32]),
+            Request::ShowStart,
+            Request::ShowStop,
+            Request::ShowSeek(u32::MAX),
+            Request::ShowStatus,
+            Request::PresetSave(0),
+            Request::PresetRecall(u8::MAX),
+            Request::Presets,
+            Request::Identity,
+            Request::SetName(""),
+            Request::Telemetry,
+            Request::LogDrain,
+            Request::Inputs,
+            Request::ProtocolVersion,
+            Request::SelfTest,
+            Request::ResetReport,
+            Request::ErrorCounters,
+            Request::FrameTiming,
+            Request::AuthChallenge,
+            Request::AuthResponse(&[0x42; 32]),
+            Request::AuthLogout,
+            Request::AuthSetSecret(&[]),
+        ];
+
+        for request in requests {
+            assert_round_trip(request);
+        }
+    }
+
+    #[test]
+    fn state_requests_keep_their_payload() {
+        let state_bytes = [0x01, 0x80, 0xF4, 0x03, 0xF6, 0x01];
+
+        assert_fixture(Request::UIUpdate(state()), &state_bytes);
+        assert_fixture(
+            Request::UIPatch(UIStatePatch {
+                mode: Some(Mode::RainbowFontain),
+                temperature: Some(Temperature::new(-10)),
+                ..UIStatePatch::default()
+            }),
+            &[0x01, 0x02, 0x00, 0x00, 0x01, 0xF6, 0x00],
+        );
+        assert_fixture(Request::SetMode(Mode::RainbowFontain), &[0x02]);
+        assert_fixture(Request::SetBrightness(Brightness::new(255)), &[0xFF]);
+        assert_fixture(
+            Request::SetSpeed(Speed::new(Milliseconds(13_000))),
+            &[0xC8, 0x65],
+        );
+        assert_fixture(Request::SetTemperature(Temperature::new(85)), &[0x55]);
+        assert_fixture(Request::SetScreenState(ScreenState::On), &[0x01]);
+        assert_fixture(Request::Batch(&[0x00, 0x02]), &[0x00, 0x02]);
+    }
+
+    #[test]
+    fn host_link_requests_keep_their_payload() {
+        assert_fixture(
+            Request::SetHostWatchdog(HostWatchdogConfig {
+                timeout_ms: 5000,
+                failsafe: state(),
+            }),
+            &[0x88, 0x27, 0x01, 0x80, 0xF4, 0x03, 0xF6, 0x01],
+        );
+        assert_fixture(Request::HostLinkStatus, &[]);
+    }
+
+    #[test]
+    fn cue_and_show_requests_keep_their_payload() {
+        assert_fixture(
+            Request::Cue(Cue {
+                effect: CueEffect::Burst(Color {
+                    r: 255,
+                    g: 0,
+                    b: 128,
+                }),
+                duration_ms: 300,
+            }),
+            &[0x01, 0xFF, 0x00, 0x80, 0xAC, 0x02],
+        );
+        assert_fixture(Request::ShowClear, &[]);
+        assert_fixture(Request::ShowUpload(&[1, 2, 3]), &[1, 2, 3]);
+        assert_fixture(Request::ShowStart, &[]);
+        assert_fixture(Request::ShowStop, &[]);
+        assert_fixture(Request::ShowSeek(90_000), &[0x90, 0xBF, 0x05]);
+        assert_fixture(Request::ShowStatus, &[]);
+    }
+
+    #[test]
+    fn preset_and_identity_requests_keep_their_payload() {
+        assert_fixture(Request::PresetSave(3), &[0x03]);
+        assert_fixture(Request::PresetRecall(7), &[0x07]);
+        assert_fixture(Request::Presets, &[]);
+        assert_fixture(Request::Identity, &[]);
+        assert_fixture(Request::SetName("Totem"), b"\x05Totem");
+    }
+
+    #[test]
+    fn diagnostic_requests_keep_their_payload() {
+        assert_fixture(Request::Telemetry, &[]);
+        assert_fixture(Request::LogDrain, &[]);
+        assert_fixture(Request::Inputs, &[]);
+        assert_fixture(Request::ProtocolVersion, &[]);
+        assert_fixture(Request::SelfTest, &[]);
+        assert_fixture(Request::ResetReport, &[]);
+        assert_fixture(Request::ErrorCounters, &[]);
+        assert_fixture(Request::FrameTiming, &[]);
+    }
+
+    #[test]
+    fn auth_requests_keep_their_payload() {
+        assert_fixture(Request::AuthChallenge, &[]);
+        assert_fixture(Request::AuthResponse(&[0xAB; 32]), &[0xAB; 32]);
+        assert_fixture(Request::AuthLogout, &[]);
+        assert_fixture(Request::AuthSetSecret(b"secret"), b"secret");
+    }
+
+    #[test]
+    fn invalid_values_are_rejected() {
+        assert_eq!(
+            Request::decode(0xFF, &[]),
+            Err(DecodeError::UnknownCommand(0xFF))
+        );
+        assert_eq!(
+            Request::decode(TELEMETRY, &[0x00]),
+            Err(DecodeError::TrailingBytes)
+        );
+        assert_eq!(
+            Request::decode(SET_MODE, &[0x03]),
+            Err(DecodeError::UnknownVariant)
+        );
+        assert_eq!(
+            Request::decode(UI_UPDATE, &[0x01]),
+            Err(DecodeError::Truncated)
+        );
+        assert_eq!(
+            Request::decode(SET_MODE, &[0x01, 0x00]),
+            Err(DecodeError::TrailingBytes)
+        );
+    }
+}
@@ -0,0 +1,98 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Reset reporting.
+//!
+//! At boot, the Totem records the cause of its last reset and counts the resets
+//! since power-on. When the reset has been caused by a panic, its message and
+//! location are kept across the reset. All of this is reported by the
+//! Reset_Report command.
+
+use core::fmt::{self, Display};
+
+use defmt::Format;
+use serde::{Deserialize, Serialize};
+
+/// The report of the last reset.
+#[derive(Debug, Format, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResetReport<'a> {
+    /// The cause of the last reset.
+    pub cause: ResetCause,
+    /// The number of resets since power-on.
+    pub resets: u32,
+    /// The panic which has caused the last reset, if any.
+    #[serde(borrow)]
+    pub panic: Option<PanicReport<'a>>,
+}
+
+/// A panic which has caused a reset.
+#[derive(Debug, Format, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PanicReport<'a> {
+    /// The panic message, possibly truncated.
+    pub message: &'a str,
+    /// The source file where the panic has occurred, possibly truncated.
+    pub file: &'a str,
+    /// The line where the panic has occurred.
+    pub line: u32,
+    /// The column where the panic has occurred.
+    pub column: u32,
+}
+
+/// The cause of a reset.
+#[derive(Debug, Format, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ResetCause {
+    /// The Totem has been powered on.
+    PowerOn,
+    /// The reset pin has been pulled low.
+    Pin,
+    /// The supply voltage has dropped below the brown-out threshold.
+    BrownOut,
+    /// The firmware has requested a reset.
+    Software,
+    /// The firmware has panicked.
+    Panic,
+    /// The independent watchdog has expired.
+    IndependentWatchdog,
+    /// The window watchdog has expired.
+    WindowWatchdog,
+    /// The microcontroller has entered an illegal low-power mode.
+    LowPower,
+    /// The cause is not known.
+    Unknown,
+}
+
+impl ResetCause {
+    /// Returns whether the reset has been expected, i.e. caused by a power-on
+    /// or the reset pin.
+    pub fn is_expected(&self) -> bool {
+        matches!(self, Self::PowerOn | Self::Pin)
+    }
+}
+
+impl Display for ResetCause {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PowerOn => write!(f, "power-on"),
+            Self::Pin => write!(f, "reset pin"),
+            Self::BrownOut => write!(f, "brown-out"),
+            Self::Software => write!(f, "software"),
+            Self::Panic => write!(f, "panic"),
+            Self::IndependentWatchdog => write!(f, "watchdog"),
+            Self::WindowWatchdog => write!(f, "window watchdog"),
+            Self::LowPower => write!(f, "low-power"),
+            Self::Unknown => write!(f, "unknown"),
+        }
+    }
+}
@@ -0,0 +1,403 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Responses sent by the Totem to the host.
+
+use defmt::Format;
+
+use crate::{
+    auth::NONCE_LEN,
+    code::*,
+    codec,
+    host_link::HostLinkStatus,
+    identity::Identity,
+    inputs::InputReadings,
+    log::LogRecord,
+    preset::Presets,
+    reset::ResetReport,
+    self_test::SelfTestReport,
+    show::ShowStatus,
+    telemetry::{ErrorCounters, FrameTiming, Telemetry},
+    DecodeError, EncodeError,
+};
+
+/// A response sent by the Totem to the host.
+///
+/// Each response is carried by an ERCP Basic command, whose code follows the
+/// one of the request. Requests without a response are replied to with an
+/// ERCP Basic ACK or NACK. See the crate documentation for the compatibility
+/// rules.
+#[derive(Debug, Format, Clone, Copy, PartialEq, Eq)]
+pub enum Response<'a> {
+    /// The telemetry (since version 1).
+    Telemetry(Telemetry),
+    /// The oldest buffered log record, if any (since version 1).
+    LogRecord(Option<LogRecord<'a>>),
+    /// The raw readings of the board inputs, when using the physical UI (since
+    /// version 1).
+    Inputs(Option<InputReadings>),
+    /// The protocol version implemented by the Totem (since version 1).
+    ProtocolVersion(u8),
+    /// The status of the link with the host (since version 2).
+    HostLinkStatus(HostLinkStatus),
+    /// The report of the power-on self-test, if it has run (since version 9).
+    SelfTest(Option<SelfTestReport>),
+    /// The report of the last reset (since version 10).
+    ResetReport(ResetReport<'a>),
+    /// The counters of the errors the firmware has recovered from (since
+    /// version 11).
+    ErrorCounters(ErrorCounters),
+    /// The timing of the LED frames (since version 12).
+    FrameTiming(FrameTiming),
+    /// An authentication challenge (since version 1).
+    AuthChallenge([u8; NONCE_LEN]),
+    /// The playback status of the show (since version 4).
+    ShowStatus(ShowStatus),
+    /// The status of each item of a batch (since version 6).
+    Batch(&'a [u8]),
+    /// The saved presets (since version 7).
+    Presets(Presets),
+    /// The identity of the Totem (since version 8).
+    Identity(Identity<'a>),
+}
+
+impl<'a> Response<'a> {
+    /// Returns the ERCP Basic command code of the response.
+    pub fn code(&self) -> u8 {
+        match self {
+            Self::Telemetry(_) => TELEMETRY_REPLY,
+            Self::LogRecord(_) => LOG_DRAIN_REPLY,
+            Self::Inputs(_) => INPUTS_REPLY,
+            Self::ProtocolVersion(_) => PROTOCOL_VERSION_REPLY,
+            Self::HostLinkStatus(_) => HOST_LINK_STATUS_REPLY,
+            Self::SelfTest(_) => SELF_TEST_REPLY,
+            Self::ResetReport(_) => RESET_REPORT_REPLY,
+            Self::ErrorCounters(_) => ERROR_COUNTERS_REPLY,
+            Self::FrameTiming(_) => FRAME_TIMING_REPLY,
+            Self::AuthChallenge(_) => AUTH_CHALLENGE_REPLY,
+            Self::ShowStatus(_) => SHOW_STATUS_REPLY,
+            Self::Batch(_) => BATCH_REPLY,
+            Self::Presets(_) => PRESETS_REPLY,
+            Self::Identity(_) => IDENTITY_REPLY,
+        }
+    }
+
+    /// Encodes the value of the ERCP Basic command in `buffer`.
+    pub fn encode<'b>(
+        &self,
+        buffer: &'b mut [u8],
+    ) -> Result<&'b [u8], EncodeError> {
+        match self {
+            Self::Telemetry(telemetry) => codec::encode(telemetry, buffer),
+            Self::LogRecord(record) => codec::encode(record, buffer),
+            Self::Inputs(inputs) => codec::encode(inputs, buffer),
+            Self::ProtocolVersion(version) => codec::encode(version, buffer),
+            Self::HostLinkStatus(status) => codec::encode(status, buffer),
+            Self::SelfTest(report) => codec::encode(report, buffer),
+            Self::ResetReport(report) => codec::encode(report, buffer),
+            Self::ErrorCounters(counters) => codec::encode(counters, buffer),
+            Self::FrameTiming(timing) => codec::encode(timing, buffer),
+            Self::AuthChallenge(nonce) => codec::copy(nonce, buffer),
+            Self::ShowStatus(status) => codec::encode(status, buffer),
+            Self::Batch(statuses) => codec::copy(statuses, buffer),
+            Self::Presets(presets) => codec::encode(presets, buffer),
+            Self::Identity(identity) => codec::encode(identity, buffer),
+        }
+    }
+
+    /// Decodes a response from the code and value of an ERCP Basic command.
+    pub fn decode(code: u8, value: &'a [u8]) -> Result<Self, DecodeError> {
+        match code {
+            TELEMETRY_REPLY => codec::decode(value).map(Self::Telemetry),
+            LOG_DRAIN_REPLY => codec::decode(value).map(Self::LogRecord),
+            INPUTS_REPLY => codec::decode(value).map(Self::Inputs),
+            PROTOCOL_VERSION_REPLY => {
+                codec::decode(value).map(Self::ProtocolVersion)
+            }
+            HOST_LINK_STATUS_REPLY => {
+                codec::decode(value).map(Self::HostLinkStatus)
+            }
+            SELF_TEST_REPLY => codec::decode(value).map(Self::SelfTest),
+            RESET_REPORT_REPLY => codec::decode(value).map(Self::ResetReport),
+            ERROR_COUNTERS_REPLY => {
+                codec::decode(value).map(Self::ErrorCounters)
+            }
+            FRAME_TIMING_REPLY => codec::decode(value).map(Self::FrameTiming),
+            AUTH_CHALLENGE_REPLY => value
+                .try_into()
+                .map(Self::AuthChallenge)
+                .map_err(|_| DecodeError::InvalidValue),
+            SHOW_STATUS_REPLY => codec::decode(value).map(Self::ShowStatus),
+            BATCH_REPLY => Ok(Self::Batch(value)),
+            PRESETS_REPLY => codec::decode(value).map(Self::Presets),
+            IDENTITY_REPLY => codec::decode(value).map(Self::Identity),
+            _ => Err(DecodeError::UnknownCommand(code)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_time::duration::Milliseconds;
+
+    use super::*;
+    use crate::{
+        identity::DeviceId,
+        inputs::AnalogReading,
+        log::LogLevel,
+        reset::{PanicReport, ResetCause},
+        self_test::{PotentiometerCheck, PotentiometerStatus},
+        state::{Brightness, Mode, ScreenState, Speed, Temperature, UIState},
+        telemetry::AdcReadings,
+    };
+
+    /// Checks that a response is decoded back from its encoding.
+    fn assert_round_trip(response: Response<'_>) {
+        let mut buffer = [0; 128];
+        let value = response.encode(&mut buffer).unwrap();
+        assert_eq!(Response::decode(response.code(), value), Ok(response));
+    }
+
+    /// Checks that a response is encoded as `bytes`, and decoded from them.
+    ///
+    /// The bytes are the payload sent by the Totems implementing the version
+    /// which introduced the command, which must never change.
+    fn assert_fixture(response: Response<'_>, bytes: &[u8]) {
+        let mut buffer = [0; 128];
+        assert_eq!(response.encode(&mut buffer), Ok(bytes));
+        assert_eq!(Response::decode(response.code(), bytes), Ok(response));
+    }
+
+    /// Returns a self-test report with a different status for each
+    /// potentiometer.
+    fn self_test_report() -> SelfTestReport {
+        let check = |status| PotentiometerCheck {
+            reading: AnalogReading::default(),
+            status,
+        };
+
+        SelfTestReport {
+            screen_present: true,
+            strips_lit: 2,
+            potentiometers: [
+                check(PotentiometerStatus::Ok),
+                check(PotentiometerStatus::Low),
+                check(PotentiometerStatus::High),
+                check(PotentiometerStatus::Noisy),
+                check(PotentiometerStatus::Ok),
+                check(PotentiometerStatus::Ok),
+            ],
+        }
+    }
+
+    #[test]
+    fn every_response_round_trips() {
+        let mut presets = Presets::default();
+        presets[7] = Some(UIState::default());
+
+        let responses = [
+            Response::Telemetry(Telemetry::default()),
+            Response::LogRecord(None),
+            Response::Inputs(Some(InputReadings {
+                microphone: AnalogReading {
+                    raw: u16::MAX,
+                    mean: 2048,
+                    min: 0,
+                    max: u16::MAX,
+                },
+                b2: true,
+                ..InputReadings::default()
+            })),
+            Response::ProtocolVersion(crate::VERSION),
+            Response::SelfTest(None),
+            Response::ResetReport(ResetReport {
+                cause: ResetCause::Unknown,
+                resets: u32::MAX,
+                panic: None,
+            }),
+            Response::ErrorCounters(ErrorCounters::default()),
+            Response::FrameTiming(FrameTiming::default()),
+            Response::HostLinkStatus(HostLinkStatus::default()),
+            Response::AuthChallenge([0; NONCE_LEN]),
+            Response::ShowStatus(ShowStatus::default()),
+            Response::Batch(&[]),
+            Response::Presets(presets),
+            Response::Identity(Identity {
+                id: DeviceId(u32::MAX),
+                name: "",
+            }),
+        ];
+
+        for response in responses {
+            assert_round_trip(response);
+        }
+    }
+
+    #[test]
+    fn diagnostic_responses_keep_their_payload() {
+        assert_fixture(
+            Response::Telemetry(Telemetry {
+                uptime_ms: 1000,
+                target_frame_rate: 60,
+                frame_rate: 59,
+                max_led_task_time_us: 1200,
+                spawn_failures: 0,
+                ercp_rx_errors: 1,
+                ercp_process_errors: 2,
+                screen_present: true,
+                adc_readings: Some(AdcReadings {
+                    mode: 1,
+                    brightness: 2,
+                    speed: 3,
+                    temperature: 4,
+                }),
+            }),
+            &[
+                0xE8, 0x07, 0x3C, 0x3B, 0xB0, 0x09, 0x00, 0x01, 0x02, 0x01,
+                0x01, 0x01, 0x02, 0x03, 0x04,
+            ],
+        );
+        assert_fixture(
+            Response::LogRecord(Some(LogRecord {
+                timestamp_ms: 1000,
+                level: LogLevel::Warn,
+                dropped: 0,
+                message: "hi",
+            })),
+            &[0x01, 0xE8, 0x07, 0x01, 0x00, 0x02, b'h', b'i'],
+        );
+        assert_fixture(Response::Inputs(None), &[0x00]);
+        assert_fixture(Response::ProtocolVersion(1), &[0x01]);
+        assert_fixture(
+            Response::SelfTest(Some(self_test_report())),
+            &[
+                0x01, 0x01, 0x02, //
+                0x00, 0x00, 0x00, 0x00, 0x00, //
+                0x00, 0x00, 0x00, 0x00, 0x01, //
+                0x00, 0x00, 0x00, 0x00, 0x02, //
+                0x00, 0x00, 0x00, 0x00, 0x03, //
+                0x00, 0x00, 0x00, 0x00, 0x00, //
+                0x00, 0x00, 0x00, 0x00, 0x00, //
+            ],
+        );
+        assert_fixture(
+            Response::ResetReport(ResetReport {
+                cause: ResetCause::Panic,
+                resets: 3,
+                panic: Some(PanicReport {
+                    message: "oops",
+                    file: "main.rs",
+                    line: 42,
+                    column: 7,
+                }),
+            }),
+            b"\x04\x03\x01\x04oops\x07main.rs\x2A\x07",
+        );
+        assert_fixture(
+            Response::ErrorCounters(ErrorCounters {
+                screen_failed: true,
+                led_write_errors: 2,
+                led_frames_dropped: 1,
+                spawn_failures: 300,
+            }),
+            &[0x01, 0x02, 0x01, 0xAC, 0x02],
+        );
+        assert_fixture(
+            Response::FrameTiming(FrameTiming {
+                period_us: 10_000,
+                mean_jitter_us: 5,
+                max_jitter_us: 20,
+                peak_jitter_us: 300,
+                skipped_frames: 1,
+            }),
+            &[0x90, 0x4E, 0x05, 0x14, 0xAC, 0x02, 0x01],
+        );
+    }
+
+    #[test]
+    fn host_link_responses_keep_their_payload() {
+        assert_fixture(
+            Response::HostLinkStatus(HostLinkStatus {
+                losses: 2,
+                failsafe_active: true,
+            }),
+            &[0x02, 0x01],
+        );
+    }
+
+    #[test]
+    fn auth_responses_keep_their_payload() {
+        assert_fixture(
+            Response::AuthChallenge([0x5A; NONCE_LEN]),
+            &[0x5A; NONCE_LEN],
+        );
+    }
+
+    #[test]
+    fn show_and_batch_responses_keep_their_payload() {
+        assert_fixture(
+            Response::ShowStatus(ShowStatus {
+                playing: true,
+                position_ms: 90_000,
+                size: 300,
+            }),
+            &[0x01, 0x90, 0xBF, 0x05, 0xAC, 0x02],
+        );
+        assert_fixture(Response::Batch(&[0x00, 0x05]), &[0x00, 0x05]);
+    }
+
+    #[test]
+    fn preset_and_identity_responses_keep_their_payload() {
+        let mut presets = Presets::default();
+        presets[1] = Some(UIState {
+            mode: Mode::RandomUnicolor,
+            brightness: Brightness::new(128),
+            speed: Speed::new(Milliseconds(500)),
+            temperature: Temperature::new(-10),
+            screen_state: ScreenState::On,
+        });
+
+        assert_fixture(
+            Response::Presets(presets),
+            &[
+                0x00, 0x01, 0x01, 0x80, 0xF4, 0x03, 0xF6, 0x01, 0x00, 0x00,
+                0x00, 0x00, 0x00, 0x00,
+            ],
+        );
+        assert_fixture(
+            Response::Identity(Identity {
+                id: DeviceId(0x1234_5678),
+                name: "Totem",
+            }),
+            b"\xF8\xAC\xD1\x91\x01\x05Totem",
+        );
+    }
+
+    #[test]
+    fn invalid_values_are_rejected() {
+        assert_eq!(
+            Response::decode(TELEMETRY, &[]),
+            Err(DecodeError::UnknownCommand(TELEMETRY))
+        );
+        assert_eq!(
+            Response::decode(AUTH_CHALLENGE_REPLY, &[0; NONCE_LEN - 1]),
+            Err(DecodeError::InvalidValue)
+        );
+        assert_eq!(
+            Response::decode(HOST_LINK_STATUS_REPLY, &[0x00, 0x01, 0x00]),
+            Err(DecodeError::TrailingBytes)
+        );
+    }
+}
@@ -0,0 +1,89 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Power-on self-test of the Totem.
+//!
+//! When built with its self-test, the Totem checks its hardware at boot: each
+//! LED strip is lit in turn for a visual check, and every potentiometer must
+//! read steadily inside its calibrated range. The resulting
+//! [`SelfTestReport`] can be read with [`Request::SelfTest`].
+//!
+//! [`Request::SelfTest`]: crate::Request::SelfTest
+
+use defmt::Format;
+use serde::{Deserialize, Serialize};
+
+use crate::inputs::AnalogReading;
+
+/// The number of potentiometers checked by the self-test.
+pub const POTENTIOMETER_COUNT: usize = 6;
+
+/// The names of the potentiometers, in the order of the report.
+pub const POTENTIOMETER_NAMES: [&str; POTENTIOMETER_COUNT] =
+    ["R1", "R2", "R3", "R4", "S1", "S2"];
+
+/// The report of the self-test.
+#[derive(Debug, Format, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SelfTestReport {
+    /// Whether the LCD screen is present.
+    pub screen_present: bool,
+    /// The number of LED strips lit in turn whose frame has been written.
+    pub strips_lit: u8,
+    /// The check of each potentiometer, in the order of
+    /// [`POTENTIOMETER_NAMES`].
+    pub potentiometers: [PotentiometerCheck; POTENTIOMETER_COUNT],
+}
+
+/// The check of a potentiometer.
+#[derive(Debug, Format, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PotentiometerCheck {
+    /// The reading of the potentiometer.
+    pub reading: AnalogReading,
+    /// The status of the potentiometer.
+    pub status: PotentiometerStatus,
+}
+
+/// The status of a potentiometer.
+#[derive(Debug, Format, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PotentiometerStatus {
+    /// The reading is steady and inside the calibrated range.
+    Ok,
+    /// The reading is below the calibrated range.
+    Low,
+    /// The reading is above the calibrated range.
+    High,
+    /// The reading fluctuates, for instance because the wiper is disconnected.
+    Noisy,
+}
+
+impl SelfTestReport {
+    /// Returns whether the self-test has passed.
+    ///
+    /// The screen is optional, hence not required to pass.
+    pub fn passed(&self) -> bool {
+        self.potentiometers
+            .iter()
+            .all(|check| check.status == PotentiometerStatus::Ok)
+    }
+
+    /// Returns the names of the potentiometers which have failed their check.
+    pub fn failures(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.potentiometers
+            .iter()
+            .zip(POTENTIOMETER_NAMES)
+            .filter(|(check, _)| check.status != PotentiometerStatus::Ok)
+            .map(|(_, name)| name)
+    }
+}
@@ -0,0 +1,106 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Shows: timelines of state changes and cues played by the Totem.
+//!
+//! A show is a sequence of [`ShowEvent`]s, each encoded with postcard and
+//! concatenated in chronological order. The host uploads the encoded show in
+//! chunks, then starts it: the Totem plays it on its own from then on, without
+//! the host.
+//!
+//! Event times are relative to the start of the show. To run a show at a given
+//! time of the day, start it at that time.
+
+use defmt::Format;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    codec::{self, Sequence},
+    cue::Cue,
+    state::UIStatePatch,
+    EncodeError,
+};
+
+/// The maximum size of an encoded show, in bytes.
+pub const SHOW_MAX_SIZE: usize = 4096;
+
+/// An event of a show.
+#[derive(Debug, Format, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ShowEvent {
+    /// The time of the event, relative to the start of the show, in
+    /// milliseconds.
+    pub at_ms: u32,
+    /// The action to perform.
+    pub action: ShowAction,
+}
+
+/// An action of a show.
+#[derive(Debug, Format, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ShowAction {
+    /// Updates the UI state.
+    Patch(UIStatePatch),
+    /// Fires a cue.
+    Cue(Cue),
+}
+
+/// The playback status of a show.
+#[derive(
+    Debug, Format, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub struct ShowStatus {
+    /// Whether the show is playing.
+    pub playing: bool,
+    /// The position in the show, in milliseconds.
+    pub position_ms: u32,
+    /// The size of the uploaded show, in bytes.
+    pub size: u16,
+}
+
+impl ShowEvent {
+    /// Encodes the event in `buffer`, to be appended to a show.
+    pub fn encode<'b>(
+        &self,
+        buffer: &'b mut [u8],
+    ) -> Result<&'b [u8], EncodeError> {
+        codec::encode(self, buffer)
+    }
+
+    /// Returns whether the event is valid.
+    pub fn is_valid(&self) -> bool {
+        match self.action {
+            ShowAction::Patch(patch) => patch.is_valid(),
+            ShowAction::Cue(cue) => cue.is_valid(),
+        }
+    }
+}
+
+/// Returns an iterator over the events of an encoded show.
+pub fn events(show: &[u8]) -> Sequence<'_, ShowEvent> {
+    Sequence::new(show)
+}
+
+/// Returns whether an encoded show is valid, i.e. all its events can be
+/// decoded, are valid and are in chronological order.
+pub fn is_valid(show: &[u8]) -> bool {
+    let mut last_ms = 0;
+
+    events(show).all(|event| match event {
+        Ok(event) if event.is_valid() && event.at_ms >= last_ms => {
+            last_ms = event.at_ms;
+            true
+        }
+        _ => false,
+    })
+}
@@ -0,0 +1,288 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Types modeling the state of the Totem UI.
+
+use defmt::{write, Format};
+use embedded_time::duration::{Generic, Milliseconds};
+use serde::{Deserialize, Serialize};
+
+/// The state of the user interface.
+#[derive(
+    Debug, Format, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub struct UIState {
+    /// The mode.
+    pub mode: Mode,
+    /// The brightness of the LED strip.
+    pub brightness: Brightness,
+    /// The speed of transitions.
+    pub speed: Speed,
+    /// The color temperature.
+    pub temperature: Temperature,
+    /// The state of the LCD screen.
+    pub screen_state: ScreenState,
+}
+
+/// A partial update of the state of the user interface.
+///
+/// Only the fields set to `Some` are updated when applied to a [`UIState`].
+#[derive(
+    Debug, Format, Default, Clone, Copy, PartialEq, Serialize, Deserialize,
+)]
+pub struct UIStatePatch {
+    /// The new mode.
+    pub mode: Option<Mode>,
+    /// The new brightness of the LED strip.
+    pub brightness: Option<Brightness>,
+    /// The new speed of transitions.
+    pub speed: Option<Speed>,
+    /// The new color temperature.
+    pub temperature: Option<Temperature>,
+    /// The new state of the LCD screen.
+    pub screen_state: Option<ScreenState>,
+}
+
+/// The mode.
+#[derive(Debug, Format, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Mode {
+    /// The off mode.
+    ///
+    /// The Totem is off, only checking UI updates.
+    Off,
+
+    /// The random unicolor mode.
+    ///
+    /// All LEDs are following the same random color progression.
+    RandomUnicolor,
+
+    /// The rainbow fontain mode.
+    ///
+    /// LED strips show a symmetrical rainbow divergence.
+    RainbowFontain,
+}
+
+/// The brightness of the LED strip.
+#[derive(
+    Debug,
+    Format,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+)]
+pub struct Brightness(pub(crate) u8);
+
+/// The speed of transitions.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize,
+)]
+pub struct Speed(pub(crate) Milliseconds);
+
+/// The color temperature.
+#[derive(
+    Debug,
+    Format,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+)]
+pub struct Temperature(pub(crate) i8);
+
+/// The screen state.
+#[derive(
+    Debug,
+    Format,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+)]
+pub enum ScreenState {
+    /// The screen is off.
+    Off,
+    /// The screen is on.
+    On,
+}
+
+impl UIState {
+    /// Applies a partial update to the state.
+    pub fn apply(&mut self, patch: &UIStatePatch) {
+        if let Some(mode) = patch.mode {
+            self.mode = mode;
+        }
+
+        if let Some(brightness) = patch.brightness {
+            self.brightness = brightness;
+        }
+
+        if let Some(speed) = patch.speed {
+            self.speed = speed;
+        }
+
+        if let Some(temperature) = patch.temperature {
+            self.temperature = temperature;
+        }
+
+        if let Some(screen_state) = patch.screen_state {
+            self.screen_state = screen_state;
+        }
+    }
+
+    /// Returns whether all values of the state are in their valid range.
+    pub fn is_valid(&self) -> bool {
+        self.speed.is_valid() && self.temperature.is_valid()
+    }
+}
+
+impl UIStatePatch {
+    /// Merges `other` into this patch.
+    ///
+    /// Fields set in `other` take precedence over the ones in `self`.
+    pub fn merge(&mut self, other: &Self) {
+        self.mode = other.mode.or(self.mode);
+        self.brightness = other.brightness.or(self.brightness);
+        self.speed = other.speed.or(self.speed);
+        self.temperature = other.temperature.or(self.temperature);
+        self.screen_state = other.screen_state.or(self.screen_state);
+    }
+
+    /// Returns whether all values set in the patch are in their valid range.
+    pub fn is_valid(&self) -> bool {
+        self.speed.is_none_or(|speed| speed.is_valid())
+            && self
+                .temperature
+                .is_none_or(|temperature| temperature.is_valid())
+    }
+
+    /// Returns whether the patch does not update anything.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl From<UIState> for UIStatePatch {
+    fn from(state: UIState) -> Self {
+        Self {
+            mode: Some(state.mode),
+            brightness: Some(state.brightness),
+            speed: Some(state.speed),
+            temperature: Some(state.temperature),
+            screen_state: Some(state.screen_state),
+        }
+    }
+}
+
+impl Default for Mode {
+    fn default() -> Self {
+        Self::Off
+    }
+}
+
+impl Default for Speed {
+    fn default() -> Self {
+        Self(Milliseconds(Self::MIN))
+    }
+}
+
+impl Default for ScreenState {
+    fn default() -> Self {
+        Self::Off
+    }
+}
+
+impl Format for Speed {
+    fn format(&self, fmt: defmt::Formatter) {
+        write!(fmt, "Speed(duration = {} ms)", self.0 .0);
+    }
+}
+
+impl Brightness {
+    /// The minimum brightness value.
+    pub const MIN: u8 = 0;
+    /// The maximum brightness value.
+    pub const MAX: u8 = u8::MAX;
+
+    /// Creates a new brightness.
+    pub fn new(value: u8) -> Self {
+        Self(value)
+    }
+
+    /// Returns the brightness value.
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+impl Speed {
+    /// The minimum transition time.
+    pub const MIN: u32 = 100;
+    /// The maximum transition time.
+    pub const MAX: u32 = 13_000;
+
+    /// Creates a new speed.
+    pub fn new(transition_time: Milliseconds) -> Self {
+        Self(transition_time)
+    }
+
+    /// Returns the duration of a transition.
+    pub fn transition_time(&self) -> Generic<u32> {
+        self.0.into()
+    }
+
+    /// Returns whether the transition time is in the valid range.
+    pub fn is_valid(&self) -> bool {
+        (Self::MIN..=Self::MAX).contains(&self.0 .0)
+    }
+}
+
+impl Temperature {
+    /// The minimum temperature value.
+    pub const MIN: i8 = -85;
+    /// The maximum temperature value.
+    pub const MAX: i8 = 85;
+
+    /// Creates a new temperature.
+    ///
+    /// Negative value gives warmer colors, positive values colder.
+    pub fn new(value: i8) -> Self {
+        Self(value)
+    }
+
+    /// Returns the temperature value.
+    pub fn value(&self) -> i8 {
+        self.0
+    }
+
+    /// Returns whether the temperature is in the valid range.
+    pub fn is_valid(&self) -> bool {
+        (Self::MIN..=Self::MAX).contains(&self.0)
+    }
+}
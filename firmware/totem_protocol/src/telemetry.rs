@@ -0,0 +1,98 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Telemetry reported by the Totem.
+
+use defmt::Format;
+use serde::{Deserialize, Serialize};
+
+/// Telemetry about the health of the Totem firmware.
+#[derive(
+    Debug, Format, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub struct Telemetry {
+    /// The time elapsed since boot, in milliseconds.
+    pub uptime_ms: u32,
+    /// The target LED refresh rate, in Hz.
+    pub target_frame_rate: u32,
+    /// The LED refresh rate measured over the last second, in Hz.
+    pub frame_rate: u32,
+    /// The maximum execution time of the LED task, in microseconds.
+    pub max_led_task_time_us: u32,
+    /// The number of task spawns that have failed.
+    pub spawn_failures: u32,
+    /// The number of errors while receiving ERCP Basic frames.
+    pub ercp_rx_errors: u32,
+    /// The number of errors while processing ERCP Basic frames.
+    pub ercp_process_errors: u32,
+    /// Whether the LCD screen is present.
+    pub screen_present: bool,
+    /// The last raw ADC readings, when using the physical UI.
+    pub adc_readings: Option<AdcReadings>,
+}
+
+/// Counters of the errors the firmware has recovered from.
+#[derive(
+    Debug, Format, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub struct ErrorCounters {
+    /// Whether the LCD screen has been disabled after an error.
+    pub screen_failed: bool,
+    /// The number of failed attempts to write a frame to the LED strip.
+    pub led_write_errors: u32,
+    /// The number of frames dropped after all attempts to write them have
+    /// failed.
+    pub led_frames_dropped: u32,
+    /// The number of task spawns that have failed.
+    pub spawn_failures: u32,
+}
+
+/// Timing of the LED frames.
+///
+/// Frames are scheduled on absolute deadlines. The jitter is the delay between
+/// the deadline of a frame and the actual start of its rendering.
+#[derive(
+    Debug, Format, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub struct FrameTiming {
+    /// The target period between two frames, in microseconds.
+    pub period_us: u32,
+    /// The mean jitter over the last second, in microseconds.
+    pub mean_jitter_us: u32,
+    /// The maximum jitter over the last second, in microseconds.
+    pub max_jitter_us: u32,
+    /// The maximum jitter since boot, in microseconds.
+    pub peak_jitter_us: u32,
+    /// The number of frames skipped since boot because a frame has started
+    /// too late.
+    pub skipped_frames: u32,
+}
+
+/// Raw ADC readings of the UI potentiometers.
+///
+/// These are the averaged values, before being scaled to the UI state.
+#[derive(
+    Debug, Format, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub struct AdcReadings {
+    /// The reading of the mode potentiometer.
+    pub mode: u16,
+    /// The reading of the brightness potentiometer.
+    pub brightness: u16,
+    /// The reading of the speed potentiometer.
+    pub speed: u16,
+    /// The reading of the temperature potentiometer.
+    pub temperature: u16,
+}
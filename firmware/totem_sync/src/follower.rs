@@ -0,0 +1,156 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! The follower side of the synchronisation.
+
+use defmt::Format;
+use totem_protocol::state::UIState;
+
+use crate::{SyncMessage, LEADER_TIMEOUT_MS};
+
+/// The maximum number of frames to skip at once to catch up with the leader.
+const MAX_SKIP: u32 = 5;
+
+/// The maximum clock drift to compensate, in parts per million.
+const MAX_DRIFT_PPM: i64 = 50_000;
+
+/// A follower in a group of Totems.
+pub struct Follower {
+    /// The period of a frame, in milliseconds.
+    frame_period_ms: u32,
+    /// The last synchronisation point with the leader.
+    last_sync: Option<SyncPoint>,
+    /// The estimated drift of the leader clock relative to the local one, in
+    /// parts per million.
+    drift_ppm: i64,
+}
+
+/// A correction to apply to the local effect to stay in phase.
+#[derive(Debug, Format, Clone, Copy, PartialEq, Eq)]
+pub enum Correction {
+    /// The local effect is in phase.
+    None,
+    /// The local effect is late: skip the given number of frames.
+    Skip(u32),
+    /// The local effect is early: hold the current frame.
+    Hold,
+}
+
+/// A synchronisation point with the leader.
+#[derive(Clone, Copy)]
+struct SyncPoint {
+    /// The local time when the message has been received.
+    local_ms: u32,
+    /// The clock of the leader.
+    leader_ms: u32,
+    /// The phase of the leader.
+    phase: u32,
+    /// The UI state of the leader.
+    state: UIState,
+}
+
+impl Follower {
+    /// Creates a new follower, rendering one frame every `frame_period_ms`.
+    pub fn new(frame_period_ms: u32) -> Self {
+        Self {
+            frame_period_ms,
+            last_sync: None,
+            drift_ppm: 0,
+        }
+    }
+
+    /// Handles a message from the leader, received at `now_ms`.
+    pub fn receive(&mut self, message: &SyncMessage, now_ms: u32) {
+        if let Some(last) = self.last_sync.filter(|_| self.is_following(now_ms))
+        {
+            let local_elapsed = now_ms.wrapping_sub(last.local_ms) as i64;
+            let leader_elapsed =
+                message.clock_ms.wrapping_sub(last.leader_ms) as i64;
+
+            if local_elapsed > 0 {
+                let measured_ppm = (leader_elapsed - local_elapsed) * 1_000_000
+                    / local_elapsed;
+
+                // Smooth the estimation to absorb the transmission jitter.
+                self.drift_ppm = ((3 * self.drift_ppm + measured_ppm) / 4)
+                    .clamp(-MAX_DRIFT_PPM, MAX_DRIFT_PPM);
+            }
+        } else {
+            self.drift_ppm = 0;
+        }
+
+        self.last_sync = Some(SyncPoint {
+            local_ms: now_ms,
+            leader_ms: message.clock_ms,
+            phase: message.phase,
+            state: message.state,
+        });
+    }
+
+    /// Returns whether the leader has been heard recently.
+    pub fn is_following(&self, now_ms: u32) -> bool {
+        self.last_sync.is_some_and(|last| {
+            now_ms.wrapping_sub(last.local_ms) < LEADER_TIMEOUT_MS
+        })
+    }
+
+    /// Returns the UI state of the leader, if it is still present.
+    pub fn state(&self, now_ms: u32) -> Option<UIState> {
+        self.last_sync
+            .filter(|_| self.is_following(now_ms))
+            .map(|last| last.state)
+    }
+
+    /// Returns the estimated drift of the leader clock, in parts per million.
+    pub fn drift_ppm(&self) -> i64 {
+        self.drift_ppm
+    }
+
+    /// Estimates the phase of the leader at `now_ms`.
+    pub fn leader_phase(&self, now_ms: u32) -> Option<u32> {
+        self.last_sync
+            .filter(|_| self.is_following(now_ms))
+            .map(|last| {
+                let local_elapsed = now_ms.wrapping_sub(last.local_ms) as i64;
+                let leader_elapsed =
+                    local_elapsed + local_elapsed * self.drift_ppm / 1_000_000;
+                let frames = leader_elapsed / self.frame_period_ms as i64;
+                last.phase.wrapping_add(frames as u32)
+            })
+    }
+
+    /// Returns the correction to apply to an effect at `local_phase`, about to
+    /// render a frame at `now_ms`.
+    ///
+    /// A difference of one frame is tolerated, to avoid oscillating around
+    /// the phase of the leader because of the jitter.
+    pub fn correction(&self, local_phase: u32, now_ms: u32) -> Correction {
+        match self.leader_phase(now_ms) {
+            Some(leader_phase) => {
+                let diff = leader_phase.wrapping_sub(local_phase) as i32;
+
+                if diff > 1 {
+                    Correction::Skip((diff as u32).min(MAX_SKIP))
+                } else if diff < -1 {
+                    Correction::Hold
+                } else {
+                    Correction::None
+                }
+            }
+
+            None => Correction::None,
+        }
+    }
+}
@@ -0,0 +1,58 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! The leader side of the synchronisation.
+
+use totem_protocol::state::UIState;
+
+use crate::{SyncMessage, SYNC_PERIOD_MS};
+
+/// The leader of a group of Totems.
+#[derive(Default)]
+pub struct Leader {
+    /// The time of the last broadcast, in milliseconds.
+    last_broadcast_ms: Option<u32>,
+}
+
+impl Leader {
+    /// Creates a new leader.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the message to broadcast at `now_ms`, if a broadcast is due.
+    pub fn poll(
+        &mut self,
+        now_ms: u32,
+        state: UIState,
+        phase: u32,
+    ) -> Option<SyncMessage> {
+        let due = self
+            .last_broadcast_ms
+            .is_none_or(|last| now_ms.wrapping_sub(last) >= SYNC_PERIOD_MS);
+
+        if due {
+            self.last_broadcast_ms = Some(now_ms);
+
+            Some(SyncMessage {
+                clock_ms: now_ms,
+                state,
+                phase,
+            })
+        } else {
+            None
+        }
+    }
+}
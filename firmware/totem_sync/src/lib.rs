@@ -0,0 +1,136 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Synchronisation of several Totems.
+//!
+//! Totems built for the same place can be linked over a serial line so that
+//! they show the same effects at the same time. One of them is the leader: it
+//! periodically broadcasts a [`SyncMessage`] with its clock, its UI state and
+//! the phase of its running effect, that is the number of frames rendered
+//! since the effect started. The other ones are followers: they apply the
+//! state of the leader and skip or hold frames to keep their phase aligned
+//! with it.
+//!
+//! When the leader disappears, followers keep running on their own, using
+//! their local UI state.
+//!
+//! This crate contains only the protocol logic, independently of the hardware,
+//! so that it can be run against simulated links and clocks.
+
+#![no_std]
+#![warn(clippy::redundant_pub_crate)]
+#![warn(clippy::use_self)]
+#![deny(missing_docs)]
+#![deny(unused_must_use)]
+#![forbid(unsafe_code)]
+
+mod follower;
+mod leader;
+mod message;
+
+pub use follower::{Correction, Follower};
+pub use leader::Leader;
+pub use message::{EncodeError, FrameReader, SyncMessage, MAX_FRAME_SIZE};
+
+/// The period between two synchronisation messages, in milliseconds.
+pub const SYNC_PERIOD_MS: u32 = 100;
+
+/// The time after which a silent leader is considered lost, in milliseconds.
+pub const LEADER_TIMEOUT_MS: u32 = 1_000;
+
+#[cfg(test)]
+mod tests {
+    use totem_protocol::state::UIState;
+
+    use super::*;
+
+    /// The period of a frame, in milliseconds.
+    const FRAME_PERIOD_MS: u32 = 20;
+
+    /// Broadcasts the state of the leader to the follower through a frame.
+    fn broadcast(
+        leader: &mut Leader,
+        follower: &mut Follower,
+        now_ms: u32,
+        phase: u32,
+    ) {
+        let message = leader.poll(now_ms, UIState::default(), phase).unwrap();
+
+        let mut buffer = [0; MAX_FRAME_SIZE];
+        let frame = message.encode(&mut buffer).unwrap();
+
+        let mut reader = FrameReader::new();
+        let received = frame.iter().find_map(|&byte| reader.push(byte));
+
+        follower.receive(&received.unwrap(), now_ms);
+    }
+
+    #[test]
+    fn the_leader_broadcasts_once_per_period() {
+        let mut leader = Leader::new();
+        let state = UIState::default();
+
+        assert!(leader.poll(0, state, 0).is_some());
+        assert!(leader.poll(SYNC_PERIOD_MS - 1, state, 0).is_none());
+        assert!(leader.poll(SYNC_PERIOD_MS, state, 0).is_some());
+    }
+
+    #[test]
+    fn a_follower_in_phase_needs_no_correction() {
+        let mut leader = Leader::new();
+        let mut follower = Follower::new(FRAME_PERIOD_MS);
+        broadcast(&mut leader, &mut follower, 0, 100);
+
+        assert!(follower.is_following(0));
+        assert_eq!(follower.state(0), Some(UIState::default()));
+        assert_eq!(follower.correction(100, 0), Correction::None);
+        assert_eq!(follower.correction(101, 0), Correction::None);
+        assert_eq!(follower.correction(110, 200), Correction::None);
+    }
+
+    #[test]
+    fn a_late_follower_skips_frames() {
+        let mut leader = Leader::new();
+        let mut follower = Follower::new(FRAME_PERIOD_MS);
+        broadcast(&mut leader, &mut follower, 0, 100);
+
+        assert_eq!(follower.correction(97, 0), Correction::Skip(3));
+        assert_eq!(follower.correction(107, 200), Correction::Skip(3));
+
+        // Large gaps are caught up over several frames.
+        assert_eq!(follower.correction(50, 0), Correction::Skip(5));
+    }
+
+    #[test]
+    fn an_early_follower_holds_frames() {
+        let mut leader = Leader::new();
+        let mut follower = Follower::new(FRAME_PERIOD_MS);
+        broadcast(&mut leader, &mut follower, 0, 100);
+
+        assert_eq!(follower.correction(102, 0), Correction::Hold);
+        assert_eq!(follower.correction(113, 200), Correction::Hold);
+    }
+
+    #[test]
+    fn a_follower_runs_on_its_own_when_the_leader_is_lost() {
+        let mut leader = Leader::new();
+        let mut follower = Follower::new(FRAME_PERIOD_MS);
+        broadcast(&mut leader, &mut follower, 0, 100);
+
+        assert!(!follower.is_following(LEADER_TIMEOUT_MS));
+        assert_eq!(follower.state(LEADER_TIMEOUT_MS), None);
+        assert_eq!(follower.correction(0, LEADER_TIMEOUT_MS), Correction::None);
+    }
+}
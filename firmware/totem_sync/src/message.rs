@@ -0,0 +1,148 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Synchronisation messages.
+
+use defmt::Format;
+use heapless::Vec;
+use serde::{Deserialize, Serialize};
+use totem_protocol::state::UIState;
+
+/// The maximum size of an encoded frame, including its delimiter.
+pub const MAX_FRAME_SIZE: usize = 48;
+
+/// A synchronisation message, broadcast by the leader.
+#[derive(Debug, Format, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SyncMessage {
+    /// The clock of the leader when sending the message, in milliseconds.
+    pub clock_ms: u32,
+    /// The UI state of the leader.
+    pub state: UIState,
+    /// The phase of the running effect, in frames since its start.
+    pub phase: u32,
+}
+
+/// An error that can occur while encoding a message.
+#[derive(Debug, Format)]
+pub struct EncodeError;
+
+impl SyncMessage {
+    /// Encodes the message in a COBS frame, delimited by a zero byte.
+    pub fn encode<'a>(
+        &self,
+        buffer: &'a mut [u8; MAX_FRAME_SIZE],
+    ) -> Result<&'a mut [u8], EncodeError> {
+        postcard::to_slice_cobs(self, buffer).map_err(|_| EncodeError)
+    }
+}
+
+/// A reader extracting messages from a stream of bytes.
+///
+/// Invalid frames are silently dropped, and the reader resynchronises on the
+/// next delimiter.
+#[derive(Default)]
+pub struct FrameReader {
+    /// The bytes of the current frame.
+    buffer: Vec<u8, MAX_FRAME_SIZE>,
+    /// Whether the current frame has overflowed the buffer.
+    overflow: bool,
+}
+
+impl FrameReader {
+    /// Creates a new frame reader.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Pushes a received byte, returning a message if it completes one.
+    pub fn push(&mut self, byte: u8) -> Option<SyncMessage> {
+        if byte != 0 {
+            if self.buffer.push(byte).is_err() {
+                self.overflow = true;
+            }
+
+            return None;
+        }
+
+        let message = if self.overflow {
+            None
+        } else {
+            postcard::from_bytes_cobs(&mut self.buffer).ok()
+        };
+
+        self.buffer.clear();
+        self.overflow = false;
+        message
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message() -> SyncMessage {
+        SyncMessage {
+            clock_ms: 1_234,
+            state: UIState::default(),
+            phase: 42,
+        }
+    }
+
+    fn read(reader: &mut FrameReader, bytes: &[u8]) -> Option<SyncMessage> {
+        bytes
+            .iter()
+            .fold(None, |message, &byte| message.or(reader.push(byte)))
+    }
+
+    #[test]
+    fn a_message_is_read_back() {
+        let mut buffer = [0; MAX_FRAME_SIZE];
+        let frame = message().encode(&mut buffer).unwrap();
+
+        assert_eq!(read(&mut FrameReader::new(), frame), Some(message()));
+    }
+
+    #[test]
+    fn the_reader_resynchronises_after_garbage() {
+        let mut buffer = [0; MAX_FRAME_SIZE];
+        let frame = message().encode(&mut buffer).unwrap();
+        let mut reader = FrameReader::new();
+
+        assert_eq!(read(&mut reader, &[0xAA, 0x55, 0x12, 0]), None);
+        assert_eq!(read(&mut reader, frame), Some(message()));
+    }
+
+    #[test]
+    fn the_reader_resynchronises_after_a_truncated_frame() {
+        let mut buffer = [0; MAX_FRAME_SIZE];
+        let frame = message().encode(&mut buffer).unwrap();
+        let mut reader = FrameReader::new();
+
+        assert_eq!(read(&mut reader, &frame[..3]), None);
+        assert_eq!(read(&mut reader, &[0]), None);
+        assert_eq!(read(&mut reader, frame), Some(message()));
+    }
+
+    #[test]
+    fn the_reader_resynchronises_after_an_overflow() {
+        let mut buffer = [0; MAX_FRAME_SIZE];
+        let frame = message().encode(&mut buffer).unwrap();
+        let mut reader = FrameReader::new();
+
+        assert_eq!(read(&mut reader, &[0xAA; 2 * MAX_FRAME_SIZE]), None);
+        assert_eq!(read(&mut reader, &[0]), None);
+        assert_eq!(read(&mut reader, frame), Some(message()));
+    }
+}
@@ -17,8 +17,6 @@
 //!
 //! This interface uses ERCP Basic to command Totem from a GUI.
 
-use ercp_basic::{ack, command::nack_reason, nack, Command};
-
 use crate::{state::*, UI};
 
 /// The graphical user interface for Totem.
@@ -27,9 +25,6 @@ pub struct GraphicalUI {
     state: UIState,
 }
 
-/// The UI_Update ERCP Basic command code.
-pub const UI_UPDATE: u8 = 0x20;
-
 impl GraphicalUI {
     /// Creates a new graphical UI.
     pub fn new() -> Self {
@@ -40,6 +35,11 @@ impl GraphicalUI {
     pub fn set_state(&mut self, state: UIState) {
         self.state = state;
     }
+
+    /// Merges a partial update into the state.
+    pub fn update_state(&mut self, patch: &UIStatePatch) {
+        self.state.apply(patch);
+    }
 }
 
 impl UI for GraphicalUI {
@@ -63,20 +63,3 @@ impl UI for GraphicalUI {
         self.state.screen_state
     }
 }
-
-/// Handles UI_Update commands.
-pub fn ui_update<'a>(
-    command: Command,
-    ui_state_update: &mut Option<UIState>,
-) -> Option<Command<'a>> {
-    if command.code() != UI_UPDATE {
-        return Some(nack!(nack_reason::INVALID_ARGUMENTS));
-    }
-
-    if let Ok(ui_state) = postcard::from_bytes(command.value()) {
-        *ui_state_update = Some(ui_state);
-        Some(ack!())
-    } else {
-        Some(nack!(nack_reason::INVALID_ARGUMENTS))
-    }
-}
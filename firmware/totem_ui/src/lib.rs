@@ -26,7 +26,8 @@
 pub mod graphical;
 #[cfg(feature = "physical")]
 pub mod physical;
-pub mod state;
+
+pub use totem_protocol::state;
 
 #[cfg(feature = "graphical")]
 pub use graphical::GraphicalUI;
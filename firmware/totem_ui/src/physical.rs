@@ -20,10 +20,15 @@ use core::{fmt::Debug, ops::Range};
 use embedded_time::duration::Milliseconds;
 use totem_board::{
     adc::{Channel, ADC},
-    peripheral::CalibratedPotentiometer,
+    peripheral::{CalibratedPotentiometer, Microphone, B2, R4, S2},
     prelude::*,
 };
 
+use totem_protocol::{
+    inputs::{AnalogReading, InputReadings},
+    telemetry::AdcReadings,
+};
+
 use crate::{state::*, UI};
 
 /// The physical user interface for Totem.
@@ -34,10 +39,33 @@ pub struct PhysicalUI<PMode, PBrightness, PSpeed, PTemperature, BScreen> {
     p_speed: PSpeed,
     p_temperature: PTemperature,
     b_screen: BScreen,
+    spare: SpareInputs,
+    inputs: InputReadings,
+    overrides: UIStatePatch,
+    anchors: AdcReadings,
+    screen_anchor: bool,
+}
+
+/// The inputs of the board not used by the physical UI.
+///
+/// They are only sampled for calibration and debugging purposes.
+pub struct SpareInputs {
+    /// The R4 potentiometer.
+    pub r4: R4,
+    /// The S2 slider.
+    pub s2: S2,
+    /// The B2 button.
+    pub b2: B2,
+    /// The microphone.
+    pub microphone: Microphone,
 }
 
 const ITERATIONS: u32 = 200;
 
+/// The change of ADC reading above which a potentiometer is considered moved
+/// since the last state update.
+const PICKUP_THRESHOLD: u16 = 64;
+
 impl<
         PMode: CalibratedPotentiometer,
         PBrightness: CalibratedPotentiometer,
@@ -54,6 +82,7 @@ impl<
         p_speed: PSpeed,
         p_temperature: PTemperature,
         b_screen: BScreen,
+        spare: SpareInputs,
     ) -> Self {
         Self {
             p_adc,
@@ -62,8 +91,56 @@ impl<
             p_speed,
             p_temperature,
             b_screen,
+            spare,
+            inputs: InputReadings::default(),
+            overrides: UIStatePatch::default(),
+            anchors: AdcReadings::default(),
+            screen_anchor: false,
+        }
+    }
+
+    /// Overrides the fields set in the patch.
+    ///
+    /// Each overridden field holds until its control is moved, so that a
+    /// recalled preset is not immediately replaced by the positions of the
+    /// potentiometers.
+    pub fn update_state(&mut self, patch: &UIStatePatch) {
+        self.overrides.merge(patch);
+        self.anchors = self.adc_readings();
+        self.screen_anchor = self.b_screen.is_high().unwrap();
+    }
+
+    /// Returns the last averaged ADC readings of the UI potentiometers.
+    pub fn adc_readings(&self) -> AdcReadings {
+        AdcReadings {
+            mode: self.inputs.r1.mean,
+            brightness: self.inputs.r2.mean,
+            speed: self.inputs.r3.mean,
+            temperature: self.inputs.s1.mean,
         }
     }
+
+    /// Reads all the inputs of the board.
+    ///
+    /// The spare inputs and buttons are sampled now, while the readings of the
+    /// UI potentiometers are the ones from the last read of the UI state.
+    pub fn read_inputs(&mut self) -> InputReadings {
+        self.inputs.r4 =
+            sample(&mut self.p_adc, &mut self.spare.r4, ITERATIONS);
+        self.inputs.s2 =
+            sample(&mut self.p_adc, &mut self.spare.s2, ITERATIONS);
+        self.inputs.microphone =
+            sample(&mut self.p_adc, &mut self.spare.microphone, ITERATIONS);
+        self.inputs.b1 = self.b_screen.is_high().unwrap();
+        self.inputs.b2 = self.spare.b2.is_high().unwrap();
+
+        self.inputs
+    }
+
+    /// Returns whether the B2 button is pressed.
+    pub fn is_b2_pressed(&mut self) -> bool {
+        self.spare.b2.is_high().unwrap()
+    }
 }
 
 impl<
@@ -75,67 +152,87 @@ impl<
     > UI for PhysicalUI<PMode, PBrightness, PSpeed, PTemperature, BScreen>
 {
     fn read_mode(&mut self) -> Mode {
-        let value = read_mean(&mut self.p_adc, &mut self.p_mode, ITERATIONS);
+        self.inputs.r1 = sample(&mut self.p_adc, &mut self.p_mode, ITERATIONS);
+        let value = self.inputs.r1.mean;
 
-        if value < (PMode::MAX - PMode::MIN) / 3 {
+        let mode = if value < (PMode::MAX - PMode::MIN) / 3 {
             Mode::Off
         } else if value < (PMode::MAX - PMode::MIN) / 3 * 2 {
             Mode::RandomUnicolor
         } else {
             Mode::RainbowFontain
-        }
+        };
+
+        let moved = has_moved(value, self.anchors.mode);
+        pick_up(&mut self.overrides.mode, moved, mode)
     }
 
     fn read_brightness(&mut self) -> Brightness {
-        let value =
-            read_mean(&mut self.p_adc, &mut self.p_brightness, ITERATIONS);
+        self.inputs.r2 =
+            sample(&mut self.p_adc, &mut self.p_brightness, ITERATIONS);
+        let value = self.inputs.r2.mean;
 
-        Brightness(adc_to_range(
+        let brightness = Brightness::new(adc_to_range(
             value,
             PBrightness::MIN..PBrightness::MAX,
             (Brightness::MIN.into())..(Brightness::MAX.into()),
-        ) as u8)
+        ) as u8);
+
+        let moved = has_moved(value, self.anchors.brightness);
+        pick_up(&mut self.overrides.brightness, moved, brightness)
     }
 
     fn read_speed(&mut self) -> Speed {
-        let value = read_mean(&mut self.p_adc, &mut self.p_speed, ITERATIONS);
+        self.inputs.r3 = sample(&mut self.p_adc, &mut self.p_speed, ITERATIONS);
+        let value = self.inputs.r3.mean;
         let transition_ms = adc_to_inverted_range(
             value,
             PSpeed::MIN..PSpeed::MAX,
             (Speed::MIN as i32)..(Speed::MAX as i32),
         );
 
-        Speed(Milliseconds(transition_ms as u32))
+        let speed = Speed::new(Milliseconds(transition_ms as u32));
+
+        let moved = has_moved(value, self.anchors.speed);
+        pick_up(&mut self.overrides.speed, moved, speed)
     }
 
     fn read_temperature(&mut self) -> Temperature {
-        let value =
-            read_mean(&mut self.p_adc, &mut self.p_temperature, ITERATIONS);
+        self.inputs.s1 =
+            sample(&mut self.p_adc, &mut self.p_temperature, ITERATIONS);
+        let value = self.inputs.s1.mean;
 
-        Temperature(adc_to_range(
+        let temperature = Temperature::new(adc_to_range(
             value,
             PTemperature::MIN..PTemperature::MAX,
             (Temperature::MIN.into())..(Temperature::MAX.into()),
-        ) as i8)
+        ) as i8);
+
+        let moved = has_moved(value, self.anchors.temperature);
+        pick_up(&mut self.overrides.temperature, moved, temperature)
     }
 
     fn read_screen_state(&mut self) -> ScreenState {
-        if self.b_screen.is_high().unwrap() {
+        let level = self.b_screen.is_high().unwrap();
+        let screen_state = if level {
             ScreenState::On
         } else {
             ScreenState::Off
-        }
+        };
+
+        let moved = level != self.screen_anchor;
+        pick_up(&mut self.overrides.screen_state, moved, screen_state)
     }
 }
 
-fn read_mean(
+fn sample(
     adc: &mut ADC,
     channel: &mut impl Channel,
     iterations: u32,
-) -> u16 {
-    ((0..iterations)
-        .fold(0, |sum: u32, _| sum + adc.read(channel).unwrap() as u32)
-        / iterations as u32) as u16
+) -> AnalogReading {
+    AnalogReading::from_samples(
+        (0..iterations).map(|_| adc.read(channel).unwrap()),
+    )
 }
 
 fn adc_to_range(
@@ -174,3 +271,15 @@ fn adc_to_inverted_range(
     let output_dynamic = output_range.len() as i32;
     output_range.end - adc_to_range(value, adc_range, 0..output_dynamic)
 }
+
+fn has_moved(value: u16, anchor: u16) -> bool {
+    value.abs_diff(anchor) > PICKUP_THRESHOLD
+}
+
+fn pick_up<T: Copy>(overridden: &mut Option<T>, moved: bool, value: T) -> T {
+    if moved {
+        *overridden = None;
+    }
+
+    overridden.unwrap_or(value)
+}
@@ -23,4 +23,5 @@
 #![forbid(unsafe_code)]
 
 pub mod delay;
-pub mod fake_timer;
+pub mod loopback;
+pub mod monotonic_timer;
@@ -0,0 +1,185 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! An in-memory serial loopback.
+//!
+//! A [`Loopback`] owns the buffers shared by its two ends: the
+//! [`DeviceEnd`] implements the `embedded-hal` serial traits, so it can be
+//! wrapped in an ERCP Basic `SerialAdapter` like a real UART, while the
+//! [`TestEnd`] injects the incoming bytes and collects the written ones. This
+//! makes it possible to exercise the ERCP Basic stack without hardware.
+
+use core::{cell::RefCell, convert::Infallible};
+
+use embedded_hal::serial::{Read, Write};
+use heapless::Deque;
+
+/// An in-memory serial loopback, with buffers of `N` bytes.
+pub struct Loopback<const N: usize> {
+    /// The buffers shared by both ends.
+    buffers: RefCell<Buffers<N>>,
+}
+
+/// The end of a loopback seen by the device, as a serial port.
+pub struct DeviceEnd<'a, const N: usize> {
+    /// The buffers of the loopback.
+    buffers: &'a RefCell<Buffers<N>>,
+}
+
+/// The end of a loopback driving the device.
+pub struct TestEnd<'a, const N: usize> {
+    /// The buffers of the loopback.
+    buffers: &'a RefCell<Buffers<N>>,
+}
+
+/// An error meaning that a loopback buffer is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BufferFull;
+
+/// The buffers of a loopback.
+struct Buffers<const N: usize> {
+    /// The bytes waiting to be read by the device.
+    rx: Deque<u8, N>,
+    /// The bytes written by the device.
+    tx: Deque<u8, N>,
+}
+
+impl<const N: usize> Loopback<N> {
+    /// Creates a new loopback.
+    pub const fn new() -> Self {
+        Self {
+            buffers: RefCell::new(Buffers {
+                rx: Deque::new(),
+                tx: Deque::new(),
+            }),
+        }
+    }
+
+    /// Splits the loopback into its device and test ends.
+    pub fn split(&mut self) -> (DeviceEnd<'_, N>, TestEnd<'_, N>) {
+        let buffers = &self.buffers;
+        (DeviceEnd { buffers }, TestEnd { buffers })
+    }
+}
+
+impl<const N: usize> Default for Loopback<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> TestEnd<'_, N> {
+    /// Injects bytes to be read by the device.
+    ///
+    /// If the buffer is full, the bytes that do not fit are dropped.
+    pub fn feed(&mut self, bytes: &[u8]) -> Result<(), BufferFull> {
+        let rx = &mut self.buffers.borrow_mut().rx;
+        for byte in bytes {
+            rx.push_back(*byte).map_err(|_| BufferFull)?;
+        }
+
+        Ok(())
+    }
+
+    /// Takes the next byte written by the device, if any.
+    pub fn take(&mut self) -> Option<u8> {
+        self.buffers.borrow_mut().tx.pop_front()
+    }
+
+    /// Returns whether there are bytes waiting to be read by the device.
+    pub fn has_data(&self) -> bool {
+        !self.buffers.borrow().rx.is_empty()
+    }
+}
+
+impl<const N: usize> Read<u8> for DeviceEnd<'_, N> {
+    type Error = Infallible;
+
+    fn read(&mut self) -> nb::Result<u8, Self::Error> {
+        self.buffers
+            .borrow_mut()
+            .rx
+            .pop_front()
+            .ok_or(nb::Error::WouldBlock)
+    }
+}
+
+impl<const N: usize> Write<u8> for DeviceEnd<'_, N> {
+    type Error = BufferFull;
+
+    fn write(&mut self, byte: u8) -> nb::Result<(), Self::Error> {
+        // Nothing drains the buffer while the device is writing: waiting for
+        // space would block forever.
+        self.buffers
+            .borrow_mut()
+            .tx
+            .push_back(byte)
+            .map_err(|_| nb::Error::Other(BufferFull))
+    }
+
+    fn flush(&mut self) -> nb::Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fed_bytes_are_read_by_the_device() {
+        let mut loopback = Loopback::<4>::new();
+        let (mut device, mut test) = loopback.split();
+
+        test.feed(&[1, 2]).unwrap();
+        assert!(test.has_data());
+
+        assert_eq!(device.read(), Ok(1));
+        assert_eq!(device.read(), Ok(2));
+        assert_eq!(device.read(), Err(nb::Error::WouldBlock));
+        assert!(!test.has_data());
+    }
+
+    #[test]
+    fn written_bytes_are_taken_by_the_test() {
+        let mut loopback = Loopback::<4>::new();
+        let (mut device, mut test) = loopback.split();
+
+        device.write(1).unwrap();
+        device.write(2).unwrap();
+
+        assert_eq!(test.take(), Some(1));
+        assert_eq!(test.take(), Some(2));
+        assert_eq!(test.take(), None);
+    }
+
+    #[test]
+    fn feeding_a_full_buffer_is_an_error() {
+        let mut loopback = Loopback::<2>::new();
+        let (_, mut test) = loopback.split();
+
+        assert_eq!(test.feed(&[1, 2, 3]), Err(BufferFull));
+    }
+
+    #[test]
+    fn writing_to_a_full_buffer_is_an_error() {
+        let mut loopback = Loopback::<2>::new();
+        let (mut device, _) = loopback.split();
+
+        device.write(1).unwrap();
+        device.write(2).unwrap();
+        assert_eq!(device.write(3), Err(nb::Error::Other(BufferFull)));
+    }
+}
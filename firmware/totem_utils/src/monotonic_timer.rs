@@ -0,0 +1,98 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! A timer for ERCP Basic backed by an RTIC monotonic.
+
+use core::ops::Sub;
+
+/// A timer for ERCP Basic backed by an RTIC monotonic.
+///
+/// RTIC only gives access to the monotonics through the `monotonics` module
+/// generated in the application, so the timer reads the time through a
+/// function, typically `monotonics::now`. With a `Systick` monotonic, instants
+/// and durations are then the `fugit` types of the monotonic, which lets ERCP
+/// Basic time out partial frames and resynchronise its parser.
+pub struct MonotonicTimer<Instant> {
+    /// The function returning the current instant.
+    now: fn() -> Instant,
+}
+
+impl<Instant> MonotonicTimer<Instant> {
+    /// Creates a new timer reading the time from `now`.
+    pub fn new(now: fn() -> Instant) -> Self {
+        Self { now }
+    }
+}
+
+impl<Instant, Duration> ercp_basic::Timer for MonotonicTimer<Instant>
+where
+    Instant: Copy + Sub<Output = Duration>,
+    Duration: PartialOrd,
+{
+    type Instant = Instant;
+    type Duration = Duration;
+
+    fn now(&mut self) -> Self::Instant {
+        (self.now)()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use core::cell::Cell;
+
+    use ercp_basic::Timer as _;
+
+    use super::*;
+
+    /// The time after which ERCP Basic drops a partial frame, in ticks.
+    const TIMEOUT: u32 = 10;
+
+    std::thread_local! {
+        /// The time of the fake monotonic, in ticks.
+        static CLOCK: Cell<u32> = const { Cell::new(0) };
+    }
+
+    fn now() -> u32 {
+        CLOCK.with(Cell::get)
+    }
+
+    fn advance(ticks: u32) {
+        CLOCK.with(|clock| clock.set(clock.get() + ticks));
+    }
+
+    #[test]
+    fn now_follows_the_monotonic() {
+        let mut timer = MonotonicTimer::new(now);
+        assert_eq!(timer.now(), 0);
+
+        advance(42);
+        assert_eq!(timer.now(), 42);
+    }
+
+    #[test]
+    fn a_partial_frame_times_out_once_the_monotonic_advances() {
+        let mut timer = MonotonicTimer::new(now);
+        let start = timer.now();
+
+        advance(TIMEOUT);
+        assert!(timer.now() - start <= TIMEOUT);
+
+        advance(1);
+        assert!(timer.now() - start > TIMEOUT);
+    }
+}
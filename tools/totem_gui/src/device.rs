@@ -17,22 +17,561 @@
 
 use std::time::Duration;
 
+use ercp_basic::command::{nack_reason, ACK, NACK};
 use ercp_device::{CustomCommandError, Device};
-use totem_ui::{graphical::UI_UPDATE, state::UIState};
+use totem_protocol::{
+    auth,
+    batch::BatchItem,
+    cue::Cue,
+    host_link::{HostLinkStatus, HostWatchdogConfig},
+    identity::{DeviceId, Identity},
+    inputs::InputReadings,
+    log::LogLevel,
+    preset::Presets,
+    reset::{ResetCause, ResetReport},
+    self_test::SelfTestReport,
+    show::{ShowEvent, ShowStatus},
+    state::{
+        Brightness, Mode, ScreenState, Speed, Temperature, UIState,
+        UIStatePatch,
+    },
+    telemetry::{ErrorCounters, FrameTiming, Telemetry},
+    DecodeError, Request, Response,
+};
+
+/// The size of the chunks when uploading a show.
+const SHOW_CHUNK_SIZE: usize = 128;
 
 /// The timeout when communication with the Totem.
 pub const TIMEOUT: Option<Duration> = Some(Duration::from_millis(100));
 
+/// The prefixes of the serial ports where to look for Totems.
+const PORT_PREFIXES: [&str; 2] = ["ttyACM", "ttyUSB"];
+
+/// An error that can occur when querying the Totem.
+#[derive(Debug)]
+pub enum QueryError {
+    /// The command has failed.
+    Command(CustomCommandError),
+    /// The Totem has rejected the command.
+    Nack(u8),
+    /// The Totem has sent an unexpected reply.
+    UnexpectedReply(u8),
+    /// The reply cannot be decoded.
+    Decode(DecodeError),
+}
+
+/// A log record received from the Totem.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Log {
+    /// The time elapsed since boot when the record was logged, in
+    /// milliseconds.
+    pub timestamp_ms: u32,
+    /// The level of the record.
+    pub level: LogLevel,
+    /// The number of records dropped before this one.
+    pub dropped: u32,
+    /// The log message.
+    pub message: String,
+}
+
+/// The identity of a Totem.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceIdentity {
+    /// The unique ID.
+    pub id: DeviceId,
+    /// The name, empty if the Totem has not been named.
+    pub name: String,
+}
+
+/// The last reset of a Totem.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LastReset {
+    /// The cause of the reset.
+    pub cause: ResetCause,
+    /// The number of resets since power-on.
+    pub resets: u32,
+    /// The panic which has caused the reset, if any.
+    pub panic: Option<Panic>,
+}
+
+/// A panic which has caused a reset of a Totem.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Panic {
+    /// The panic message, possibly truncated.
+    pub message: String,
+    /// The source file where the panic has occurred, possibly truncated.
+    pub file: String,
+    /// The line where the panic has occurred.
+    pub line: u32,
+    /// The column where the panic has occurred.
+    pub column: u32,
+}
+
+/// A Totem found on a serial port.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Discovered {
+    /// The serial port.
+    pub port: String,
+    /// The identity of the Totem.
+    pub identity: DeviceIdentity,
+}
+
 /// ERCP device extention for Totem.
 pub trait DeviceExt {
     /// Updates the UI.
-    fn ui_update(&mut self, state: &UIState) -> Result<(), CustomCommandError>;
+    fn ui_update(&mut self, state: &UIState) -> Result<(), QueryError>;
+
+    /// Partially updates the UI.
+    ///
+    /// Only the fields set in the patch are updated on the Totem.
+    fn ui_patch(&mut self, patch: &UIStatePatch) -> Result<(), QueryError>;
+
+    /// Sets the mode.
+    fn set_mode(&mut self, mode: Mode) -> Result<(), QueryError>;
+
+    /// Sets the brightness.
+    fn set_brightness(
+        &mut self,
+        brightness: Brightness,
+    ) -> Result<(), QueryError>;
+
+    /// Sets the speed.
+    fn set_speed(&mut self, speed: Speed) -> Result<(), QueryError>;
+
+    /// Sets the color temperature.
+    fn set_temperature(
+        &mut self,
+        temperature: Temperature,
+    ) -> Result<(), QueryError>;
+
+    /// Sets the screen state.
+    fn set_screen_state(
+        &mut self,
+        screen_state: ScreenState,
+    ) -> Result<(), QueryError>;
+
+    /// Applies several UI state changes atomically.
+    ///
+    /// Returns the status of each item: [`totem_protocol::batch::OK`] if it is
+    /// valid, or the NACK reason it has been rejected for. The changes are
+    /// applied only if all items are valid.
+    fn batch(&mut self, items: &[BatchItem]) -> Result<Vec<u8>, QueryError>;
+
+    /// Configures the host link watchdog.
+    fn set_host_watchdog(
+        &mut self,
+        config: &HostWatchdogConfig,
+    ) -> Result<(), QueryError>;
+
+    /// Fires a cue over the running mode.
+    fn fire_cue(&mut self, cue: &Cue) -> Result<(), QueryError>;
+
+    /// Uploads a show, replacing the previous one.
+    fn upload_show(&mut self, events: &[ShowEvent]) -> Result<(), QueryError>;
+
+    /// Starts the uploaded show from the beginning.
+    fn start_show(&mut self) -> Result<(), QueryError>;
+
+    /// Plays the uploaded show from a position, in milliseconds.
+    fn seek_show(&mut self, position_ms: u32) -> Result<(), QueryError>;
+
+    /// Stops the show.
+    fn stop_show(&mut self) -> Result<(), QueryError>;
+
+    /// Gets the playback status of the show.
+    fn show_status(&mut self) -> Result<ShowStatus, QueryError>;
+
+    /// Saves the current UI state of the Totem in a preset slot.
+    fn save_preset(&mut self, slot: u8) -> Result<(), QueryError>;
+
+    /// Recalls the UI state saved in a preset slot.
+    fn recall_preset(&mut self, slot: u8) -> Result<(), QueryError>;
+
+    /// Gets the presets saved on the Totem.
+    fn presets(&mut self) -> Result<Presets, QueryError>;
+
+    /// Gets the identity of the Totem.
+    fn identity(&mut self) -> Result<DeviceIdentity, QueryError>;
+
+    /// Sets the name of the Totem.
+    ///
+    /// An empty name clears it.
+    fn set_name(&mut self, name: &str) -> Result<(), QueryError>;
+
+    /// Gets the protocol version implemented by the Totem.
+    fn protocol_version(&mut self) -> Result<u8, QueryError>;
+
+    /// Gets the telemetry.
+    fn telemetry(&mut self) -> Result<Telemetry, QueryError>;
+
+    /// Gets the state of the link with the host.
+    fn host_link_status(&mut self) -> Result<HostLinkStatus, QueryError>;
+
+    /// Gets the report of the power-on self-test.
+    ///
+    /// Returns `None` if the Totem has been built without its self-test.
+    fn self_test(&mut self) -> Result<Option<SelfTestReport>, QueryError>;
+
+    /// Gets the report of the last reset.
+    fn last_reset(&mut self) -> Result<LastReset, QueryError>;
+
+    /// Gets the counters of the peripheral and task errors.
+    fn error_counters(&mut self) -> Result<ErrorCounters, QueryError>;
+
+    /// Gets the timing of the LED frames.
+    fn frame_timing(&mut self) -> Result<FrameTiming, QueryError>;
+
+    /// Gets the raw readings of the board inputs.
+    ///
+    /// Returns `None` if the Totem does not use its physical UI.
+    fn inputs(&mut self) -> Result<Option<InputReadings>, QueryError>;
+
+    /// Gets the oldest log record buffered on the Totem, if any.
+    fn drain_log(&mut self) -> Result<Option<Log>, QueryError>;
+
+    /// Authenticates with the shared secret.
+    fn authenticate(&mut self, secret: &[u8]) -> Result<(), QueryError>;
+
+    /// Closes the authenticated session.
+    fn logout(&mut self) -> Result<(), QueryError>;
+
+    /// Sets the shared secret.
+    ///
+    /// An empty secret disables authentication. The session is closed in any
+    /// case.
+    fn set_secret(&mut self, secret: &[u8]) -> Result<(), QueryError>;
 }
 
 impl DeviceExt for Device {
-    fn ui_update(&mut self, state: &UIState) -> Result<(), CustomCommandError> {
-        let value = postcard::to_allocvec(state).unwrap();
-        self.command(UI_UPDATE, &value, TIMEOUT)?;
+    fn ui_update(&mut self, state: &UIState) -> Result<(), QueryError> {
+        execute(self, Request::UIUpdate(*state))
+    }
+
+    fn ui_patch(&mut self, patch: &UIStatePatch) -> Result<(), QueryError> {
+        execute(self, Request::UIPatch(*patch))
+    }
+
+    fn set_mode(&mut self, mode: Mode) -> Result<(), QueryError> {
+        execute(self, Request::SetMode(mode))
+    }
+
+    fn set_brightness(
+        &mut self,
+        brightness: Brightness,
+    ) -> Result<(), QueryError> {
+        execute(self, Request::SetBrightness(brightness))
+    }
+
+    fn set_speed(&mut self, speed: Speed) -> Result<(), QueryError> {
+        execute(self, Request::SetSpeed(speed))
+    }
+
+    fn set_temperature(
+        &mut self,
+        temperature: Temperature,
+    ) -> Result<(), QueryError> {
+        execute(self, Request::SetTemperature(temperature))
+    }
+
+    fn set_screen_state(
+        &mut self,
+        screen_state: ScreenState,
+    ) -> Result<(), QueryError> {
+        execute(self, Request::SetScreenState(screen_state))
+    }
+
+    fn batch(&mut self, items: &[BatchItem]) -> Result<Vec<u8>, QueryError> {
+        let mut batch = Vec::new();
+        let mut buffer = [0; REQUEST_BUFFER_SIZE];
+        for item in items {
+            batch.extend_from_slice(item.encode(&mut buffer).unwrap());
+        }
+
+        query(self, Request::Batch(&batch), |response| match response {
+            Response::Batch(statuses) => Some(statuses.to_vec()),
+            _ => None,
+        })
+    }
+
+    fn set_host_watchdog(
+        &mut self,
+        config: &HostWatchdogConfig,
+    ) -> Result<(), QueryError> {
+        execute(self, Request::SetHostWatchdog(*config))
+    }
+
+    fn fire_cue(&mut self, cue: &Cue) -> Result<(), QueryError> {
+        execute(self, Request::Cue(*cue))
+    }
+
+    fn upload_show(&mut self, events: &[ShowEvent]) -> Result<(), QueryError> {
+        let mut show = Vec::new();
+        let mut buffer = [0; REQUEST_BUFFER_SIZE];
+        for event in events {
+            show.extend_from_slice(event.encode(&mut buffer).unwrap());
+        }
+
+        execute(self, Request::ShowClear)?;
+        for chunk in show.chunks(SHOW_CHUNK_SIZE) {
+            execute(self, Request::ShowUpload(chunk))?;
+        }
+
         Ok(())
     }
+
+    fn start_show(&mut self) -> Result<(), QueryError> {
+        execute(self, Request::ShowStart)
+    }
+
+    fn seek_show(&mut self, position_ms: u32) -> Result<(), QueryError> {
+        execute(self, Request::ShowSeek(position_ms))
+    }
+
+    fn stop_show(&mut self) -> Result<(), QueryError> {
+        execute(self, Request::ShowStop)
+    }
+
+    fn show_status(&mut self) -> Result<ShowStatus, QueryError> {
+        query(self, Request::ShowStatus, |response| match response {
+            Response::ShowStatus(status) => Some(status),
+            _ => None,
+        })
+    }
+
+    fn save_preset(&mut self, slot: u8) -> Result<(), QueryError> {
+        execute(self, Request::PresetSave(slot))
+    }
+
+    fn recall_preset(&mut self, slot: u8) -> Result<(), QueryError> {
+        execute(self, Request::PresetRecall(slot))
+    }
+
+    fn presets(&mut self) -> Result<Presets, QueryError> {
+        query(self, Request::Presets, |response| match response {
+            Response::Presets(presets) => Some(presets),
+            _ => None,
+        })
+    }
+
+    fn identity(&mut self) -> Result<DeviceIdentity, QueryError> {
+        query(self, Request::Identity, |response| match response {
+            Response::Identity(Identity { id, name }) => Some(DeviceIdentity {
+                id,
+                name: String::from(name),
+            }),
+            _ => None,
+        })
+    }
+
+    fn set_name(&mut self, name: &str) -> Result<(), QueryError> {
+        execute(self, Request::SetName(name))
+    }
+
+    fn protocol_version(&mut self) -> Result<u8, QueryError> {
+        query(self, Request::ProtocolVersion, |response| match response {
+            Response::ProtocolVersion(version) => Some(version),
+            _ => None,
+        })
+    }
+
+    fn telemetry(&mut self) -> Result<Telemetry, QueryError> {
+        query(self, Request::Telemetry, |response| match response {
+            Response::Telemetry(telemetry) => Some(telemetry),
+            _ => None,
+        })
+    }
+
+    fn host_link_status(&mut self) -> Result<HostLinkStatus, QueryError> {
+        query(self, Request::HostLinkStatus, |response| match response {
+            Response::HostLinkStatus(status) => Some(status),
+            _ => None,
+        })
+    }
+
+    fn self_test(&mut self) -> Result<Option<SelfTestReport>, QueryError> {
+        query(self, Request::SelfTest, |response| match response {
+            Response::SelfTest(report) => Some(report),
+            _ => None,
+        })
+    }
+
+    fn last_reset(&mut self) -> Result<LastReset, QueryError> {
+        query(self, Request::ResetReport, |response| match response {
+            Response::ResetReport(ResetReport {
+                cause,
+                resets,
+                panic,
+            }) => Some(LastReset {
+                cause,
+                resets,
+                panic: panic.map(|panic| Panic {
+                    message: String::from(panic.message),
+                    file: String::from(panic.file),
+                    line: panic.line,
+                    column: panic.column,
+                }),
+            }),
+            _ => None,
+        })
+    }
+
+    fn error_counters(&mut self) -> Result<ErrorCounters, QueryError> {
+        query(self, Request::ErrorCounters, |response| match response {
+            Response::ErrorCounters(counters) => Some(counters),
+            _ => None,
+        })
+    }
+
+    fn frame_timing(&mut self) -> Result<FrameTiming, QueryError> {
+        query(self, Request::FrameTiming, |response| match response {
+            Response::FrameTiming(timing) => Some(timing),
+            _ => None,
+        })
+    }
+
+    fn inputs(&mut self) -> Result<Option<InputReadings>, QueryError> {
+        query(self, Request::Inputs, |response| match response {
+            Response::Inputs(inputs) => Some(inputs),
+            _ => None,
+        })
+    }
+
+    fn drain_log(&mut self) -> Result<Option<Log>, QueryError> {
+        query(self, Request::LogDrain, |response| match response {
+            Response::LogRecord(record) => Some(record.map(|record| Log {
+                timestamp_ms: record.timestamp_ms,
+                level: record.level,
+                dropped: record.dropped,
+                message: String::from(record.message),
+            })),
+            _ => None,
+        })
+    }
+
+    fn authenticate(&mut self, secret: &[u8]) -> Result<(), QueryError> {
+        let nonce =
+            query(self, Request::AuthChallenge, |response| match response {
+                Response::AuthChallenge(nonce) => Some(nonce),
+                _ => None,
+            })?;
+
+        let response = auth::response(secret, &nonce);
+        execute(self, Request::AuthResponse(&response))
+    }
+
+    fn logout(&mut self) -> Result<(), QueryError> {
+        execute(self, Request::AuthLogout)
+    }
+
+    fn set_secret(&mut self, secret: &[u8]) -> Result<(), QueryError> {
+        execute(self, Request::AuthSetSecret(secret))
+    }
+}
+
+impl std::fmt::Display for DeviceIdentity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.name.is_empty() {
+            write!(f, "Totem {}", self.id)
+        } else {
+            write!(f, "{} ({})", self.name, self.id)
+        }
+    }
+}
+
+impl From<CustomCommandError> for QueryError {
+    fn from(error: CustomCommandError) -> Self {
+        Self::Command(error)
+    }
+}
+
+impl std::fmt::Display for QueryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Command(error) => write!(f, "{}", error),
+            Self::Nack(reason) => match describe_nack(*reason) {
+                Some(description) => {
+                    write!(f, "command rejected: {}", description)
+                }
+                None => {
+                    write!(f, "command rejected (reason = {:#04x})", reason)
+                }
+            },
+            Self::UnexpectedReply(code) => {
+                write!(f, "unexpected reply (code = {:#04x})", code)
+            }
+            Self::Decode(error) => write!(f, "invalid reply: {:?}", error),
+        }
+    }
+}
+
+/// Looks for Totems on the serial ports.
+///
+/// Each USB serial port is opened and asked for the identity of the Totem
+/// behind it. Ports where no Totem replies are skipped.
+pub fn discover() -> Vec<Discovered> {
+    let mut ports: Vec<String> = std::fs::read_dir("/dev")
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| {
+            PORT_PREFIXES.iter().any(|prefix| name.starts_with(prefix))
+        })
+        .map(|name| format!("/dev/{}", name))
+        .collect();
+
+    ports.sort();
+
+    ports
+        .into_iter()
+        .filter_map(|port| {
+            let identity = Device::new(&port).ok()?.identity().ok()?;
+            Some(Discovered { port, identity })
+        })
+        .collect()
+}
+
+/// Describes a NACK reason sent by the Totem.
+fn describe_nack(reason: u8) -> Option<&'static str> {
+    match reason {
+        nack_reason::UNKNOWN_COMMAND => Some("unknown command"),
+        nack_reason::INVALID_ARGUMENTS => Some("invalid arguments"),
+        reason => totem_protocol::nack_reason::description(reason),
+    }
+}
+
+/// The size of the buffer for request values.
+const REQUEST_BUFFER_SIZE: usize = 255;
+
+/// Sends a request and checks that the reply is an ACK.
+fn execute(device: &mut Device, request: Request) -> Result<(), QueryError> {
+    let mut buffer = [0; REQUEST_BUFFER_SIZE];
+    let value = request.encode(&mut buffer).unwrap();
+    let reply = device.command(request.code(), value, TIMEOUT)?;
+
+    match (reply.code(), reply.value()) {
+        (ACK, _) => Ok(()),
+        (NACK, [reason, ..]) => Err(QueryError::Nack(*reason)),
+        (code, _) => Err(QueryError::UnexpectedReply(code)),
+    }
+}
+
+/// Sends a request and extracts the expected data from its response.
+fn query<T>(
+    device: &mut Device,
+    request: Request,
+    extract: impl FnOnce(Response) -> Option<T>,
+) -> Result<T, QueryError> {
+    let mut buffer = [0; REQUEST_BUFFER_SIZE];
+    let value = request.encode(&mut buffer).unwrap();
+    let reply = device.command(request.code(), value, TIMEOUT)?;
+
+    if let (NACK, [reason, ..]) = (reply.code(), reply.value()) {
+        return Err(QueryError::Nack(*reason));
+    }
+
+    let response = Response::decode(reply.code(), reply.value())
+        .map_err(QueryError::Decode)?;
+    extract(response).ok_or(QueryError::UnexpectedReply(reply.code()))
 }
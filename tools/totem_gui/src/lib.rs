@@ -22,3 +22,5 @@
 #![forbid(unsafe_code)]
 
 pub mod device;
+pub mod plot;
+pub mod show;
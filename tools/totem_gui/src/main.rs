@@ -16,7 +16,14 @@
 #![deny(unused_must_use)]
 #![forbid(unsafe_code)]
 
+use std::{
+    cell::RefCell,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
 use gtk::{
+    glib,
     prelude::*,
     Orientation::{Horizontal, Vertical},
 };
@@ -24,39 +31,163 @@ use relm4::{send, AppUpdate, Model, RelmApp, Sender, WidgetPlus, Widgets};
 
 use embedded_time::duration::Milliseconds;
 use ercp_device::Device;
-use totem_ui::state::{
-    Brightness, Mode, ScreenState, Speed, Temperature, UIState,
+use totem_protocol::{
+    cue::{Color, Cue, CueEffect},
+    host_link::{HostLinkStatus, HostWatchdogConfig},
+    identity::MAX_NAME_LEN,
+    nack_reason::BUSY,
+    preset::PRESET_COUNT,
+    self_test::{SelfTestReport, POTENTIOMETER_NAMES},
+    state::{
+        Brightness, Mode, ScreenState, Speed, Temperature, UIState,
+        UIStatePatch,
+    },
+    telemetry::{ErrorCounters, FrameTiming, Telemetry},
 };
 
-use totem_gui::device::{DeviceExt, TIMEOUT};
+use totem_gui::{
+    device::{
+        self, DeviceExt, Discovered, LastReset, Log, QueryError, TIMEOUT,
+    },
+    plot::{self, InputHistory},
+    show,
+};
+
+/// The maximum number of log records to fetch at once.
+const MAX_LOGS_PER_FETCH: usize = 64;
+
+/// The period between two UI updates sent to the Totem.
+///
+/// Changes made in between are merged in a single update.
+const UI_UPDATE_PERIOD: Duration = Duration::from_millis(50);
+
+/// The time without any command after which the Totem reverts to its failsafe
+/// state, when enabled.
+const HOST_WATCHDOG_TIMEOUT_MS: u32 = 2000;
+
+/// The maximum period between two commands sent to the Totem when its failsafe
+/// is enabled.
+const HEARTBEAT_PERIOD: Duration = Duration::from_millis(500);
+
+/// The brightness of the failsafe state.
+const FAILSAFE_BRIGHTNESS: u8 = 16;
+
+/// The cues fired from the GUI, with their labels.
+const CUES: [(&str, Cue); 3] = [
+    (
+        "Flash",
+        Cue {
+            effect: CueEffect::Flash,
+            duration_ms: 300,
+        },
+    ),
+    (
+        "Burst",
+        Cue {
+            effect: CueEffect::Burst(Color {
+                r: 255,
+                g: 0,
+                b: 64,
+            }),
+            duration_ms: 800,
+        },
+    ),
+    (
+        "Blackout",
+        Cue {
+            effect: CueEffect::Blackout,
+            duration_ms: 1000,
+        },
+    ),
+];
+
+/// The period between two samples of the inputs when plotting them live.
+const INPUTS_SAMPLING_PERIOD: Duration = Duration::from_millis(100);
 
 struct AppModel {
     port: String,
+    devices: Vec<Discovered>,
+    devices_changed: bool,
     device: Option<Device>,
     connection_status: String,
+    name: String,
+    name_status: String,
     ping_status: String,
+    secret: String,
+    auth_status: String,
     ui_state: UIState,
+    pending_patch: UIStatePatch,
+    failsafe: bool,
+    failsafe_status: String,
+    show_path: String,
+    show_status: String,
+    save_preset: bool,
+    preset_status: String,
+    last_command: Instant,
+    diagnostics: String,
+    logs: String,
+    live_inputs: bool,
+    input_history: Rc<RefCell<InputHistory>>,
+    inputs: String,
 }
 
 enum AppMsg {
+    ScanDevices,
     UpdatePort(String),
+    UpdateName(String),
+    SetName,
     UpdateMode(Mode),
     UpdateBrightness(Brightness),
     UpdateSpeed(Speed),
     UpdateTemperature(Temperature),
     UpdateScreenState(ScreenState),
+    UpdateSecret(String),
+    SendUIUpdate,
+    ToggleFailsafe(bool),
+    FireCue(Cue),
+    ToggleSavePreset(bool),
+    SelectPreset(u8),
+    UpdateShowPath(String),
+    UploadShow,
+    StartShow,
+    StopShow,
     Connect,
     Ping,
+    Authenticate,
+    Logout,
+    RefreshDiagnostics,
+    FetchLogs,
+    ToggleLiveInputs(bool),
+    SampleInputs,
 }
 
 impl Default for AppModel {
     fn default() -> Self {
         Self {
             port: String::from("/dev/ttyACM0"),
+            devices: Vec::new(),
+            devices_changed: false,
             device: None,
             connection_status: String::from("Disconnected."),
+            name: String::new(),
+            name_status: String::from("Name not changed."),
             ping_status: String::from("Not yet."),
+            secret: String::new(),
+            auth_status: String::from("Not authenticated."),
             ui_state: UIState::default(),
+            pending_patch: UIStatePatch::default(),
+            failsafe: false,
+            failsafe_status: String::from("Failsafe disabled."),
+            show_path: String::new(),
+            show_status: String::from("No show uploaded."),
+            save_preset: false,
+            preset_status: String::from("Click a preset to recall it."),
+            last_command: Instant::now(),
+            diagnostics: String::from("No diagnostics yet."),
+            logs: String::new(),
+            live_inputs: false,
+            input_history: Rc::new(RefCell::new(InputHistory::new())),
+            inputs: String::from("No inputs yet."),
         }
     }
 }
@@ -74,57 +205,213 @@ impl AppUpdate for AppModel {
         _components: &(),
         _sender: Sender<AppMsg>,
     ) -> bool {
+        // The device picker is refreshed only after a scan.
+        self.devices_changed = false;
+
         match msg {
             AppMsg::UpdatePort(port) => self.port = port,
+            AppMsg::UpdateName(name) => self.name = name,
+            AppMsg::UpdateSecret(secret) => self.secret = secret,
+
+            AppMsg::ScanDevices => {
+                // The connected Totem would not reply on a second handle to
+                // its port.
+                self.device = None;
+                self.devices = device::discover();
+                self.devices_changed = true;
+                self.connection_status =
+                    format!("Found {} Totems.", self.devices.len());
+            }
 
             AppMsg::UpdateMode(mode) => {
                 self.ui_state.mode = mode;
-                if let Some(device) = &mut self.device {
-                    device.ui_update(&self.ui_state).ok();
-                }
+                self.pending_patch.mode = Some(mode);
             }
 
             AppMsg::UpdateBrightness(brightness) => {
                 if brightness != self.ui_state.brightness {
                     self.ui_state.brightness = brightness;
-                    if let Some(device) = &mut self.device {
-                        device.ui_update(&self.ui_state).ok();
-                    }
+                    self.pending_patch.brightness = Some(brightness);
                 }
             }
 
             AppMsg::UpdateSpeed(speed) => {
                 if speed != self.ui_state.speed {
                     self.ui_state.speed = speed;
-                    if let Some(device) = &mut self.device {
-                        device.ui_update(&self.ui_state).ok();
-                    }
+                    self.pending_patch.speed = Some(speed);
                 }
             }
 
             AppMsg::UpdateTemperature(temperature) => {
                 if temperature != self.ui_state.temperature {
                     self.ui_state.temperature = temperature;
-                    if let Some(device) = &mut self.device {
-                        device.ui_update(&self.ui_state).ok();
-                    }
+                    self.pending_patch.temperature = Some(temperature);
                 }
             }
 
             AppMsg::UpdateScreenState(screen_state) => {
                 if screen_state != self.ui_state.screen_state {
                     self.ui_state.screen_state = screen_state;
-                    if let Some(device) = &mut self.device {
-                        device.ui_update(&self.ui_state).ok();
+                    self.pending_patch.screen_state = Some(screen_state);
+                }
+            }
+
+            AppMsg::SendUIUpdate => {
+                let device = match &mut self.device {
+                    Some(device) => device,
+                    None => return false,
+                };
+
+                if !self.pending_patch.is_empty() {
+                    let patch = std::mem::take(&mut self.pending_patch);
+                    self.last_command = Instant::now();
+
+                    match device.ui_patch(&patch) {
+                        Ok(()) => (),
+
+                        // Keep the update for the next period if the Totem
+                        // is busy.
+                        Err(QueryError::Nack(BUSY)) => {
+                            self.pending_patch = patch;
+                        }
+
+                        Err(error) => {
+                            self.connection_status =
+                                format!("Error: {}.", error);
+                            return true;
+                        }
+                    }
+                } else if self.failsafe
+                    && self.last_command.elapsed() >= HEARTBEAT_PERIOD
+                {
+                    // Keep the host link watchdog fed.
+                    self.last_command = Instant::now();
+                    device.ping(TIMEOUT).ok();
+                }
+
+                return false;
+            }
+
+            AppMsg::ToggleFailsafe(failsafe) => {
+                if let Some(device) = &mut self.device {
+                    let config = HostWatchdogConfig {
+                        timeout_ms: if failsafe {
+                            HOST_WATCHDOG_TIMEOUT_MS
+                        } else {
+                            0
+                        },
+                        failsafe: UIState {
+                            brightness: Brightness::new(FAILSAFE_BRIGHTNESS),
+                            ..self.ui_state
+                        },
+                    };
+
+                    match device.set_host_watchdog(&config) {
+                        Ok(()) => {
+                            self.failsafe = failsafe;
+                            self.last_command = Instant::now();
+                            self.failsafe_status = String::from(if failsafe {
+                                "Failsafe enabled."
+                            } else {
+                                "Failsafe disabled."
+                            });
+                        }
+
+                        Err(error) => {
+                            self.failsafe_status = format!("Error: {}.", error);
+                        }
                     }
                 }
             }
 
+            AppMsg::FireCue(cue) => {
+                if let Some(device) = &mut self.device {
+                    self.last_command = Instant::now();
+                    if let Err(error) = device.fire_cue(&cue) {
+                        self.connection_status = format!("Error: {}.", error);
+                    }
+                }
+            }
+
+            AppMsg::ToggleSavePreset(save_preset) => {
+                self.save_preset = save_preset;
+                self.preset_status = String::from(if save_preset {
+                    "Click a preset to save the current state in it."
+                } else {
+                    "Click a preset to recall it."
+                });
+            }
+
+            AppMsg::SelectPreset(slot) => {
+                if let Some(device) = &mut self.device {
+                    self.last_command = Instant::now();
+
+                    self.preset_status = if self.save_preset {
+                        match device.save_preset(slot) {
+                            Ok(()) => format!("Preset {} saved.", slot + 1),
+                            Err(error) => format!("Error: {}.", error),
+                        }
+                    } else {
+                        // Drop the changes not sent yet, which would override
+                        // the preset.
+                        self.pending_patch = UIStatePatch::default();
+
+                        match device.recall_preset(slot) {
+                            Ok(()) => format!("Preset {} recalled.", slot + 1),
+                            Err(error) => format!("Error: {}.", error),
+                        }
+                    };
+                }
+            }
+
+            AppMsg::UpdateShowPath(path) => {
+                self.show_path = path;
+            }
+
+            AppMsg::UploadShow => {
+                if let Some(device) = &mut self.device {
+                    self.show_status =
+                        match upload_show(device, &self.show_path) {
+                            Ok(count) => format!("Uploaded {} events.", count),
+                            Err(error) => format!("Error: {}.", error),
+                        };
+                }
+            }
+
+            AppMsg::StartShow => {
+                if let Some(device) = &mut self.device {
+                    self.show_status = match device.start_show() {
+                        Ok(()) => String::from("Playing."),
+                        Err(error) => format!("Error: {}.", error),
+                    };
+                }
+            }
+
+            AppMsg::StopShow => {
+                if let Some(device) = &mut self.device {
+                    self.show_status = match device.stop_show() {
+                        Ok(()) => String::from("Stopped."),
+                        Err(error) => format!("Error: {}.", error),
+                    };
+                }
+            }
+
             AppMsg::Connect => match Device::new(&self.port) {
-                Ok(device) => {
+                Ok(mut device) => {
+                    self.connection_status = match device.identity() {
+                        Ok(identity) => {
+                            format!(
+                                "Connected to {} on {}.",
+                                identity, self.port
+                            )
+                        }
+
+                        // Totems older than protocol version 8 have no
+                        // identity.
+                        Err(_) => format!("Connected to {}.", self.port),
+                    };
+
                     self.device = Some(device);
-                    self.connection_status =
-                        format!("Connected to {}.", self.port);
                 }
 
                 Err(error) => {
@@ -145,6 +432,128 @@ impl AppUpdate for AppModel {
                     }
                 }
             }
+
+            AppMsg::SetName => {
+                if let Some(device) = &mut self.device {
+                    self.name_status = match device.set_name(&self.name) {
+                        Ok(()) => String::from("Name saved."),
+                        Err(error) => format!("Error: {}.", error),
+                    };
+                }
+            }
+
+            AppMsg::Authenticate => {
+                if let Some(device) = &mut self.device {
+                    self.auth_status =
+                        match device.authenticate(self.secret.as_bytes()) {
+                            Ok(()) => String::from("Authenticated."),
+                            Err(error) => format!("Error: {}.", error),
+                        };
+                }
+            }
+
+            AppMsg::Logout => {
+                if let Some(device) = &mut self.device {
+                    self.auth_status = match device.logout() {
+                        Ok(()) => String::from("Not authenticated."),
+                        Err(error) => format!("Error: {}.", error),
+                    };
+                }
+            }
+
+            AppMsg::RefreshDiagnostics => {
+                if let Some(device) = &mut self.device {
+                    let mut diagnostics = match device.telemetry() {
+                        Ok(telemetry) => format_telemetry(&telemetry),
+                        Err(error) => format!("Error: {}.", error),
+                    };
+
+                    diagnostics.push('\n');
+                    diagnostics.push_str(&match device.host_link_status() {
+                        Ok(status) => format_host_link_status(&status),
+                        Err(error) => {
+                            format!("Host link: error: {}.", error)
+                        }
+                    });
+
+                    diagnostics.push('\n');
+                    diagnostics.push_str(&match device.last_reset() {
+                        Ok(reset) => format_last_reset(&reset),
+                        Err(error) => format!("Last reset: error: {}.", error),
+                    });
+
+                    diagnostics.push('\n');
+                    diagnostics.push_str(&match device.self_test() {
+                        Ok(report) => format_self_test(report.as_ref()),
+                        Err(error) => format!("Self-test: error: {}.", error),
+                    });
+
+                    diagnostics.push('\n');
+                    diagnostics.push_str(&match device.error_counters() {
+                        Ok(counters) => format_error_counters(&counters),
+                        Err(error) => format!("Errors: error: {}.", error),
+                    });
+
+                    diagnostics.push('\n');
+                    diagnostics.push_str(&match device.frame_timing() {
+                        Ok(timing) => format_frame_timing(&timing),
+                        Err(error) => {
+                            format!("Frame timing: error: {}.", error)
+                        }
+                    });
+
+                    self.diagnostics = diagnostics;
+                }
+            }
+
+            AppMsg::FetchLogs => {
+                if let Some(device) = &mut self.device {
+                    for _ in 0..MAX_LOGS_PER_FETCH {
+                        match device.drain_log() {
+                            Ok(Some(log)) => {
+                                self.logs.push_str(&format_log(&log))
+                            }
+                            Ok(None) => break,
+                            Err(error) => {
+                                self.logs
+                                    .push_str(&format!("Error: {}.\n", error));
+                                break;
+                            }
+                        }
+                    }
+                }
+            }
+
+            AppMsg::ToggleLiveInputs(live) => {
+                self.live_inputs = live;
+                if live {
+                    self.input_history.borrow_mut().clear();
+                }
+            }
+
+            AppMsg::SampleInputs => {
+                let device = match &mut self.device {
+                    Some(device) if self.live_inputs => device,
+                    _ => return false,
+                };
+
+                match device.inputs() {
+                    Ok(Some(readings)) => {
+                        self.input_history.borrow_mut().push(readings);
+                        self.inputs = plot::format_readings(&readings);
+                    }
+
+                    Ok(None) => {
+                        self.inputs = String::from(
+                            "Inputs are not available with the graphical UI.",
+                        );
+                    }
+
+                    Err(error) => {
+                        self.inputs = format!("Error: {}.", error);
+                    }
+                }
+            }
         }
 
         true
@@ -165,6 +574,27 @@ impl Widgets<AppModel, ()> for AppWidgets {
                 //                         Connection                         //
                 ////////////////////////////////////////////////////////////////
 
+                append = &gtk::Box {
+                    set_orientation: Horizontal,
+                    set_homogeneous: true,
+
+                    append = &gtk::Button {
+                        set_label: "Scan",
+                        connect_clicked(sender) => move |_| {
+                            send!(sender, AppMsg::ScanDevices);
+                        },
+                    },
+
+                    append = &gtk::ComboBoxText {
+                        set_devices: track!(model.devices_changed, &model.devices),
+                        connect_changed(sender) => move |picker| {
+                            if let Some(port) = picker.active_id() {
+                                send!(sender, AppMsg::UpdatePort(port.to_string()));
+                            }
+                        },
+                    },
+                },
+
                 append = &gtk::Entry {
                     set_placeholder_text: Some("/dev/ttyACM0"),
                     connect_changed(sender) => move |entry| {
@@ -205,6 +635,78 @@ impl Widgets<AppModel, ()> for AppWidgets {
                     }
                 },
 
+                append = &gtk::Box {
+                    set_orientation: Horizontal,
+                    set_homogeneous: true,
+
+                    append = &gtk::ToggleButton {
+                        set_label: "Failsafe",
+                        connect_toggled(sender) => move |button| {
+                            send!(sender, AppMsg::ToggleFailsafe(button.is_active()));
+                        },
+                    },
+
+                    append = &gtk::Label {
+                        set_label: watch! { &model.failsafe_status },
+                    }
+                },
+
+                append = &gtk::PasswordEntry {
+                    set_placeholder_text: Some("Secret"),
+                    connect_changed(sender) => move |entry| {
+                        let secret = entry.text().to_string();
+                        send!(sender, AppMsg::UpdateSecret(secret));
+                    }
+                },
+
+                append = &gtk::Box {
+                    set_orientation: Horizontal,
+                    set_homogeneous: true,
+
+                    append = &gtk::Button {
+                        set_label: "Authenticate",
+                        connect_clicked(sender) => move |_| {
+                            send!(sender, AppMsg::Authenticate);
+                        },
+                    },
+
+                    append = &gtk::Button {
+                        set_label: "Logout",
+                        connect_clicked(sender) => move |_| {
+                            send!(sender, AppMsg::Logout);
+                        },
+                    },
+
+                    append = &gtk::Label {
+                        set_label: watch! { &model.auth_status },
+                    }
+                },
+
+                append = &gtk::Box {
+                    set_orientation: Horizontal,
+                    set_homogeneous: true,
+
+                    append = &gtk::Entry {
+                        set_placeholder_text: Some("Name"),
+                        set_max_length: MAX_NAME_LEN as i32,
+                        connect_changed(sender) => move |entry| {
+                            let name = entry.text().to_string();
+                            send!(sender, AppMsg::UpdateName(name));
+                        }
+                    },
+
+                    append = &gtk::Button {
+                        set_label: "Rename",
+                        connect_clicked(sender) => move |_| {
+                            send!(sender, AppMsg::SetName);
+                        },
+                    },
+
+                    append = &gtk::Label {
+                        set_label: watch! { &model.name_status },
+                    }
+                },
+
                 ////////////////////////////////////////////////////////////////
                 //                        LED control                         //
                 ////////////////////////////////////////////////////////////////
@@ -362,9 +864,359 @@ impl Widgets<AppModel, ()> for AppWidgets {
                     },
                 },
 
+                ////////////////////////////////////////////////////////////////
+                //                            Cues                            //
+                ////////////////////////////////////////////////////////////////
+
+                append: cues = &gtk::Box {
+                    set_orientation: Horizontal,
+                    set_homogeneous: true,
+                },
+
+                ////////////////////////////////////////////////////////////////
+                //                          Presets                           //
+                ////////////////////////////////////////////////////////////////
+
+                append = &gtk::Box {
+                    set_orientation: Vertical,
+
+                    append: presets = &gtk::Box {
+                        set_orientation: Horizontal,
+                        set_homogeneous: true,
+                    },
+
+                    append = &gtk::ToggleButton {
+                        set_label: "Save",
+                        connect_toggled(sender) => move |button| {
+                            send!(sender, AppMsg::ToggleSavePreset(button.is_active()));
+                        },
+                    },
+
+                    append = &gtk::Label {
+                        set_label: watch! { &model.preset_status },
+                    },
+                },
+
+                ////////////////////////////////////////////////////////////////
+                //                            Show                            //
+                ////////////////////////////////////////////////////////////////
+
+                append = &gtk::Box {
+                    set_orientation: Vertical,
+
+                    append = &gtk::Entry {
+                        set_placeholder_text: Some("Show file"),
+                        connect_changed(sender) => move |entry| {
+                            let path = entry.text().to_string();
+                            send!(sender, AppMsg::UpdateShowPath(path));
+                        }
+                    },
+
+                    append = &gtk::Box {
+                        set_orientation: Horizontal,
+                        set_homogeneous: true,
+
+                        append = &gtk::Button {
+                            set_label: "Upload",
+                            connect_clicked(sender) => move |_| {
+                                send!(sender, AppMsg::UploadShow);
+                            },
+                        },
+
+                        append = &gtk::Button {
+                            set_label: "Start",
+                            connect_clicked(sender) => move |_| {
+                                send!(sender, AppMsg::StartShow);
+                            },
+                        },
+
+                        append = &gtk::Button {
+                            set_label: "Stop",
+                            connect_clicked(sender) => move |_| {
+                                send!(sender, AppMsg::StopShow);
+                            },
+                        },
+                    },
+
+                    append = &gtk::Label {
+                        set_label: watch! { &model.show_status },
+                    },
+                },
+
+                ////////////////////////////////////////////////////////////////
+                //                        Diagnostics                         //
+                ////////////////////////////////////////////////////////////////
+
+                append = &gtk::Box {
+                    set_orientation: Vertical,
+
+                    append = &gtk::Button {
+                        set_label: "Refresh diagnostics",
+                        connect_clicked(sender) => move |_| {
+                            send!(sender, AppMsg::RefreshDiagnostics);
+                        },
+                    },
+
+                    append = &gtk::Label {
+                        set_xalign: 0.0,
+                        set_label: watch! { &model.diagnostics },
+                    },
+                },
+
+                ////////////////////////////////////////////////////////////////
+                //                            Logs                            //
+                ////////////////////////////////////////////////////////////////
+
+                append = &gtk::Box {
+                    set_orientation: Vertical,
+
+                    append = &gtk::Button {
+                        set_label: "Fetch logs",
+                        connect_clicked(sender) => move |_| {
+                            send!(sender, AppMsg::FetchLogs);
+                        },
+                    },
+
+                    append = &gtk::ScrolledWindow {
+                        set_min_content_height: 150,
+                        set_child = Some(&gtk::Label) {
+                            set_xalign: 0.0,
+                            set_yalign: 0.0,
+                            set_selectable: true,
+                            set_label: watch! { &model.logs },
+                        },
+                    },
+                },
+
+                ////////////////////////////////////////////////////////////////
+                //                           Inputs                           //
+                ////////////////////////////////////////////////////////////////
+
+                append = &gtk::Box {
+                    set_orientation: Vertical,
+
+                    append = &gtk::ToggleButton {
+                        set_label: "Live inputs",
+                        connect_toggled(sender) => move |button| {
+                            send!(sender, AppMsg::ToggleLiveInputs(button.is_active()));
+                        },
+                    },
+
+                    append: input_plot = &gtk::DrawingArea {
+                        set_content_height: 150,
+                        set_hexpand: true,
+                    },
+
+                    append = &gtk::Label {
+                        set_xalign: 0.0,
+                        set_css_classes: &["monospace"],
+                        set_label: watch! { &model.inputs },
+                    },
+                },
+
             },
         }
     }
+
+    fn post_init() {
+        let history = model.input_history.clone();
+        input_plot.set_draw_func(move |_, cr, width, height| {
+            plot::draw(&history.borrow(), cr, width, height).ok();
+        });
+
+        for (label, cue) in CUES {
+            let button = gtk::Button::with_label(label);
+            let cue_sender = sender.clone();
+            button.connect_clicked(move |_| {
+                send!(cue_sender, AppMsg::FireCue(cue));
+            });
+            cues.append(&button);
+        }
+
+        for slot in 0..PRESET_COUNT as u8 {
+            let button = gtk::Button::with_label(&(slot + 1).to_string());
+            let preset_sender = sender.clone();
+            button.connect_clicked(move |_| {
+                send!(preset_sender, AppMsg::SelectPreset(slot));
+            });
+            presets.append(&button);
+        }
+
+        let update_sender = sender.clone();
+        glib::timeout_add_local(UI_UPDATE_PERIOD, move || {
+            send!(update_sender, AppMsg::SendUIUpdate);
+            glib::Continue(true)
+        });
+
+        let timer_sender = sender.clone();
+        glib::timeout_add_local(INPUTS_SAMPLING_PERIOD, move || {
+            send!(timer_sender, AppMsg::SampleInputs);
+            glib::Continue(true)
+        });
+    }
+
+    fn post_view() {
+        self.input_plot.queue_draw();
+    }
+}
+
+trait DevicePicker {
+    fn set_devices(&self, devices: &[Discovered]);
+}
+
+impl DevicePicker for gtk::ComboBoxText {
+    fn set_devices(&self, devices: &[Discovered]) {
+        self.remove_all();
+
+        for device in devices {
+            let label = format!("{} on {}", device.identity, device.port);
+            self.append(Some(&device.port), &label);
+        }
+
+        self.set_active((!devices.is_empty()).then_some(0));
+    }
+}
+
+fn format_log(log: &Log) -> String {
+    let mut line = String::new();
+
+    if log.dropped > 0 {
+        line.push_str(&format!("[{} records dropped]\n", log.dropped));
+    }
+
+    line.push_str(&format!(
+        "{:>10.3} {:<5} {}\n",
+        log.timestamp_ms as f64 / 1000.0,
+        log.level,
+        log.message,
+    ));
+
+    line
+}
+
+fn upload_show(device: &mut Device, path: &str) -> Result<usize, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let events = show::parse(&text).map_err(|e| e.to_string())?;
+    device.upload_show(&events).map_err(|e| e.to_string())?;
+    Ok(events.len())
+}
+
+fn format_telemetry(telemetry: &Telemetry) -> String {
+    let adc_readings = match telemetry.adc_readings {
+        Some(readings) => format!(
+            "mode = {}, brightness = {}, speed = {}, temperature = {}",
+            readings.mode,
+            readings.brightness,
+            readings.speed,
+            readings.temperature,
+        ),
+        None => String::from("n/a"),
+    };
+
+    format!(
+        "Uptime: {} s\n\
+         Frame rate: {} / {} Hz\n\
+         Max LED task time: {} µs\n\
+         Spawn failures: {}\n\
+         ERCP errors: {} rx, {} process\n\
+         Screen: {}\n\
+         ADC: {}",
+        telemetry.uptime_ms / 1000,
+        telemetry.frame_rate,
+        telemetry.target_frame_rate,
+        telemetry.max_led_task_time_us,
+        telemetry.spawn_failures,
+        telemetry.ercp_rx_errors,
+        telemetry.ercp_process_errors,
+        if telemetry.screen_present {
+            "present"
+        } else {
+            "absent"
+        },
+        adc_readings,
+    )
+}
+
+fn format_host_link_status(status: &HostLinkStatus) -> String {
+    format!(
+        "Host link losses: {}{}",
+        status.losses,
+        if status.failsafe_active {
+            " (failsafe active)"
+        } else {
+            ""
+        },
+    )
+}
+
+fn format_last_reset(reset: &LastReset) -> String {
+    let mut text = format!(
+        "Last reset: {} ({} since power-on)",
+        reset.cause, reset.resets
+    );
+
+    if let Some(panic) = &reset.panic {
+        text.push_str(&format!(
+            "\n  Panicked at {}:{}:{}: {}",
+            panic.file, panic.line, panic.column, panic.message,
+        ));
+    }
+
+    text
+}
+
+fn format_error_counters(counters: &ErrorCounters) -> String {
+    format!(
+        "Errors: screen {}, {} LED write errors, {} frames dropped, \
+         {} spawn failures",
+        if counters.screen_failed {
+            "failed"
+        } else {
+            "OK"
+        },
+        counters.led_write_errors,
+        counters.led_frames_dropped,
+        counters.spawn_failures,
+    )
+}
+
+fn format_frame_timing(timing: &FrameTiming) -> String {
+    format!(
+        "Frame timing: {} µs period, jitter {} µs mean, {} µs max, \
+         {} µs peak, {} frames skipped",
+        timing.period_us,
+        timing.mean_jitter_us,
+        timing.max_jitter_us,
+        timing.peak_jitter_us,
+        timing.skipped_frames,
+    )
+}
+
+fn format_self_test(report: Option<&SelfTestReport>) -> String {
+    let report = match report {
+        Some(report) => report,
+        None => return String::from("Self-test: not run"),
+    };
+
+    let mut text = format!(
+        "Self-test: {}, {} LED strips lit",
+        if report.passed() { "passed" } else { "failed" },
+        report.strips_lit,
+    );
+
+    for (name, check) in POTENTIOMETER_NAMES.iter().zip(&report.potentiometers)
+    {
+        text.push_str(&format!(
+            "\n  {}: {:?} (mean = {}, min = {}, max = {})",
+            name,
+            check.status,
+            check.reading.mean,
+            check.reading.min,
+            check.reading.max,
+        ));
+    }
+
+    text
 }
 
 fn main() {
@@ -0,0 +1,157 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Live plot of the raw readings of the Totem inputs.
+
+use std::collections::VecDeque;
+
+use gtk::cairo::{self, Context};
+use totem_protocol::inputs::{AnalogReading, InputReadings};
+
+/// The number of readings kept in the history.
+pub const HISTORY_LEN: usize = 200;
+
+/// The full scale of the 12-bit ADC.
+const ADC_FULL_SCALE: f64 = 4095.0;
+
+/// The names and colours of the plotted analog inputs.
+const CHANNELS: [(&str, (f64, f64, f64)); 7] = [
+    ("R1", (0.89, 0.10, 0.11)),
+    ("R2", (0.22, 0.49, 0.72)),
+    ("R3", (0.30, 0.69, 0.29)),
+    ("R4", (0.60, 0.31, 0.64)),
+    ("S1", (1.00, 0.50, 0.00)),
+    ("S2", (0.65, 0.34, 0.16)),
+    ("Mic", (0.50, 0.50, 0.50)),
+];
+
+/// A history of input readings.
+#[derive(Debug, Default)]
+pub struct InputHistory {
+    readings: VecDeque<InputReadings>,
+}
+
+impl InputHistory {
+    /// Creates an empty history.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Pushes new readings, dropping the oldest ones if the history is full.
+    pub fn push(&mut self, readings: InputReadings) {
+        if self.readings.len() == HISTORY_LEN {
+            self.readings.pop_front();
+        }
+
+        self.readings.push_back(readings);
+    }
+
+    /// Clears the history.
+    pub fn clear(&mut self) {
+        self.readings.clear();
+    }
+
+    /// Returns the last readings, if any.
+    pub fn last(&self) -> Option<&InputReadings> {
+        self.readings.back()
+    }
+}
+
+/// Draws the mean value of each analog input over the history.
+pub fn draw(
+    history: &InputHistory,
+    cr: &Context,
+    width: i32,
+    height: i32,
+) -> Result<(), cairo::Error> {
+    let (width, height) = (width as f64, height as f64);
+
+    cr.set_source_rgb(1.0, 1.0, 1.0);
+    cr.paint()?;
+
+    let step = width / (HISTORY_LEN - 1) as f64;
+    let y = |value: u16| height - value as f64 / ADC_FULL_SCALE * height;
+
+    for (index, (name, (r, g, b))) in CHANNELS.iter().enumerate() {
+        cr.set_source_rgb(*r, *g, *b);
+        cr.set_line_width(1.5);
+
+        for (i, readings) in history.readings.iter().enumerate() {
+            let value = channel(readings, index).mean;
+            if i == 0 {
+                cr.move_to(0.0, y(value));
+            } else {
+                cr.line_to(i as f64 * step, y(value));
+            }
+        }
+
+        cr.stroke()?;
+
+        // Legend.
+        cr.move_to(5.0 + 40.0 * index as f64, 15.0);
+        cr.show_text(name)?;
+    }
+
+    Ok(())
+}
+
+/// Formats the last readings as a table.
+pub fn format_readings(readings: &InputReadings) -> String {
+    let mut text = String::from("Input   raw  mean   min   max\n");
+
+    for (index, (name, _)) in CHANNELS.iter().enumerate() {
+        let AnalogReading {
+            raw,
+            mean,
+            min,
+            max,
+        } = channel(readings, index);
+
+        text.push_str(&format!(
+            "{:<5} {:>5} {:>5} {:>5} {:>5}\n",
+            name, raw, mean, min, max
+        ));
+    }
+
+    text.push_str(&format!(
+        "B1: {}, B2: {}",
+        level(readings.b1),
+        level(readings.b2)
+    ));
+
+    text
+}
+
+/// Returns the reading of the analog input at `index` in [`CHANNELS`].
+fn channel(readings: &InputReadings, index: usize) -> AnalogReading {
+    match index {
+        0 => readings.r1,
+        1 => readings.r2,
+        2 => readings.r3,
+        3 => readings.r4,
+        4 => readings.s1,
+        5 => readings.s2,
+        _ => readings.microphone,
+    }
+}
+
+/// Returns the name of a logic level.
+fn level(high: bool) -> &'static str {
+    if high {
+        "high"
+    } else {
+        "low"
+    }
+}
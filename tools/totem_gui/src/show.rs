@@ -0,0 +1,197 @@
+// Totem - A totem for music festivals, built with love to spread love.
+// Copyright (C) 2022 Jean-Philippe Cugnet <jean-philippe@cugnet.eu>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, version 3 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Text format of the shows.
+//!
+//! A show is written as one event per line, with its time relative to the
+//! start of the show and its action:
+//!
+//! ```text
+//! # Warm up, then go wild on the drop.
+//! 00:00:00 mode unicolor
+//! 00:00:00 brightness 64
+//! 00:25:00 mode rainbow
+//! 00:40:00 cue flash 300
+//! 00:40:00 brightness 255
+//! ```
+//!
+//! Times are written `HH:MM:SS` or `HH:MM:SS.mmm`. The actions are:
+//!
+//! * `mode off|unicolor|rainbow`,
+//! * `brightness <0-255>`,
+//! * `speed <transition time in ms>`,
+//! * `temperature <-85-85>`,
+//! * `screen on|off`,
+//! * `cue flash <duration in ms>`,
+//! * `cue burst <r> <g> <b> <duration in ms>`,
+//! * `cue blackout <duration in ms>`.
+//!
+//! Empty lines and lines starting with `#` are ignored. Events must be in
+//! chronological order.
+
+use std::{fmt, str::FromStr};
+
+use embedded_time::duration::Milliseconds;
+use totem_protocol::{
+    cue::{Color, Cue, CueEffect},
+    show::{ShowAction, ShowEvent},
+    state::{Brightness, Mode, ScreenState, Speed, Temperature, UIStatePatch},
+};
+
+/// An error in a show, with its line number.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// The line of the error, starting at 1.
+    pub line: usize,
+    /// The description of the error.
+    pub message: String,
+}
+
+/// Parses a show.
+pub fn parse(show: &str) -> Result<Vec<ShowEvent>, ParseError> {
+    let mut events: Vec<ShowEvent> = Vec::new();
+
+    for (index, line) in show.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let error = |message: &str| ParseError {
+            line: index + 1,
+            message: String::from(message),
+        };
+
+        let mut words = line.split_whitespace();
+        let at_ms = words
+            .next()
+            .and_then(parse_time)
+            .ok_or_else(|| error("invalid time"))?;
+        let words: Vec<&str> = words.collect();
+        let action = parse_action(&words).map_err(error)?;
+
+        let event = ShowEvent { at_ms, action };
+        if !event.is_valid() {
+            return Err(error("value out of range"));
+        }
+
+        if events.last().is_some_and(|last| last.at_ms > at_ms) {
+            return Err(error("event before the previous one"));
+        }
+
+        events.push(event);
+    }
+
+    Ok(events)
+}
+
+/// Parses a time, as `HH:MM:SS` or `HH:MM:SS.mmm`.
+fn parse_time(time: &str) -> Option<u32> {
+    let (time, millis) = match time.split_once('.') {
+        Some((time, millis)) if millis.len() == 3 => {
+            (time, millis.parse().ok()?)
+        }
+        Some(_) => return None,
+        None => (time, 0),
+    };
+
+    let mut parts = time.split(':').map(|part| part.parse::<u32>().ok());
+    let (hours, minutes, seconds) =
+        match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(Some(h)), Some(Some(m)), Some(Some(s)), None)
+                if m < 60 && s < 60 =>
+            {
+                (h, m, s)
+            }
+            _ => return None,
+        };
+
+    ((hours * 60 + minutes) * 60 + seconds)
+        .checked_mul(1000)?
+        .checked_add(millis)
+}
+
+/// Parses an action.
+fn parse_action(words: &[&str]) -> Result<ShowAction, &'static str> {
+    let patch = |patch| Ok(ShowAction::Patch(patch));
+    let cue = |effect, duration: &str| {
+        Ok(ShowAction::Cue(Cue {
+            effect,
+            duration_ms: parse_number(duration)?,
+        }))
+    };
+
+    match words {
+        ["mode", mode] => patch(UIStatePatch {
+            mode: Some(match *mode {
+                "off" => Mode::Off,
+                "unicolor" => Mode::RandomUnicolor,
+                "rainbow" => Mode::RainbowFontain,
+                _ => return Err("unknown mode"),
+            }),
+            ..Default::default()
+        }),
+
+        ["brightness", value] => patch(UIStatePatch {
+            brightness: Some(Brightness::new(parse_number(value)?)),
+            ..Default::default()
+        }),
+
+        ["speed", value] => patch(UIStatePatch {
+            speed: Some(Speed::new(Milliseconds(parse_number(value)?))),
+            ..Default::default()
+        }),
+
+        ["temperature", value] => patch(UIStatePatch {
+            temperature: Some(Temperature::new(parse_number(value)?)),
+            ..Default::default()
+        }),
+
+        ["screen", state] => patch(UIStatePatch {
+            screen_state: Some(match *state {
+                "on" => ScreenState::On,
+                "off" => ScreenState::Off,
+                _ => return Err("unknown screen state"),
+            }),
+            ..Default::default()
+        }),
+
+        ["cue", "flash", duration] => cue(CueEffect::Flash, duration),
+
+        ["cue", "burst", r, g, b, duration] => cue(
+            CueEffect::Burst(Color {
+                r: parse_number(r)?,
+                g: parse_number(g)?,
+                b: parse_number(b)?,
+            }),
+            duration,
+        ),
+
+        ["cue", "blackout", duration] => cue(CueEffect::Blackout, duration),
+
+        _ => Err("unknown action"),
+    }
+}
+
+/// Parses a number.
+fn parse_number<T: FromStr>(value: &str) -> Result<T, &'static str> {
+    value.parse().map_err(|_| "invalid number")
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}